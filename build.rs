@@ -0,0 +1,25 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Embeds build info for GET /version: GIT_HASH (taken from the environment or `git`) and BUILD_TIMESTAMP (Unix seconds)
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_HASH");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let git_hash = std::env::var("GIT_HASH").ok().filter(|hash| !hash.is_empty()).or_else(|| {
+        let output = Command::new("git").args(["rev-parse", "--short", "HEAD"]).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    });
+    if let Some(hash) = git_hash {
+        println!("cargo:rustc-env=GIT_HASH={}", hash);
+    }
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible
+    let built_at = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs()));
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", built_at);
+}
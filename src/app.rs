@@ -0,0 +1,199 @@
+use axum::{
+    error_handling::HandleErrorLayer,
+    extract::{DefaultBodyLimit, State},
+    routing::{get, post, put, delete},
+    Router,
+    middleware,
+};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tower::{limit::GlobalConcurrencyLimitLayer, ServiceBuilder};
+
+use crate::{
+    handlers::{
+        handlers::{root, method_not_allowed, overloaded},
+        auth_handlers::{register_user, register_and_login, login_user, get_profile, update_profile, delete_profile, verify_email, resend_verification, email_available},
+        post_v2_handlers,
+        post_handlers::{create_post, duplicate_post, get_post, get_post_full, get_random_post, head_post, get_user_posts, get_all_posts, get_post_changes, update_post, publish_post, unpublish_post, delete_post, get_trashed_posts, restore_post, purge_post, batch_delete_posts, upload_post_cover, get_post_cover, get_post_raw, like_post, unlike_post, get_liked_posts, get_feed, mark_post_read, mark_posts_read, get_unread_posts},
+        event_handlers::{posts_ws, posts_sse},
+        admin_handlers::{admin_delete_post, admin_create_user, admin_set_user_role, admin_get_audit_log, admin_get_reports, admin_export_posts, admin_set_log_level},
+        schema_handlers::get_schema,
+        tag_handlers::get_tags,
+        error_handlers::get_errors,
+        comment_handlers::{create_comment, get_comments, get_comment_count},
+        report_handlers::report_post,
+        activity_handlers::get_user_activity,
+        user_handlers::{get_user_by_username, follow_user, unfollow_user},
+        version_handlers::get_version,
+    },
+    helpers::middleware::{auth_middleware, optional_auth_middleware, request_logging_middleware, request_timeout_middleware, client_ip_middleware, body_logging_middleware, json_guard_middleware, query_guard_middleware, header_guard_middleware, fields_middleware, timestamp_format_middleware, error_details_middleware, default_language_middleware, options_middleware, trailing_slash_middleware, cors_layer},
+    model::model::PostFields,
+    state::AppState,
+    config::{Features, Settings},
+};
+
+/// The full application: every route and middleware layer, shared by the server binary and the tests
+pub fn build_app(pool: Arc<SqlitePool>, settings: Settings) -> Router {
+    build_app_with_read_pool(pool.clone(), pool, settings)
+}
+
+// Routes of the optional features that are switched on; the rest aren't mounted at all
+fn feature_routes(features: Features) -> Router<AppState> {
+    let mut routes = Router::new();
+    if features.registration {
+        routes = routes
+            .route("/auth/register", post(register_user))
+            .route("/auth/register-and-login", post(register_and_login))
+            .route("/auth/email-available", get(email_available));
+    }
+    if features.comments {
+        routes = routes
+            .route("/posts/{id}/comments", get(get_comments).post(create_comment))
+            .route("/posts/{id}/comments/count", get(get_comment_count));
+    }
+    if features.likes {
+        routes = routes
+            .route("/posts/liked", get(get_liked_posts).layer(middleware::from_fn_with_state(PostFields::Post, fields_middleware)))
+            .route("/posts/{id}/like", post(like_post).delete(unlike_post));
+    }
+    routes
+}
+
+/// Like `build_app`, but read-only endpoints query `read_pool` (e.g. a replica) instead of the primary
+pub fn build_app_with_read_pool(pool: Arc<SqlitePool>, read_pool: Arc<SqlitePool>, settings: Settings) -> Router {
+    let state = AppState::with_read_pool(pool, read_pool, settings);
+    let settings = state.settings.clone();
+    
+    // CORS setup
+    let cors = cors_layer(&state.settings);
+
+    // Route setup
+    let app = Router::new()
+        .route("/", get(root))
+        
+        // Public routes
+        .route("/auth/login", post(login_user))
+        .route("/auth/verify-email", post(verify_email))
+        .route("/posts", get(get_all_posts).layer(middleware::from_fn_with_state(PostFields::Post, fields_middleware)))
+        .route("/posts/{id}", get(get_post).head(head_post).layer(middleware::from_fn_with_state(PostFields::Post, fields_middleware)))
+        .route("/posts/random", get(get_random_post).layer(middleware::from_fn_with_state(PostFields::Post, fields_middleware)))
+        .route("/posts/{id}/cover", get(get_post_cover))
+        .route("/posts/{id}/raw", get(get_post_raw))
+        .route("/posts/{id}/full", get(get_post_full).layer(middleware::from_fn_with_state(PostFields::Thread, fields_middleware)))
+        .route("/posts/stream", get(posts_sse))
+        .route("/posts/changes", get(get_post_changes))
+        .route("/ws/posts", get(posts_ws))
+        .route("/schema/{type}", get(get_schema))
+        .route("/tags", get(get_tags))
+        .route("/users/{username}", get(get_user_by_username))
+        .route("/users/{id}/activity", get(get_user_activity))
+        .route("/errors", get(get_errors))
+        .route("/version", get(get_version))
+        
+        // Protected routes
+        .route("/auth/profile", get(get_profile))
+        .route("/auth/profile", put(update_profile))
+        .route("/auth/profile", delete(delete_profile))
+        .route("/auth/resend-verification", post(resend_verification))
+        .route("/posts", post(create_post))
+        .route("/posts/my", get(get_user_posts).layer(middleware::from_fn_with_state(PostFields::Post, fields_middleware)))
+        .route("/posts/unread", get(get_unread_posts).layer(middleware::from_fn_with_state(PostFields::Post, fields_middleware)))
+        .route("/posts/read", post(mark_posts_read))
+        .route("/posts/{id}", put(update_post))
+        .route("/posts/{id}", delete(delete_post))
+        .route("/posts/trash", get(get_trashed_posts))
+        .route("/posts/{id}/restore", post(restore_post))
+        .route("/posts/{id}/purge", delete(purge_post))
+        .route("/posts/batch-delete", post(batch_delete_posts))
+        .route("/posts/{id}/duplicate", post(duplicate_post))
+        .route("/posts/{id}/read", post(mark_post_read))
+        .route("/posts/{id}/publish", post(publish_post))
+        .route("/posts/{id}/unpublish", post(unpublish_post))
+        .route("/posts/{id}/report", post(report_post))
+        .route("/users/{id}/follow", post(follow_user).delete(unfollow_user))
+        .route("/me/feed", get(get_feed).layer(middleware::from_fn_with_state(PostFields::Post, fields_middleware)))
+        // Upload size is enforced by the handler against MAX_COVER_BYTES
+        .route("/posts/{id}/cover", post(upload_post_cover).layer(DefaultBodyLimit::disable()))
+        
+        // Admin routes
+        .route("/admin/posts/{id}", delete(admin_delete_post))
+        .route("/admin/users", post(admin_create_user))
+        .route("/admin/users/{id}/role", put(admin_set_user_role))
+        .route("/admin/audit", get(admin_get_audit_log))
+        .route("/admin/reports", get(admin_get_reports))
+        .route("/admin/export/posts.ndjson", get(admin_export_posts))
+        .route("/admin/log-level", put(admin_set_log_level))
+        .merge(feature_routes(settings.features))
+
+        // Versioned reads: /v1 keeps the current post shape, /v2 maps the same rows to PostV2Response
+        .nest("/v1", Router::new()
+            .route("/posts", get(get_all_posts).layer(middleware::from_fn_with_state(PostFields::Post, fields_middleware)))
+            .route("/posts/{id}", get(get_post).head(head_post).layer(middleware::from_fn_with_state(PostFields::Post, fields_middleware))))
+        .nest("/v2", Router::new()
+            .route("/posts", get(post_v2_handlers::get_all_posts).layer(middleware::from_fn_with_state(PostFields::V2, fields_middleware)))
+            .route("/posts/{id}", get(post_v2_handlers::get_post).layer(middleware::from_fn_with_state(PostFields::V2, fields_middleware))))
+        .method_not_allowed_fallback(method_not_allowed)
+        
+        .layer(middleware::from_fn_with_state(state.settings.clone(), timestamp_format_middleware))
+        .layer(middleware::from_fn_with_state(state.settings.clone(), error_details_middleware))
+        .layer(cors)
+        .layer(middleware::from_fn_with_state(state.settings.clone(), body_logging_middleware))
+        .layer(middleware::from_fn_with_state(state.settings.clone(), json_guard_middleware))
+        .layer(middleware::from_fn_with_state(state.settings.clone(), query_guard_middleware))
+        .layer(middleware::from_fn_with_state(state.settings.clone(), header_guard_middleware))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            |State(state): State<AppState>, req: axum::extract::Request, next: axum::middleware::Next| async move {
+                // Auth middleware; OPTIONS only lists a route's methods, so it never needs a token
+                let path = req.uri().path();
+                if req.method() != "OPTIONS" && (path.starts_with("/auth/profile") || 
+                   path.starts_with("/auth/resend-verification") ||
+                   path.starts_with("/admin") ||
+                   path.starts_with("/posts") && req.method() == "POST" ||
+                   path.starts_with("/posts/my") ||
+                   path.starts_with("/posts/liked") ||
+                   path.starts_with("/posts/unread") ||
+                   path.starts_with("/posts/trash") ||
+                   path.starts_with("/me/") ||
+                   (path.starts_with("/users/") && path.ends_with("/follow")) ||
+                   (path.starts_with("/posts/") && (req.method() == "PUT" || req.method() == "DELETE"))) {
+                    auth_middleware(State(state), req, next).await
+                } else {
+                    optional_auth_middleware(State(state), req, next).await
+                }
+            }
+        ))
+        .layer(middleware::from_fn_with_state(state.settings.clone(), request_timeout_middleware))
+        .layer(middleware::from_fn_with_state(state.settings.request_log_level, request_logging_middleware))
+        .layer(middleware::from_fn_with_state(state.settings.clone(), client_ip_middleware))
+        .with_state(state);
+
+    // Layers on the router itself only run once a route has matched, so path normalization wraps it.
+    // So does OPTIONS handling: axum only adds the Allow header on the way out of the router
+    let max_concurrent_requests = settings.max_concurrent_requests;
+    let api_prefix = settings.api_prefix.clone();
+    let app = Router::new()
+        .fallback_service(app)
+        .layer(middleware::from_fn_with_state(settings.clone(), options_middleware))
+        .layer(middleware::from_fn_with_state(settings.clone(), trailing_slash_middleware));
+
+    // Routes and middleware inside see paths with the prefix stripped; anything outside it is a 404
+    let app = match api_prefix {
+        Some(prefix) => Router::new().nest_service(&prefix, app),
+        None => app,
+    };
+
+    // Outside everything else, so a request over the limit is shed before any other work; the permit is held until the response head is ready
+    let app = match max_concurrent_requests {
+        Some(max) => app.layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(overloaded))
+                .load_shed()
+                .layer(GlobalConcurrencyLimitLayer::new(max)),
+        ),
+        None => app,
+    };
+
+    // Outermost, so even the overload response falls back to DEFAULT_LANGUAGE
+    app.layer(middleware::from_fn_with_state(settings, default_language_middleware))
+}
@@ -0,0 +1,535 @@
+use chrono::Duration;
+use std::sync::Arc;
+use crate::helpers::clock::{Clock, SystemClock};
+use crate::helpers::log_level::LogFilter;
+use crate::helpers::messages::Lang;
+use crate::helpers::validation::{parse_domain_list, DISPOSABLE_EMAIL_DOMAINS};
+
+const DEFAULT_DATABASE_URL: &str = "sqlite:./api_rust_one.db";
+const DEFAULT_PORT: u16 = 8081;
+// Only for Settings::default (tests); from_env requires JWT_SECRET
+const DEV_JWT_SECRET: &str = "your-secret-key-change-in-production";
+const DEFAULT_MAX_COVER_BYTES: usize = 2 * 1024 * 1024;
+const DEFAULT_MAX_JSON_DEPTH: usize = 32;
+const DEFAULT_MAX_JSON_FIELDS: usize = 1000;
+const DEFAULT_MAX_QUERY_LENGTH: usize = 2048;
+const DEFAULT_MAX_QUERY_PARAMS: usize = 32;
+const DEFAULT_MAX_HEADER_COUNT: usize = 64;
+const DEFAULT_MAX_HEADER_BYTES: usize = 16 * 1024;
+const DEFAULT_CORS_MAX_AGE_SECONDS: u64 = 600;
+const DEFAULT_CORS_EXPOSE_HEADERS: [&str; 3] = ["x-request-id", "etag", "link"];
+const DEFAULT_JWT_ISSUER: &str = "api-rustone";
+const DEFAULT_JWT_AUDIENCE: &str = "api-rustone";
+pub const DEFAULT_READING_WPM: u32 = 200;
+const DEFAULT_EMAIL_CHECK_RATE_LIMIT: u32 = 10;
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 512;
+const DEFAULT_ACCESS_TOKEN_TTL_MINUTES: i64 = 24 * 60;
+const DEFAULT_VERIFICATION_RESEND_RATE_LIMIT: u32 = 3;
+const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 30_000;
+const DEFAULT_PRUNE_INTERVAL_MINUTES: u64 = 60;
+const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+/// What happens to a user's posts when their account is deleted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PostDeletion {
+    /// Remove the posts along with their covers and tags
+    Hard,
+    /// Keep the post and user rows but mark them deleted so they drop out of every read
+    #[default]
+    Soft,
+}
+
+impl std::str::FromStr for PostDeletion {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "hard" => Ok(PostDeletion::Hard),
+            "soft" => Ok(PostDeletion::Soft),
+            other => Err(format!("Unknown post deletion mode: {}", other)),
+        }
+    }
+}
+
+/// What happens to request paths with a trailing slash, e.g. `/posts/`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TrailingSlash {
+    /// Route `/posts/` as `/posts`
+    #[default]
+    Trim,
+    /// Answer with a 308 redirect to the path without the slash
+    Redirect,
+    /// Leave paths alone; `/posts/` only matches a route declared with the slash
+    Strict,
+}
+
+impl std::str::FromStr for TrailingSlash {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "trim" => Ok(TrailingSlash::Trim),
+            "redirect" => Ok(TrailingSlash::Redirect),
+            "strict" => Ok(TrailingSlash::Strict),
+            other => Err(format!("Unknown trailing slash policy: {}", other)),
+        }
+    }
+}
+
+/// Algorithm used for newly stored password hashes; existing hashes of either kind keep verifying
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PasswordAlgorithm {
+    #[default]
+    Bcrypt,
+    /// Argon2id; bcrypt hashes are upgraded on the user's next successful login
+    Argon2,
+}
+
+impl std::str::FromStr for PasswordAlgorithm {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "bcrypt" => Ok(PasswordAlgorithm::Bcrypt),
+            "argon2" => Ok(PasswordAlgorithm::Argon2),
+            other => Err(format!("Unknown password hasher: {}", other)),
+        }
+    }
+}
+
+/// How `created_at` / `updated_at` are written in responses
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TimestampFormat {
+    /// RFC 3339 strings, e.g. "2024-01-01T12:00:00+00:00"
+    #[default]
+    Rfc3339,
+    /// Integer milliseconds since the Unix epoch
+    EpochMillis,
+}
+
+impl std::str::FromStr for TimestampFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "rfc3339" => Ok(TimestampFormat::Rfc3339),
+            "epoch_millis" => Ok(TimestampFormat::EpochMillis),
+            other => Err(format!("Unknown timestamp format: {}", other)),
+        }
+    }
+}
+
+/// Column a post listing is ordered by
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostSortField {
+    CreatedAt,
+    UpdatedAt,
+    /// Case-insensitive, so "Apple" and "apple" sort together
+    Title,
+}
+
+/// Order of GET /posts: a field, `-` prefixed for descending (e.g. `-created_at`); ties always break by id
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PostSort {
+    pub field: PostSortField,
+    pub descending: bool,
+}
+
+impl Default for PostSort {
+    fn default() -> Self {
+        PostSort { field: PostSortField::CreatedAt, descending: true }
+    }
+}
+
+impl std::str::FromStr for PostSort {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (descending, name) = match value.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, value),
+        };
+        let field = match name.to_ascii_lowercase().as_str() {
+            "created_at" => PostSortField::CreatedAt,
+            "updated_at" => PostSortField::UpdatedAt,
+            "title" => PostSortField::Title,
+            other => return Err(format!("Unknown sort field: {}", other)),
+        };
+        Ok(PostSort { field, descending })
+    }
+}
+
+/// Optional features; a disabled one has its routes left out of the router, so they answer 404
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Features {
+    /// Reading and writing post comments (FEATURE_COMMENTS, default on)
+    pub comments: bool,
+    /// Liking posts and the liked-posts listing (FEATURE_LIKES, default on)
+    pub likes: bool,
+    /// Self-service sign-up and the email availability check (FEATURE_REGISTRATION, default on); admins can still create accounts
+    pub registration: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features { comments: true, likes: true, registration: true }
+    }
+}
+
+// Runtime settings read from the environment at startup and shared through AppState
+#[derive(Clone, Debug)]
+pub struct Settings {
+    /// Primary database (DATABASE_URL, default sqlite:./api_rust_one.db)
+    pub database_url: String,
+    /// Optional read replica for read-only queries (DATABASE_READ_URL)
+    pub database_read_url: Option<String>,
+    /// Port the server listens on (PORT, default 8081)
+    pub port: u16,
+    /// Key signing access and verification tokens (JWT_SECRET, required)
+    pub jwt_secret: String,
+    /// Reject a new post whose title matches one of the author's existing posts (REJECT_DUPLICATE_POST_TITLES)
+    pub reject_duplicate_post_titles: bool,
+    /// Let clients pick a post's slug on creation instead of deriving it from the title (CLIENT_SLUGS)
+    pub client_slugs: bool,
+    /// Sessions idle longer than this are rejected even if the JWT is still valid (IDLE_TIMEOUT_MINUTES, unset = off)
+    pub idle_timeout: Option<Duration>,
+    /// Most live sessions a user may hold; logging in past it revokes their oldest (MAX_SESSIONS_PER_USER, unset = unlimited)
+    pub max_sessions_per_user: Option<u32>,
+    /// Emails that get the admin role when they register (ADMIN_EMAILS, comma-separated)
+    pub admin_emails: Vec<String>,
+    /// Largest accepted post cover upload in bytes (MAX_COVER_BYTES, default 2 MiB)
+    pub max_cover_bytes: usize,
+    /// Take the client IP from X-Forwarded-For / X-Real-IP instead of the socket peer (TRUST_PROXY)
+    pub trust_proxy: bool,
+    /// Allow anyone to sign up via POST /auth/register; ADMIN_EMAILS and admin-created accounts are exempt (REGISTRATION_OPEN, default true)
+    pub registration_open: bool,
+    /// Deepest object/array nesting accepted in a JSON request body (MAX_JSON_DEPTH, default 32)
+    pub max_json_depth: usize,
+    /// Most object fields accepted across a whole JSON request body (MAX_JSON_FIELDS, default 1000)
+    pub max_json_fields: usize,
+    /// Longest accepted query string in bytes (MAX_QUERY_LENGTH, default 2048)
+    pub max_query_length: usize,
+    /// Most `&`-separated query parameters accepted (MAX_QUERY_PARAMS, default 32)
+    pub max_query_params: usize,
+    /// Most request headers accepted (MAX_HEADER_COUNT, default 64)
+    pub max_header_count: usize,
+    /// Largest accepted total of request header names and values in bytes (MAX_HEADER_BYTES, default 16384)
+    pub max_header_bytes: usize,
+    /// Most posts a single user may have (MAX_POSTS_PER_USER, unset or 0 = unlimited)
+    pub max_posts_per_user: Option<i64>,
+    /// How a deleted user's posts are removed (USER_POST_DELETION=hard|soft, default soft)
+    pub user_post_deletion: PostDeletion,
+    /// How often expired sessions, old trash and old tombstones are pruned (PRUNE_INTERVAL_MINUTES, default 60, 0 = never)
+    pub prune_interval: Option<std::time::Duration>,
+    /// How long soft-deleted posts and /posts/changes tombstones are kept before pruning (TRASH_RETENTION_DAYS, default 30)
+    pub trash_retention: Duration,
+    /// How long browsers may cache a CORS preflight (CORS_MAX_AGE_SECONDS, default 600, 0 = don't send)
+    pub cors_max_age: Option<std::time::Duration>,
+    /// Response headers scripts on other origins may read (CORS_EXPOSE_HEADERS, comma-separated, default X-Request-Id, ETag and Link)
+    pub cors_expose_headers: Vec<String>,
+    /// Serialization of response timestamps (TIMESTAMP_FORMAT=rfc3339|epoch_millis, default rfc3339)
+    pub timestamp_format: TimestampFormat,
+    /// Hasher for new passwords (PASSWORD_HASHER=bcrypt|argon2, default bcrypt)
+    pub password_hasher: PasswordAlgorithm,
+    /// Handling of trailing slashes in request paths (TRAILING_SLASH=trim|redirect|strict, default trim)
+    pub trailing_slash: TrailingSlash,
+    /// `iss` claim put in access tokens and required on the ones we accept (JWT_ISSUER, default api-rustone)
+    pub jwt_issuer: String,
+    /// `aud` claim put in access tokens and required on the ones we accept (JWT_AUDIENCE, default api-rustone)
+    pub jwt_audience: String,
+    /// Reading speed behind a post's `reading_time_minutes` (READING_WPM, default 200)
+    pub reading_wpm: u32,
+    /// Email availability checks allowed per client IP per minute (EMAIL_CHECK_RATE_LIMIT, default 10)
+    pub email_check_rate_limit: u32,
+    /// How long the anonymous `/posts` feed is served from memory (POSTS_CACHE_TTL_SECONDS, default 0 = no caching)
+    pub posts_cache_ttl: Option<std::time::Duration>,
+    /// Refuse registrations from disposable email domains (BLOCK_DISPOSABLE_EMAILS)
+    pub block_disposable_emails: bool,
+    /// Domains counted as disposable (DISPOSABLE_EMAIL_DOMAINS_FILE, one per line, default the bundled list)
+    pub disposable_email_domains: Vec<String>,
+    /// Requests handled at once; more are refused with 503 (MAX_CONCURRENT_REQUESTS, default 512, 0 = unlimited)
+    pub max_concurrent_requests: Option<usize>,
+    /// Lifetime of the access token issued at login (ACCESS_TOKEN_TTL_MINUTES, default 24 hours)
+    pub access_token_ttl: Duration,
+    /// Show post authors' emails to everyone instead of only to the authors themselves (EXPOSE_AUTHOR_EMAIL)
+    pub expose_author_email: bool,
+    /// Verification email resends allowed per user per hour (VERIFICATION_RESEND_RATE_LIMIT, default 3)
+    pub verification_resend_rate_limit: u32,
+    /// Keys that exempt service callers sending them as `X-Api-Key` from rate limits (TRUSTED_API_KEYS, comma-separated)
+    pub trusted_api_keys: Vec<String>,
+    /// Path every route is served under, e.g. `/api/v1` (API_PREFIX, default none)
+    pub api_prefix: Option<String>,
+    /// Time budget per request; past it the response is a 504 and in-flight queries are interrupted (REQUEST_TIMEOUT_MS, default 30s, 0 = none)
+    pub request_timeout: Option<std::time::Duration>,
+    /// Order of GET /posts when the request has no `sort` (DEFAULT_POST_SORT, default -created_at)
+    pub default_post_sort: PostSort,
+    /// Language of messages for clients whose Accept-Language has no supported match (DEFAULT_LANGUAGE=en|es, default en)
+    pub default_language: Lang,
+    /// Level of the per-request access log line (REQUEST_LOG_LEVEL, default info)
+    pub request_log_level: tracing::Level,
+    /// Log request and response bodies at trace level, secrets redacted (LOG_BODIES, default off)
+    pub log_bodies: bool,
+    /// Include the underlying database error in client-facing error messages (EXPOSE_ERROR_DETAILS, default off)
+    pub expose_error_details: bool,
+    /// `Link` headers with first/prev/next/last URLs on paged listings; each costs a count query (PAGINATION_LINKS, default on)
+    pub pagination_links: bool,
+    /// Answer `OPTIONS` on any route with 204 and an `Allow` header, not just CORS preflights (OPTIONS_ALLOW, default on)
+    pub options_allow: bool,
+    /// Features mounted in the router (FEATURE_* flags, all on by default)
+    pub features: Features,
+    /// Time source for stored timestamps and token expiry; not configurable from the environment
+    pub clock: Arc<dyn Clock>,
+    /// Log filter PUT /admin/log-level adjusts; main attaches it to the subscriber, not configurable from the environment
+    pub log_filter: LogFilter,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            database_url: DEFAULT_DATABASE_URL.to_string(),
+            database_read_url: None,
+            port: DEFAULT_PORT,
+            jwt_secret: DEV_JWT_SECRET.to_string(),
+            reject_duplicate_post_titles: false,
+            client_slugs: false,
+            idle_timeout: None,
+            max_sessions_per_user: None,
+            admin_emails: Vec::new(),
+            max_cover_bytes: DEFAULT_MAX_COVER_BYTES,
+            trust_proxy: false,
+            registration_open: true,
+            max_json_depth: DEFAULT_MAX_JSON_DEPTH,
+            max_json_fields: DEFAULT_MAX_JSON_FIELDS,
+            max_query_length: DEFAULT_MAX_QUERY_LENGTH,
+            max_query_params: DEFAULT_MAX_QUERY_PARAMS,
+            max_header_count: DEFAULT_MAX_HEADER_COUNT,
+            max_header_bytes: DEFAULT_MAX_HEADER_BYTES,
+            max_posts_per_user: None,
+            user_post_deletion: PostDeletion::default(),
+            prune_interval: Some(std::time::Duration::from_secs(DEFAULT_PRUNE_INTERVAL_MINUTES * 60)),
+            trash_retention: Duration::days(DEFAULT_TRASH_RETENTION_DAYS),
+            cors_max_age: Some(std::time::Duration::from_secs(DEFAULT_CORS_MAX_AGE_SECONDS)),
+            cors_expose_headers: DEFAULT_CORS_EXPOSE_HEADERS.iter().map(|header| header.to_string()).collect(),
+            timestamp_format: TimestampFormat::default(),
+            password_hasher: PasswordAlgorithm::default(),
+            trailing_slash: TrailingSlash::default(),
+            jwt_issuer: DEFAULT_JWT_ISSUER.to_string(),
+            jwt_audience: DEFAULT_JWT_AUDIENCE.to_string(),
+            reading_wpm: DEFAULT_READING_WPM,
+            email_check_rate_limit: DEFAULT_EMAIL_CHECK_RATE_LIMIT,
+            posts_cache_ttl: None,
+            block_disposable_emails: false,
+            disposable_email_domains: parse_domain_list(DISPOSABLE_EMAIL_DOMAINS),
+            max_concurrent_requests: Some(DEFAULT_MAX_CONCURRENT_REQUESTS),
+            access_token_ttl: Duration::minutes(DEFAULT_ACCESS_TOKEN_TTL_MINUTES),
+            expose_author_email: false,
+            verification_resend_rate_limit: DEFAULT_VERIFICATION_RESEND_RATE_LIMIT,
+            trusted_api_keys: Vec::new(),
+            api_prefix: None,
+            request_timeout: Some(std::time::Duration::from_millis(DEFAULT_REQUEST_TIMEOUT_MS)),
+            default_post_sort: PostSort::default(),
+            default_language: Lang::En,
+            request_log_level: tracing::Level::INFO,
+            log_bodies: false,
+            expose_error_details: false,
+            pagination_links: true,
+            options_allow: true,
+            features: Features::default(),
+            clock: Arc::new(SystemClock),
+            log_filter: LogFilter::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads every setting from the environment, reporting all missing or malformed variables at once
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    // `lookup` stands in for the environment so loading can be tested without touching process state
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let env = EnvReader { lookup: &lookup, problems: std::cell::RefCell::new(Vec::new()) };
+
+        let settings = Self {
+            database_url: env.string("DATABASE_URL").unwrap_or_else(|| DEFAULT_DATABASE_URL.to_string()),
+            database_read_url: env.string("DATABASE_READ_URL"),
+            port: env.parse("PORT").unwrap_or(DEFAULT_PORT),
+            jwt_secret: env.required("JWT_SECRET"),
+            reject_duplicate_post_titles: env.flag("REJECT_DUPLICATE_POST_TITLES", false),
+            client_slugs: env.flag("CLIENT_SLUGS", false),
+            idle_timeout: env.parse::<i64>("IDLE_TIMEOUT_MINUTES")
+                .filter(|minutes| *minutes > 0)
+                .map(Duration::minutes),
+            max_sessions_per_user: env.parse::<u32>("MAX_SESSIONS_PER_USER").filter(|max| *max > 0),
+            admin_emails: env.list("ADMIN_EMAILS").unwrap_or_default(),
+            max_cover_bytes: env.parse("MAX_COVER_BYTES").unwrap_or(DEFAULT_MAX_COVER_BYTES),
+            trust_proxy: env.flag("TRUST_PROXY", false),
+            registration_open: env.flag("REGISTRATION_OPEN", true),
+            max_json_depth: env.parse("MAX_JSON_DEPTH").unwrap_or(DEFAULT_MAX_JSON_DEPTH),
+            max_json_fields: env.parse("MAX_JSON_FIELDS").unwrap_or(DEFAULT_MAX_JSON_FIELDS),
+            max_query_length: env.parse("MAX_QUERY_LENGTH").unwrap_or(DEFAULT_MAX_QUERY_LENGTH),
+            max_query_params: env.parse("MAX_QUERY_PARAMS").unwrap_or(DEFAULT_MAX_QUERY_PARAMS),
+            max_header_count: env.parse("MAX_HEADER_COUNT").unwrap_or(DEFAULT_MAX_HEADER_COUNT),
+            max_header_bytes: env.parse("MAX_HEADER_BYTES").unwrap_or(DEFAULT_MAX_HEADER_BYTES),
+            max_posts_per_user: env.parse::<i64>("MAX_POSTS_PER_USER").filter(|max| *max > 0),
+            user_post_deletion: env.parse("USER_POST_DELETION").unwrap_or_default(),
+            prune_interval: Some(env.parse("PRUNE_INTERVAL_MINUTES").unwrap_or(DEFAULT_PRUNE_INTERVAL_MINUTES))
+                .filter(|minutes| *minutes > 0)
+                .map(|minutes| std::time::Duration::from_secs(minutes * 60)),
+            trash_retention: Duration::days(
+                env.parse::<i64>("TRASH_RETENTION_DAYS")
+                    .filter(|days| *days > 0)
+                    .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS),
+            ),
+            cors_max_age: Some(env.parse("CORS_MAX_AGE_SECONDS").unwrap_or(DEFAULT_CORS_MAX_AGE_SECONDS))
+                .filter(|seconds| *seconds > 0)
+                .map(std::time::Duration::from_secs),
+            cors_expose_headers: env.list("CORS_EXPOSE_HEADERS")
+                .unwrap_or_else(|| DEFAULT_CORS_EXPOSE_HEADERS.iter().map(|header| header.to_string()).collect()),
+            timestamp_format: env.parse("TIMESTAMP_FORMAT").unwrap_or_default(),
+            password_hasher: env.parse("PASSWORD_HASHER").unwrap_or_default(),
+            trailing_slash: env.parse("TRAILING_SLASH").unwrap_or_default(),
+            jwt_issuer: env.string("JWT_ISSUER").unwrap_or_else(|| DEFAULT_JWT_ISSUER.to_string()),
+            jwt_audience: env.string("JWT_AUDIENCE").unwrap_or_else(|| DEFAULT_JWT_AUDIENCE.to_string()),
+            reading_wpm: env.parse("READING_WPM").filter(|wpm| *wpm > 0).unwrap_or(DEFAULT_READING_WPM),
+            email_check_rate_limit: env.parse("EMAIL_CHECK_RATE_LIMIT").unwrap_or(DEFAULT_EMAIL_CHECK_RATE_LIMIT),
+            posts_cache_ttl: env.parse::<u64>("POSTS_CACHE_TTL_SECONDS")
+                .filter(|seconds| *seconds > 0)
+                .map(std::time::Duration::from_secs),
+            block_disposable_emails: env.flag("BLOCK_DISPOSABLE_EMAILS", false),
+            disposable_email_domains: parse_domain_list(
+                &env.file("DISPOSABLE_EMAIL_DOMAINS_FILE").unwrap_or_else(|| DISPOSABLE_EMAIL_DOMAINS.to_string()),
+            ),
+            max_concurrent_requests: Some(env.parse("MAX_CONCURRENT_REQUESTS").unwrap_or(DEFAULT_MAX_CONCURRENT_REQUESTS))
+                .filter(|max| *max > 0),
+            access_token_ttl: Duration::minutes(
+                env.parse::<i64>("ACCESS_TOKEN_TTL_MINUTES")
+                    .filter(|minutes| *minutes > 0)
+                    .unwrap_or(DEFAULT_ACCESS_TOKEN_TTL_MINUTES),
+            ),
+            expose_author_email: env.flag("EXPOSE_AUTHOR_EMAIL", false),
+            verification_resend_rate_limit: env.parse("VERIFICATION_RESEND_RATE_LIMIT")
+                .unwrap_or(DEFAULT_VERIFICATION_RESEND_RATE_LIMIT),
+            trusted_api_keys: env.list("TRUSTED_API_KEYS").unwrap_or_default(),
+            api_prefix: env.string("API_PREFIX").and_then(|prefix| normalize_prefix(&prefix)),
+            request_timeout: Some(env.parse("REQUEST_TIMEOUT_MS").unwrap_or(DEFAULT_REQUEST_TIMEOUT_MS))
+                .filter(|ms| *ms > 0)
+                .map(std::time::Duration::from_millis),
+            default_post_sort: env.parse("DEFAULT_POST_SORT").unwrap_or_default(),
+            default_language: env.parse("DEFAULT_LANGUAGE").unwrap_or(Lang::En),
+            request_log_level: env.parse("REQUEST_LOG_LEVEL").unwrap_or(tracing::Level::INFO),
+            log_bodies: env.flag("LOG_BODIES", false),
+            expose_error_details: env.flag("EXPOSE_ERROR_DETAILS", false),
+            pagination_links: env.flag("PAGINATION_LINKS", true),
+            options_allow: env.flag("OPTIONS_ALLOW", true),
+            features: Features {
+                comments: env.flag("FEATURE_COMMENTS", true),
+                likes: env.flag("FEATURE_LIKES", true),
+                registration: env.flag("FEATURE_REGISTRATION", true),
+            },
+            clock: Arc::new(SystemClock),
+            log_filter: LogFilter::default(),
+        };
+
+        let problems = env.problems.into_inner();
+        if problems.is_empty() {
+            Ok(settings)
+        } else {
+            Err(ConfigError { problems })
+        }
+    }
+
+    pub fn is_admin_email(&self, email: &str) -> bool {
+        self.admin_emails.iter().any(|admin| admin.eq_ignore_ascii_case(email))
+    }
+
+    /// Email domains registration refuses; empty unless BLOCK_DISPOSABLE_EMAILS is on
+    pub fn blocked_email_domains(&self) -> &[String] {
+        if self.block_disposable_emails {
+            &self.disposable_email_domains
+        } else {
+            &[]
+        }
+    }
+}
+
+// "api/v1/" -> "/api/v1"; a bare "/" means no prefix
+fn normalize_prefix(prefix: &str) -> Option<String> {
+    let trimmed = prefix.trim_matches('/');
+    (!trimmed.is_empty()).then(|| format!("/{}", trimmed))
+}
+
+/// Every problem found while loading settings, one line per variable
+#[derive(Debug, thiserror::Error)]
+#[error("invalid configuration:\n  {}", .problems.join("\n  "))]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+// Reads variables through a lookup and records what is missing or malformed instead of stopping at the first problem
+struct EnvReader<'a> {
+    lookup: &'a dyn Fn(&str) -> Option<String>,
+    problems: std::cell::RefCell<Vec<String>>,
+}
+
+impl EnvReader<'_> {
+    fn string(&self, name: &str) -> Option<String> {
+        (self.lookup)(name).map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+    }
+
+    fn required(&self, name: &str) -> String {
+        self.string(name).unwrap_or_else(|| {
+            self.problems.borrow_mut().push(format!("{} is required but not set", name));
+            String::new()
+        })
+    }
+
+    fn parse<T: std::str::FromStr>(&self, name: &str) -> Option<T> {
+        let value = self.string(name)?;
+        match value.parse() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                self.problems.borrow_mut().push(format!("{} has an invalid value: {:?}", name, value));
+                None
+            }
+        }
+    }
+
+    fn flag(&self, name: &str, default: bool) -> bool {
+        let Some(value) = self.string(name) else {
+            return default;
+        };
+        match value.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            _ => {
+                self.problems.borrow_mut().push(format!("{} must be a boolean, got {:?}", name, value));
+                default
+            }
+        }
+    }
+
+    // Contents of the file the variable points at
+    fn file(&self, name: &str) -> Option<String> {
+        let path = self.string(name)?;
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Some(contents),
+            Err(e) => {
+                self.problems.borrow_mut().push(format!("{} could not be read from {:?}: {}", name, path, e));
+                None
+            }
+        }
+    }
+
+    // Set but empty means an explicitly empty list
+    fn list(&self, name: &str) -> Option<Vec<String>> {
+        (self.lookup)(name).map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+    }
+}
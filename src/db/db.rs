@@ -7,7 +7,7 @@ pub async fn get_client() -> mongodb::error::Result<Client> {
       .await?;
   let server_api = ServerApi::builder().version(ServerApiVersion::V1).build();
   client_options.server_api = Some(server_api);
-  Ok(Client::with_options(client_options)?)
+  Client::with_options(client_options)
 }
 
 
@@ -1,3 +1,6 @@
+#[allow(clippy::module_inception)]
 pub mod db;
 pub mod sql_db;
+pub mod row;
 pub mod repositories;
+pub mod pruning;
@@ -0,0 +1,52 @@
+use sqlx::sqlite::SqlitePool;
+use anyhow::Result;
+use chrono::Duration;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use crate::config::Settings;
+use crate::db::repositories::sql_post_repo::SqlPostRepository;
+use crate::db::repositories::sql_user_repo::SqlUserRepository;
+use crate::helpers::clock::Clock;
+use tracing::{error, info};
+
+/// Rows removed by one pruning pass
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PruneReport {
+    pub sessions: u64,
+    pub posts: u64,
+    pub tombstones: u64,
+}
+
+/// Deletes expired sessions, plus posts trashed and tombstones left more than `retention` ago
+pub async fn prune(pool: &SqlitePool, clock: Arc<dyn Clock>, retention: Duration) -> Result<PruneReport> {
+    let cutoff = clock.now() - retention;
+
+    let sessions = SqlUserRepository::new(pool.clone()).with_clock(clock).prune_sessions().await?;
+    let post_repo = SqlPostRepository::new(pool.clone());
+    let posts = post_repo.prune_trash(cutoff).await?;
+    let tombstones = post_repo.prune_tombstones(cutoff).await?;
+
+    Ok(PruneReport { sessions, posts, tombstones })
+}
+
+/// Runs `prune` every PRUNE_INTERVAL_MINUTES on its own task; nothing is spawned when pruning is off
+pub fn spawn_pruning(pool: SqlitePool, settings: &Settings) -> Option<JoinHandle<()>> {
+    let period = settings.prune_interval?;
+    let clock = settings.clock.clone();
+    let retention = settings.trash_retention;
+
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            // A failed pass is retried on the next tick
+            match prune(&pool, clock.clone(), retention).await {
+                Ok(report) => info!(
+                    "Pruned {} expired sessions, {} trashed posts and {} tombstones",
+                    report.sessions, report.posts, report.tombstones
+                ),
+                Err(e) => error!("Pruning failed: {}", e),
+            }
+        }
+    }))
+}
@@ -1,3 +1,5 @@
 pub mod user_repo;
 pub mod sql_user_repo;
-pub mod sql_post_repo; 
\ No newline at end of file
+pub mod sql_post_repo;
+pub mod sql_audit_repo;
+pub mod sql_comment_repo; pub mod sql_report_repo;
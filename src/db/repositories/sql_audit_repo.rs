@@ -0,0 +1,77 @@
+use sqlx::{sqlite::{SqliteConnection, SqlitePool, SqliteRow}, Row};
+use anyhow::Result;
+use uuid::Uuid;
+use chrono::Utc;
+use crate::model::model::{AuditAction, AuditEntry};
+use crate::db::row::{get_datetime, get_uuid};
+use tracing::debug;
+
+pub struct SqlAuditRepository {
+    pool: SqlitePool,
+}
+
+impl SqlAuditRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        debug!("Creating new SqlAuditRepository");
+        Self { pool }
+    }
+
+    // Writes an entry on `conn`, normally the transaction of the action being recorded
+    pub async fn record(conn: &mut SqliteConnection, actor_id: Uuid, action: AuditAction, target_id: Uuid) -> Result<()> {
+        debug!("Recording audit entry: {} {} {}", actor_id, action.as_str(), target_id);
+        
+        sqlx::query(
+            r#"
+            INSERT INTO audit_log (id, actor_id, action, target_id, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(actor_id.to_string())
+        .bind(action.as_str())
+        .bind(target_id.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    // Newest first
+    pub async fn list(&self, limit: u32, offset: u32) -> Result<Vec<AuditEntry>> {
+        debug!("Listing audit entries (limit {}, offset {})", limit, offset);
+        
+        let rows = sqlx::query(
+            r#"
+            SELECT id, actor_id, action, target_id, created_at
+            FROM audit_log
+            ORDER BY created_at DESC, rowid DESC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::map_entry).collect()
+    }
+
+    pub async fn count(&self) -> Result<i64> {
+        let count = sqlx::query_scalar("SELECT COUNT(*) FROM audit_log")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    fn map_entry(row: &SqliteRow) -> Result<AuditEntry> {
+        Ok(AuditEntry {
+            id: get_uuid(row, "id")?,
+            actor_id: get_uuid(row, "actor_id")?,
+            action: row.get::<String, _>("action").parse().map_err(anyhow::Error::msg)?,
+            target_id: get_uuid(row, "target_id")?,
+            created_at: get_datetime(row, "created_at")?,
+        })
+    }
+}
@@ -0,0 +1,161 @@
+use sqlx::{sqlite::{SqlitePool, SqliteRow}, Row};
+use anyhow::Result;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use crate::model::model::{Comment, CommentOrder};
+use crate::db::row::{get_datetime, get_uuid};
+use crate::db::sql_db::retry_busy;
+use tracing::{debug, info};
+
+pub struct SqlCommentRepository {
+    pool: SqlitePool,
+    // Pool for find_*/get_all_* style reads; the primary unless a replica is attached
+    read_pool: SqlitePool,
+}
+
+impl SqlCommentRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        debug!("Creating new SqlCommentRepository");
+        Self { read_pool: pool.clone(), pool }
+    }
+
+    /// Sends reads to `read_pool` (a replica); writes keep using the primary
+    pub fn with_read_pool(mut self, read_pool: SqlitePool) -> Self {
+        self.read_pool = read_pool;
+        self
+    }
+
+    pub async fn create_comment(&self, post_id: Uuid, author_id: Uuid, content: String) -> Result<Comment> {
+        info!("Creating comment on post {} by user {}", post_id, author_id);
+        
+        let comment = Comment {
+            id: Uuid::new_v4(),
+            post_id,
+            author_id,
+            content,
+            created_at: Utc::now(),
+        };
+
+        retry_busy(|| {
+            sqlx::query(
+                r#"
+                INSERT INTO comments (id, post_id, author_id, content, created_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(comment.id.to_string())
+            .bind(comment.post_id.to_string())
+            .bind(comment.author_id.to_string())
+            .bind(&comment.content)
+            .bind(comment.created_at.to_rfc3339())
+            .execute(&self.pool)
+        })
+        .await?;
+
+        debug!("Comment created successfully: id={}", comment.id);
+        Ok(comment)
+    }
+
+    // One page of a post's comments, optionally only those created after `since`
+    pub async fn list_for_post(
+        &self,
+        post_id: Uuid,
+        since: Option<DateTime<Utc>>,
+        order: CommentOrder,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Comment>> {
+        debug!("Listing comments of post {} (since {:?}, {:?}, limit {}, offset {})", post_id, since, order, limit, offset);
+        
+        let direction = match order {
+            CommentOrder::Oldest => "ASC",
+            CommentOrder::Newest => "DESC",
+        };
+
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT c.id, c.post_id, c.author_id, c.content, c.created_at
+            FROM comments c
+            JOIN users u ON u.id = c.author_id
+            WHERE c.post_id = ?
+              AND u.deleted_at IS NULL
+              AND (? IS NULL OR c.created_at > ?)
+            ORDER BY c.created_at {direction}, c.rowid {direction}
+            LIMIT ? OFFSET ?
+            "#,
+        ))
+        .bind(post_id.to_string())
+        .bind(since.map(|since| since.to_rfc3339()))
+        .bind(since.map(|since| since.to_rfc3339()))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        rows.iter().map(Self::map_comment).collect()
+    }
+
+    // How many comments `list_for_post` would return across all pages
+    pub async fn count_for_post(&self, post_id: Uuid) -> Result<i64> {
+        debug!("Counting comments of post {}", post_id);
+
+        let count = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM comments c
+            JOIN users u ON u.id = c.author_id
+            WHERE c.post_id = ?
+              AND u.deleted_at IS NULL
+            "#,
+        )
+        .bind(post_id.to_string())
+        .fetch_one(&self.read_pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    // Newest comments by `author_id` created before `before`, on live posts `viewer` may see in a listing
+    pub async fn find_by_author(
+        &self,
+        author_id: Uuid,
+        viewer: Option<Uuid>,
+        before: Option<DateTime<Utc>>,
+        limit: u32,
+    ) -> Result<Vec<Comment>> {
+        debug!("Finding comments by author: {}", author_id);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT c.id, c.post_id, c.author_id, c.content, c.created_at
+            FROM comments c
+            JOIN posts p ON p.id = c.post_id
+            WHERE c.author_id = ?1
+              AND p.deleted_at IS NULL
+              AND (p.visibility = 'public' OR p.author_id = ?2)
+              AND (p.status = 'published' OR p.author_id = ?2)
+              AND (?3 IS NULL OR c.created_at < ?3)
+            ORDER BY c.created_at DESC, c.rowid DESC
+            LIMIT ?4
+            "#,
+        )
+        .bind(author_id.to_string())
+        .bind(viewer.map(|id| id.to_string()))
+        .bind(before.map(|before| before.to_rfc3339()))
+        .bind(limit)
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        rows.iter().map(Self::map_comment).collect()
+    }
+
+    fn map_comment(row: &SqliteRow) -> Result<Comment> {
+        Ok(Comment {
+            id: get_uuid(row, "id")?,
+            post_id: get_uuid(row, "post_id")?,
+            author_id: get_uuid(row, "author_id")?,
+            content: row.get("content"),
+            created_at: get_datetime(row, "created_at")?,
+        })
+    }
+}
@@ -1,48 +1,124 @@
-use sqlx::{sqlite::SqlitePool, Row};
+use futures_util::{FutureExt, Stream, StreamExt};
+use sqlx::{query::Query, Transaction, sqlite::{Sqlite, SqliteArguments, SqliteConnection, SqlitePool, SqliteRow}, Row};
 use anyhow::Result;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
-use crate::model::model::{Post, CreatePostRequest, UpdatePostRequest, PostResponse, UserResponse};
+use std::sync::Arc;
+use std::time::Instant;
+use crate::model::model::{Post, CreatePostRequest, UpdatePostRequest, PostListFilter, PostResponse, PostAuthor, PostStatus, PostChange, PostTombstone, UserResponse, Visibility, TagCount, AuditAction, BatchMode};
+use crate::db::repositories::sql_audit_repo::SqlAuditRepository;
+use crate::config::{PostSort, PostSortField, DEFAULT_READING_WPM};
+use crate::helpers::clock::{Clock, SystemClock};
+use crate::helpers::validation::slugify;
+use crate::db::row::{get_datetime, get_optional_datetime, get_uuid};
+use crate::db::sql_db::{retry_busy, within_deadline};
 use tracing::{debug, info};
 
+// Optional filters of a listing query, as ?2 (tag), ?3 (author username) and ?4 (created after)
+const LISTING_FILTERS: &str = "(?2 IS NULL OR EXISTS(SELECT 1 FROM post_tags t WHERE t.post_id = p.id AND t.tag = ?2))
+              AND (?3 IS NULL OR p.author_id IN (SELECT id FROM users WHERE username = ?3))
+              AND (?4 IS NULL OR p.created_at > ?4)";
+
 pub struct SqlPostRepository {
     pool: SqlitePool,
+    // Pool for find_*/get_all_* style reads; the primary unless a replica is attached
+    read_pool: SqlitePool,
+    reading_wpm: u32,
+    // Path prefix for links in responses, e.g. cover_url ("" when routes aren't nested)
+    api_prefix: String,
+    // Reads bounded by within_deadline stop at this instant
+    deadline: Option<Instant>,
+    clock: Arc<dyn Clock>,
 }
 
 impl SqlPostRepository {
     pub fn new(pool: SqlitePool) -> Self {
         debug!("Creating new SqlPostRepository");
-        Self { pool }
+        Self { read_pool: pool.clone(), pool, reading_wpm: DEFAULT_READING_WPM, api_prefix: String::new(), deadline: None, clock: Arc::new(SystemClock) }
+    }
+
+    /// Sends reads to `read_pool` (a replica); writes keep using the primary
+    pub fn with_read_pool(mut self, read_pool: SqlitePool) -> Self {
+        self.read_pool = read_pool;
+        self
+    }
+
+    /// Reading speed used for `reading_time_minutes` on mapped posts
+    pub fn with_reading_wpm(mut self, reading_wpm: u32) -> Self {
+        self.reading_wpm = reading_wpm.max(1);
+        self
+    }
+
+    /// Prefixes links in mapped posts with the path the routes are served under (API_PREFIX)
+    pub fn with_api_prefix(mut self, api_prefix: Option<&str>) -> Self {
+        self.api_prefix = api_prefix.unwrap_or_default().to_string();
+        self
+    }
+
+    /// Interrupts post reads that are still running at `deadline` (the request's remaining budget)
+    pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Stamps created_at, updated_at and like/cover times from `clock` instead of the wall clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
     }
 
     pub async fn create_post(&self, post_data: CreatePostRequest, author_id: Uuid) -> Result<Post> {
         let id = Uuid::new_v4();
-        let now = Utc::now();
+        let now = self.clock.now();
         
         info!("Creating new post with title: {}", post_data.title);
+
+        // A client-supplied slug is used as is; otherwise one is derived from the title
+        let slug = match post_data.slug {
+            Some(slug) => slug,
+            None => self.unique_slug(&slugify(&post_data.title)).await?,
+        };
         
         let post = Post {
             id,
             title: post_data.title,
+            slug,
             content: post_data.content,
             author_id,
+            visibility: post_data.visibility,
+            status: post_data.status,
+            published_at: (post_data.status == PostStatus::Published).then_some(now),
+            edited_at: None,
+            tags: post_data.tags,
             created_at: now,
             updated_at: now,
         };
 
-        sqlx::query(
-            r#"
-            INSERT INTO posts (id, title, content, author_id, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(post.id.to_string())
-        .bind(&post.title)
-        .bind(&post.content)
-        .bind(post.author_id.to_string())
-        .bind(post.created_at.to_rfc3339())
-        .bind(post.updated_at.to_rfc3339())
-        .execute(&self.pool)
+        // The post and its tags go in together; a busy database retries the whole transaction
+        let post_ref = &post;
+        retry_busy(|| async move {
+            let mut tx = self.pool.begin().await?;
+            sqlx::query(
+                r#"
+                INSERT INTO posts (id, title, slug, content, author_id, visibility, status, published_at, created_at, updated_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(post_ref.id.to_string())
+            .bind(&post_ref.title)
+            .bind(&post_ref.slug)
+            .bind(&post_ref.content)
+            .bind(post_ref.author_id.to_string())
+            .bind(post_ref.visibility.as_str())
+            .bind(post_ref.status.as_str())
+            .bind(post_ref.published_at.map(|at| at.to_rfc3339()))
+            .bind(post_ref.created_at.to_rfc3339())
+            .bind(post_ref.updated_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+            Self::set_tags(&mut tx, post_ref.id, &post_ref.tags).await?;
+            tx.commit().await
+        })
         .await?;
 
         debug!("Post created successfully: id={}", post.id);
@@ -52,26 +128,23 @@ impl SqlPostRepository {
     pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Post>> {
         debug!("Finding post by id: {}", id);
         
-        let row = sqlx::query(
-            r#"
-            SELECT id, title, content, author_id, created_at, updated_at
-            FROM posts WHERE id = ?
-            "#,
-        )
-        .bind(id.to_string())
-        .fetch_optional(&self.pool)
+        let row = within_deadline(&self.read_pool, self.deadline, move |conn| {
+            sqlx::query(
+                r#"
+                SELECT id, title, slug, content, author_id, visibility, status, published_at, edited_at, created_at, updated_at,
+                    (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = posts.id) as tags
+                FROM posts WHERE id = ? AND deleted_at IS NULL
+                "#,
+            )
+            .bind(id.to_string())
+            .fetch_optional(conn)
+            .boxed()
+        })
         .await?;
 
         match row {
             Some(row) => {
-                let post = Post {
-                    id: Uuid::parse_str(&row.get::<String, _>("id"))?,
-                    title: row.get("title"),
-                    content: row.get("content"),
-                    author_id: Uuid::parse_str(&row.get::<String, _>("author_id"))?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
-                };
+                let post = Self::map_post(&row)?;
                 debug!("Post with id {} found", id);
                 Ok(Some(post))
             }
@@ -82,41 +155,112 @@ impl SqlPostRepository {
         }
     }
 
-    pub async fn find_by_id_with_author(&self, id: Uuid) -> Result<Option<PostResponse>> {
-        debug!("Finding post by id with author: {}", id);
+    pub async fn find_by_author_and_title(&self, author_id: Uuid, title: &str) -> Result<Option<Post>> {
+        debug!("Finding post by author {} and title: {}", author_id, title);
         
         let row = sqlx::query(
             r#"
-            SELECT 
-                p.id, p.title, p.content, p.author_id, p.created_at, p.updated_at,
-                u.name as author_name, u.email as author_email, u.created_at as author_created_at, u.updated_at as author_updated_at
-            FROM posts p
-            JOIN users u ON p.author_id = u.id
-            WHERE p.id = ?
+            SELECT id, title, slug, content, author_id, visibility, status, published_at, edited_at, created_at, updated_at,
+                (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = posts.id) as tags
+            FROM posts WHERE author_id = ? AND title = ? AND deleted_at IS NULL
+            LIMIT 1
+            "#,
+        )
+        .bind(author_id.to_string())
+        .bind(title)
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        row.as_ref().map(Self::map_post).transpose()
+    }
+
+    pub async fn count_by_author(&self, author_id: Uuid) -> Result<i64> {
+        debug!("Counting posts by author: {}", author_id);
+        
+        let count = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM posts WHERE author_id = ? AND deleted_at IS NULL
+            "#,
+        )
+        .bind(author_id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    // Ownership check ahead of a write: it reads the primary and never loads the row
+    pub async fn owned_by(&self, id: Uuid, author_id: Uuid) -> Result<bool> {
+        let owned: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM posts WHERE id = ? AND author_id = ? AND deleted_at IS NULL)
             "#,
         )
         .bind(id.to_string())
-        .fetch_optional(&self.pool)
+        .bind(author_id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(owned)
+    }
+
+    // Slugs are unique across all posts, including soft-deleted ones
+    pub async fn slug_exists(&self, slug: &str) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM posts WHERE slug = ?)
+            "#,
+        )
+        .bind(slug)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(exists)
+    }
+
+    // `base`, or `base-2`, `base-3`, ... for the first one no post has taken yet
+    async fn unique_slug(&self, base: &str) -> Result<String> {
+        let taken: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT slug FROM posts WHERE slug = ?1 OR slug LIKE ?1 || '-%'
+            "#,
+        )
+        .bind(base)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let slug = std::iter::once(base.to_string())
+            .chain((2..).map(|n| format!("{}-{}", base, n)))
+            .find(|candidate| !taken.contains(candidate))
+            .expect("an unbounded sequence has a free slug");
+        Ok(slug)
+    }
+
+    pub async fn find_by_id_with_author(&self, id: Uuid) -> Result<Option<PostResponse>> {
+        debug!("Finding post by id with author: {}", id);
+        
+        let row = within_deadline(&self.read_pool, self.deadline, move |conn| {
+            sqlx::query(
+                r#"
+                SELECT 
+                    p.id, p.title, p.slug, p.content, p.author_id, p.visibility, p.status, p.published_at, p.edited_at, p.created_at, p.updated_at,
+                    u.name as author_name, u.username as author_username, u.email as author_email, u.created_at as author_created_at, u.updated_at as author_updated_at,
+                    EXISTS(SELECT 1 FROM post_covers c WHERE c.post_id = p.id) as has_cover,
+                    (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = p.id) as tags
+                FROM posts p
+                JOIN users u ON p.author_id = u.id
+                WHERE p.id = ? AND p.deleted_at IS NULL
+                "#,
+            )
+            .bind(id.to_string())
+            .fetch_optional(conn)
+            .boxed()
+        })
         .await?;
 
         match row {
             Some(row) => {
-                let author = UserResponse {
-                    id: Uuid::parse_str(&row.get::<String, _>("author_id"))?,
-                    name: row.get("author_name"),
-                    email: row.get("author_email"),
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("author_created_at"))?.with_timezone(&Utc),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("author_updated_at"))?.with_timezone(&Utc),
-                };
-
-                let post_response = PostResponse {
-                    id: Uuid::parse_str(&row.get::<String, _>("id"))?,
-                    title: row.get("title"),
-                    content: row.get("content"),
-                    author,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
-                };
+                let post_response = self.map_post_with_author(&row)?;
                 
                 debug!("Post with id {} found", id);
                 Ok(Some(post_response))
@@ -128,35 +272,218 @@ impl SqlPostRepository {
         }
     }
 
+    // One listed post picked at random; a full scan, which is fine at SQLite scale
+    pub async fn find_random_with_author(&self) -> Result<Option<PostResponse>> {
+        debug!("Finding a random post");
+
+        let row = within_deadline(&self.read_pool, self.deadline, move |conn| {
+            sqlx::query(
+                r#"
+                SELECT 
+                    p.id, p.title, p.slug, p.content, p.author_id, p.visibility, p.status, p.published_at, p.edited_at, p.created_at, p.updated_at,
+                    u.name as author_name, u.username as author_username, u.email as author_email, u.created_at as author_created_at, u.updated_at as author_updated_at,
+                    EXISTS(SELECT 1 FROM post_covers c WHERE c.post_id = p.id) as has_cover,
+                    (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = p.id) as tags
+                FROM posts p
+                JOIN users u ON p.author_id = u.id
+                WHERE p.deleted_at IS NULL AND p.visibility = 'public' AND p.status = 'published'
+                ORDER BY RANDOM()
+                LIMIT 1
+                "#,
+            )
+            .fetch_optional(conn)
+            .boxed()
+        })
+        .await?;
+
+        row.map(|row| self.map_post_with_author(&row)).transpose()
+    }
+
+    // Newest first; posts created in the same instant fall back to id order so repeated reads agree
     pub async fn find_by_author(&self, author_id: Uuid) -> Result<Vec<Post>> {
         debug!("Finding posts by author: {}", author_id);
         
         let rows = sqlx::query(
             r#"
-            SELECT id, title, content, author_id, created_at, updated_at
-            FROM posts WHERE author_id = ?
-            ORDER BY created_at DESC
+            SELECT id, title, slug, content, author_id, visibility, status, published_at, edited_at, created_at, updated_at,
+                (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = posts.id) as tags
+            FROM posts WHERE author_id = ? AND deleted_at IS NULL
+            ORDER BY created_at DESC, id DESC
             "#,
         )
         .bind(author_id.to_string())
-        .fetch_all(&self.pool)
+        .fetch_all(&self.read_pool)
         .await?;
 
-        let posts: Result<Vec<Post>> = rows
-            .into_iter()
-            .map(|row| {
-                Ok(Post {
-                    id: Uuid::parse_str(&row.get::<String, _>("id"))?,
-                    title: row.get("title"),
-                    content: row.get("content"),
-                    author_id: Uuid::parse_str(&row.get::<String, _>("author_id"))?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
-                })
-            })
-            .collect();
+        rows.iter().map(Self::map_post).collect()
+    }
+
+    pub async fn find_by_author_with_author(&self, author_id: Uuid) -> Result<Vec<PostResponse>> {
+        debug!("Finding posts with author by author: {}", author_id);
+        
+        let rows = sqlx::query(
+            r#"
+            SELECT 
+                p.id, p.title, p.slug, p.content, p.author_id, p.visibility, p.status, p.published_at, p.edited_at, p.created_at, p.updated_at,
+                u.name as author_name, u.username as author_username, u.email as author_email, u.created_at as author_created_at, u.updated_at as author_updated_at,
+                EXISTS(SELECT 1 FROM post_covers c WHERE c.post_id = p.id) as has_cover,
+                (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = p.id) as tags
+            FROM posts p
+            JOIN users u ON p.author_id = u.id
+            WHERE p.author_id = ? AND p.deleted_at IS NULL
+            ORDER BY p.created_at DESC, p.id DESC
+            "#,
+        )
+        .bind(author_id.to_string())
+        .fetch_all(&self.read_pool)
+        .await?;
 
-        posts
+        rows.iter().map(|row| self.map_post_with_author(row)).collect()
+    }
+
+    // Newest posts by `author_id` created before `before` that `viewer` may see in a listing
+    pub async fn find_activity_by_author(
+        &self,
+        author_id: Uuid,
+        viewer: Option<Uuid>,
+        before: Option<DateTime<Utc>>,
+        limit: u32,
+    ) -> Result<Vec<Post>> {
+        debug!("Finding post activity of author: {}", author_id);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, title, slug, content, author_id, visibility, status, published_at, edited_at, created_at, updated_at,
+                (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = posts.id) as tags
+            FROM posts
+            WHERE author_id = ?1 AND deleted_at IS NULL
+              AND (visibility = 'public' OR author_id = ?2)
+              AND (status = 'published' OR author_id = ?2)
+              AND (?3 IS NULL OR created_at < ?3)
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?4
+            "#,
+        )
+        .bind(author_id.to_string())
+        .bind(viewer.map(|id| id.to_string()))
+        .bind(before.map(|before| before.to_rfc3339()))
+        .bind(limit)
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        rows.iter().map(Self::map_post).collect()
+    }
+
+    // Posts created or updated after `since`, oldest change first, for incremental sync. Posts the viewer
+    // could see that were deleted or hidden from them since come back as tombstones
+    // Up to `limit` changes after the (`since`, `after`) cursor, ordered by time then id, and whether more follow
+    pub async fn find_changed_since(
+        &self,
+        since: DateTime<Utc>,
+        after: Option<Uuid>,
+        limit: u32,
+        viewer: Option<Uuid>,
+    ) -> Result<(Vec<PostChange>, bool)> {
+        debug!("Finding posts changed since: {}", since);
+
+        let since = since.to_rfc3339();
+        let after = after.map(|id| id.to_string());
+        let viewer = viewer.map(|id| id.to_string());
+        // One extra row from each side tells whether there's another page
+        let fetch = i64::from(limit) + 1;
+
+        let (since_arg, after_arg, viewer_arg) = (since.clone(), after.clone(), viewer.clone());
+        let rows = within_deadline(&self.read_pool, self.deadline, move |conn| {
+            sqlx::query(
+                r#"
+                SELECT id, title, slug, content, author_id, visibility, status, published_at, edited_at, created_at, updated_at,
+                    (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = posts.id) as tags
+                FROM posts
+                WHERE (updated_at > ?1 OR (updated_at = ?1 AND id > ?3))
+                  AND deleted_at IS NULL
+                  AND (visibility = 'public' OR (visibility = 'private' AND author_id = ?2))
+                  AND (status = 'published' OR author_id = ?2)
+                ORDER BY updated_at ASC, id ASC
+                LIMIT ?4
+                "#,
+            )
+            .bind(since_arg)
+            .bind(viewer_arg)
+            .bind(after_arg)
+            .bind(fetch)
+            .fetch_all(conn)
+            .boxed()
+        })
+        .await?;
+
+        let mut changes = rows
+            .iter()
+            .map(|row| Self::map_post(row).map(PostChange::Upserted))
+            .collect::<Result<Vec<_>>>()?;
+
+        // A restored or re-published post is listed above, not as a tombstone
+        let tombstones = within_deadline(&self.read_pool, self.deadline, move |conn| {
+            sqlx::query(
+                r#"
+                SELECT t.post_id, t.removed_at
+                FROM post_tombstones t
+                LEFT JOIN posts p ON p.id = t.post_id
+                WHERE (t.removed_at > ?1 OR (t.removed_at = ?1 AND t.post_id > ?3))
+                  AND (t.was_public OR t.author_id IS ?2)
+                  AND NOT COALESCE(
+                      p.deleted_at IS NULL
+                      AND (p.visibility = 'public' OR (p.visibility = 'private' AND p.author_id IS ?2))
+                      AND (p.status = 'published' OR p.author_id IS ?2),
+                      0
+                  )
+                ORDER BY t.removed_at ASC, t.post_id ASC
+                LIMIT ?4
+                "#,
+            )
+            .bind(since)
+            .bind(viewer)
+            .bind(after)
+            .bind(fetch)
+            .fetch_all(conn)
+            .boxed()
+        })
+        .await?;
+
+        for row in &tombstones {
+            changes.push(PostChange::Removed(PostTombstone {
+                id: get_uuid(row, "post_id")?,
+                deleted: true,
+                updated_at: get_datetime(row, "removed_at")?,
+            }));
+        }
+        changes.sort_by_key(|change| (change.updated_at(), change.id()));
+
+        let more = changes.len() > limit as usize;
+        changes.truncate(limit as usize);
+        Ok((changes, more))
+    }
+
+    // Leaves a tombstone at `now` for each post matching `condition` (with ?1 bound to `key`), for the sync feed.
+    // Runs on `conn`, the transaction hiding the posts, before their visibility and status change
+    pub async fn record_tombstones(conn: &mut SqliteConnection, condition: &str, key: Uuid, now: &str) -> Result<()> {
+        debug!("Recording post tombstones where {}", condition);
+
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO post_tombstones (post_id, author_id, was_public, removed_at)
+            SELECT id, author_id, visibility = 'public' AND status = 'published', ?2
+            FROM posts WHERE {}
+            ON CONFLICT (post_id) DO UPDATE
+            SET was_public = post_tombstones.was_public OR excluded.was_public, removed_at = excluded.removed_at
+            "#,
+            condition
+        ))
+        .bind(key.to_string())
+        .bind(now)
+        .execute(conn)
+        .await?;
+
+        Ok(())
     }
 
     pub async fn update_post(&self, id: Uuid, author_id: Uuid, update_data: UpdatePostRequest) -> Result<Option<Post>> {
@@ -173,35 +500,72 @@ impl SqlPostRepository {
             return Ok(None); // Not authorized
         }
 
+        let was_visibility = post.visibility;
         let mut updated_post = post;
         let mut updated = false;
+        // Only a real change to the title or content counts as an edit
+        let mut edited = false;
 
         if let Some(title) = update_data.title {
+            edited |= title != updated_post.title;
             updated_post.title = title;
             updated = true;
         }
 
         if let Some(content) = update_data.content {
+            edited |= content != updated_post.content;
             updated_post.content = content;
             updated = true;
         }
 
+        if let Some(visibility) = update_data.visibility {
+            updated_post.visibility = visibility;
+            updated = true;
+        }
+
+        let new_tags = update_data.tags.is_some();
+        if let Some(tags) = update_data.tags {
+            updated_post.tags = tags;
+            updated = true;
+        }
+
         if updated {
-            updated_post.updated_at = Utc::now();
+            updated_post.updated_at = self.clock.now();
+            if edited {
+                updated_post.edited_at = Some(updated_post.updated_at);
+            }
             
-            sqlx::query(
+            let mut tx = self.pool.begin().await?;
+            // A visibility change can hide the post from some readers
+            if updated_post.visibility != was_visibility {
+                Self::record_tombstones(&mut tx, "id = ?1", id, &updated_post.updated_at.to_rfc3339()).await?;
+            }
+
+            // Ownership is checked again here, so nothing is written (tags included) if the post
+            // was trashed or changed hands after it was read above
+            let result = sqlx::query(
                 r#"
                 UPDATE posts 
-                SET title = ?, content = ?, updated_at = ?
-                WHERE id = ?
+                SET title = ?, content = ?, visibility = ?, edited_at = ?, updated_at = ?
+                WHERE id = ? AND author_id = ? AND deleted_at IS NULL
                 "#,
             )
             .bind(&updated_post.title)
             .bind(&updated_post.content)
+            .bind(updated_post.visibility.as_str())
+            .bind(updated_post.edited_at.map(|at| at.to_rfc3339()))
             .bind(updated_post.updated_at.to_rfc3339())
             .bind(id.to_string())
-            .execute(&self.pool)
+            .bind(author_id.to_string())
+            .execute(&mut *tx)
             .await?;
+            if result.rows_affected() == 0 {
+                return Ok(None);
+            }
+            if new_tags {
+                Self::set_tags(&mut tx, id, &updated_post.tags).await?;
+            }
+            tx.commit().await?;
 
             debug!("Post with id {} updated successfully", id);
         }
@@ -209,32 +573,289 @@ impl SqlPostRepository {
         Ok(Some(updated_post))
     }
 
+    // Moves `author_id`'s post to `status`. Publishing stamps published_at, unpublishing clears it;
+    // either counts as a change for the sync feed. Setting the current status again changes nothing
+    pub async fn set_status(&self, id: Uuid, author_id: Uuid, status: PostStatus) -> Result<Option<Post>> {
+        info!("Setting status of post {} to {}", id, status.as_str());
+
+        let mut post = match self.find_by_id(id).await? {
+            Some(post) if post.author_id == author_id => post,
+            _ => return Ok(None),
+        };
+        if post.status == status {
+            return Ok(Some(post));
+        }
+
+        let now = self.clock.now();
+        post.status = status;
+        post.published_at = (status == PostStatus::Published).then_some(now);
+        post.updated_at = now;
+
+        let mut tx = self.pool.begin().await?;
+        if status == PostStatus::Draft {
+            Self::record_tombstones(&mut tx, "id = ?1", id, &now.to_rfc3339()).await?;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE posts SET status = ?, published_at = ?, updated_at = ? WHERE id = ?
+            "#,
+        )
+        .bind(post.status.as_str())
+        .bind(post.published_at.map(|at| at.to_rfc3339()))
+        .bind(post.updated_at.to_rfc3339())
+        .bind(id.to_string())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(Some(post))
+    }
+
+    // Moves `author_id`'s post to their trash; it keeps its comments, likes and tags until purged.
+    // The sync feed reports it through the tombstone left behind
     pub async fn delete_post(&self, id: Uuid, author_id: Uuid) -> Result<bool> {
         info!("Deleting post with id: {}", id);
+
+        let now = self.clock.now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE posts SET deleted_at = ?1, updated_at = ?1
+            WHERE id = ?2 AND author_id = ?3 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(&now)
+        .bind(id.to_string())
+        .bind(author_id.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+        let deleted = result.rows_affected() > 0;
+        if deleted {
+            Self::record_tombstones(&mut tx, "id = ?1", id, &now).await?;
+        }
+        tx.commit().await?;
+
+        if deleted {
+            debug!("Post with id {} deleted successfully", id);
+        } else {
+            debug!("No post with id {} found to delete", id);
+        }
         
-        // First check if post exists and belongs to the author
-        let existing_post = self.find_by_id(id).await?;
-        if existing_post.is_none() {
+        Ok(deleted)
+    }
+
+    // Takes `author_id`'s post back out of their trash
+    pub async fn restore_post(&self, id: Uuid, author_id: Uuid) -> Result<Option<Post>> {
+        info!("Restoring post with id: {}", id);
+
+        let result = sqlx::query(
+            r#"
+            UPDATE posts SET deleted_at = NULL, updated_at = ?
+            WHERE id = ? AND author_id = ? AND deleted_at IS NOT NULL
+            "#,
+        )
+        .bind(self.clock.now().to_rfc3339())
+        .bind(id.to_string())
+        .bind(author_id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            debug!("No trashed post with id {} to restore", id);
+            return Ok(None);
+        }
+        self.find_by_id(id).await
+    }
+
+    // The author's trash, most recently deleted first
+    pub async fn find_trashed_by(&self, author_id: Uuid) -> Result<Vec<Post>> {
+        debug!("Finding trashed posts of author: {}", author_id);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, title, slug, content, author_id, visibility, status, published_at, edited_at, created_at, updated_at,
+                (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = posts.id) as tags
+            FROM posts WHERE author_id = ? AND deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC, id DESC
+            "#,
+        )
+        .bind(author_id.to_string())
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        rows.iter().map(Self::map_post).collect()
+    }
+
+    // Permanently removes a soft-deleted post `author_id` owns; live posts and anyone else's are left alone
+    pub async fn purge_post(&self, id: Uuid, author_id: Uuid) -> Result<bool> {
+        info!("Purging trashed post with id: {}", id);
+
+        let mut tx = self.pool.begin().await?;
+        let trashed: bool = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) > 0 FROM posts WHERE id = ? AND author_id = ? AND deleted_at IS NOT NULL
+            "#,
+        )
+        .bind(id.to_string())
+        .bind(author_id.to_string())
+        .fetch_one(&mut *tx)
+        .await?;
+        if !trashed {
+            debug!("No trashed post with id {} to purge", id);
             return Ok(false);
         }
 
-        let post = existing_post.unwrap();
-        if post.author_id != author_id {
-            return Ok(false); // Not authorized
+        // Child rows go first, foreign keys are enforced
+        for statement in [
+            "DELETE FROM post_covers WHERE post_id = ?",
+            "DELETE FROM post_tags WHERE post_id = ?",
+            "DELETE FROM comments WHERE post_id = ?",
+            "DELETE FROM post_likes WHERE post_id = ?",
+            "DELETE FROM post_reads WHERE post_id = ?",
+            "DELETE FROM post_reports WHERE post_id = ?",
+            "DELETE FROM posts WHERE id = ?",
+        ] {
+            sqlx::query(statement)
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        debug!("Post with id {} purged", id);
+        Ok(true)
+    }
+
+    // Permanently removes every post soft-deleted before `cutoff` along with its child rows, and returns how many went
+    pub async fn prune_trash(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        info!("Pruning posts trashed before {}", cutoff);
+
+        let mut tx = self.pool.begin().await?;
+
+        // Child rows go first, foreign keys are enforced
+        for statement in [
+            "DELETE FROM post_covers WHERE post_id IN (SELECT id FROM posts WHERE deleted_at < ?1)",
+            "DELETE FROM post_tags WHERE post_id IN (SELECT id FROM posts WHERE deleted_at < ?1)",
+            "DELETE FROM comments WHERE post_id IN (SELECT id FROM posts WHERE deleted_at < ?1)",
+            "DELETE FROM post_likes WHERE post_id IN (SELECT id FROM posts WHERE deleted_at < ?1)",
+            "DELETE FROM post_reads WHERE post_id IN (SELECT id FROM posts WHERE deleted_at < ?1)",
+            "DELETE FROM post_reports WHERE post_id IN (SELECT id FROM posts WHERE deleted_at < ?1)",
+        ] {
+            sqlx::query(statement)
+                .bind(cutoff.to_rfc3339())
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let result = sqlx::query(
+            r#"
+            DELETE FROM posts WHERE deleted_at < ?
+            "#,
+        )
+        .bind(cutoff.to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Forgets tombstones left before `cutoff`; sync clients with an older cursor should resync in full
+    pub async fn prune_tombstones(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        debug!("Pruning post tombstones older than {}", cutoff);
+
+        let result = sqlx::query(
+            r#"
+            DELETE FROM post_tombstones WHERE removed_at < ?
+            "#,
+        )
+        .bind(cutoff.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Moves the listed posts `author_id` owns to their trash in one transaction and returns their ids; the rest are left alone.
+    // In atomic mode nothing is deleted unless every id qualifies, though the qualifying ids are still returned
+    pub async fn delete_many(&self, ids: &[Uuid], author_id: Uuid, mode: BatchMode) -> Result<Vec<Uuid>> {
+        info!("Batch deleting {} posts for author: {}", ids.len(), author_id);
+
+        let now = self.clock.now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+        let mut deleted = Vec::new();
+
+        for id in ids {
+            let result = sqlx::query(
+                r#"
+                UPDATE posts SET deleted_at = ?1, updated_at = ?1
+                WHERE id = ?2 AND author_id = ?3 AND deleted_at IS NULL
+                "#,
+            )
+            .bind(&now)
+            .bind(id.to_string())
+            .bind(author_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+            if result.rows_affected() > 0 {
+                Self::record_tombstones(&mut tx, "id = ?1", *id, &now).await?;
+                deleted.push(*id);
+            }
         }
+
+        if mode == BatchMode::Atomic && deleted.len() < ids.len() {
+            tx.rollback().await?;
+            debug!("Rolled back batch delete, {} of {} posts qualified", deleted.len(), ids.len());
+            return Ok(deleted);
+        }
+        tx.commit().await?;
+
+        debug!("Batch deleted {} of {} posts", deleted.len(), ids.len());
+        Ok(deleted)
+    }
+
+    // Moderation delete that skips the ownership check, audited as `actor_id`
+    pub async fn delete_any(&self, id: Uuid, actor_id: Uuid) -> Result<bool> {
+        info!("Deleting post with id {} regardless of author", id);
         
+        let mut tx = self.pool.begin().await?;
+        Self::record_tombstones(&mut tx, "id = ?1", id, &self.clock.now().to_rfc3339()).await?;
+
+        // Child rows go first, foreign keys are enforced
+        for statement in [
+            "DELETE FROM post_covers WHERE post_id = ?",
+            "DELETE FROM post_tags WHERE post_id = ?",
+            "DELETE FROM comments WHERE post_id = ?",
+            "DELETE FROM post_likes WHERE post_id = ?",
+            "DELETE FROM post_reads WHERE post_id = ?",
+            "DELETE FROM post_reports WHERE post_id = ?",
+        ] {
+            sqlx::query(statement)
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
+
         let result = sqlx::query(
             r#"
             DELETE FROM posts WHERE id = ?
             "#,
         )
         .bind(id.to_string())
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
         let deleted = result.rows_affected() > 0;
         if deleted {
-            debug!("Post with id {} deleted successfully", id);
+            SqlAuditRepository::record(&mut tx, actor_id, AuditAction::PostForceDeleted, id).await?;
+        }
+
+        tx.commit().await?;
+
+        if deleted {
+            debug!("Post with id {} deleted by moderator", id);
         } else {
             debug!("No post with id {} found to delete", id);
         }
@@ -242,44 +863,605 @@ impl SqlPostRepository {
         Ok(deleted)
     }
 
-    pub async fn get_all_posts(&self) -> Result<Vec<PostResponse>> {
-        debug!("Getting all posts");
+    pub async fn set_cover(&self, post_id: Uuid, content_type: &str, data: &[u8]) -> Result<()> {
+        info!("Storing cover image for post: {}", post_id);
+        
+        sqlx::query(
+            r#"
+            INSERT INTO post_covers (post_id, content_type, data, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (post_id) DO UPDATE SET
+                content_type = excluded.content_type,
+                data = excluded.data,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(post_id.to_string())
+        .bind(content_type)
+        .bind(data)
+        .bind(self.clock.now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        debug!("Cover image stored for post {}", post_id);
+        Ok(())
+    }
+
+    // Returns the cover's content type and bytes
+    pub async fn find_cover(&self, post_id: Uuid) -> Result<Option<(String, Vec<u8>)>> {
+        debug!("Finding cover image for post: {}", post_id);
         
+        let row = sqlx::query(
+            r#"
+            SELECT content_type, data FROM post_covers WHERE post_id = ?
+            "#,
+        )
+        .bind(post_id.to_string())
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        Ok(row.map(|row| (row.get("content_type"), row.get("data"))))
+    }
+
+    // Replaces the post's tags with `tags`, inside the transaction writing the post itself
+    async fn set_tags(tx: &mut Transaction<'_, Sqlite>, post_id: Uuid, tags: &[String]) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM post_tags WHERE post_id = ?
+            "#,
+        )
+        .bind(post_id.to_string())
+        .execute(&mut **tx)
+        .await?;
+
+        for tag in tags {
+            sqlx::query(
+                r#"
+                INSERT INTO post_tags (post_id, tag) VALUES (?, ?)
+                "#,
+            )
+            .bind(post_id.to_string())
+            .bind(tag)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    // Liking twice is a no-op
+    pub async fn like_post(&self, post_id: Uuid, user_id: Uuid) -> Result<()> {
+        debug!("User {} liking post {}", user_id, post_id);
+
+        retry_busy(|| {
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO post_likes (post_id, user_id, created_at) VALUES (?, ?, ?)
+                "#,
+            )
+            .bind(post_id.to_string())
+            .bind(user_id.to_string())
+            .bind(self.clock.now().to_rfc3339())
+            .execute(&self.pool)
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn unlike_post(&self, post_id: Uuid, user_id: Uuid) -> Result<bool> {
+        debug!("User {} unliking post {}", user_id, post_id);
+
+        let result = sqlx::query(
+            r#"
+            DELETE FROM post_likes WHERE post_id = ? AND user_id = ?
+            "#,
+        )
+        .bind(post_id.to_string())
+        .bind(user_id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn count_likes(&self, post_id: Uuid) -> Result<i64> {
+        debug!("Counting likes of post {}", post_id);
+
+        let count = sqlx::query_scalar("SELECT COUNT(*) FROM post_likes WHERE post_id = ?")
+            .bind(post_id.to_string())
+            .fetch_one(&self.read_pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    // Posts `user_id` has liked and can still see, most recently liked first
+    pub async fn find_liked_by(&self, user_id: Uuid, limit: u32, offset: u32) -> Result<Vec<PostResponse>> {
+        debug!("Finding posts liked by user: {}", user_id);
+
         let rows = sqlx::query(
             r#"
             SELECT 
-                p.id, p.title, p.content, p.author_id, p.created_at, p.updated_at,
-                u.name as author_name, u.email as author_email, u.created_at as author_created_at, u.updated_at as author_updated_at
+                p.id, p.title, p.slug, p.content, p.author_id, p.visibility, p.status, p.published_at, p.edited_at, p.created_at, p.updated_at,
+                u.name as author_name, u.username as author_username, u.email as author_email, u.created_at as author_created_at, u.updated_at as author_updated_at,
+                EXISTS(SELECT 1 FROM post_covers c WHERE c.post_id = p.id) as has_cover,
+                (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = p.id) as tags
+            FROM post_likes l
+            JOIN posts p ON l.post_id = p.id
+            JOIN users u ON p.author_id = u.id
+            WHERE l.user_id = ?1 AND p.deleted_at IS NULL
+              AND (p.visibility != 'private' OR p.author_id = ?1)
+              AND (p.status = 'published' OR p.author_id = ?1)
+            ORDER BY l.created_at DESC, p.id
+            LIMIT ?2 OFFSET ?3
+            "#,
+        )
+        .bind(user_id.to_string())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        rows.iter().map(|row| self.map_post_with_author(row)).collect()
+    }
+
+    /// Marks whichever of `post_ids` `user_id` can see as read and returns those ids; reading twice keeps the first read_at.
+    /// In atomic mode nothing is marked unless every post is visible
+    pub async fn mark_read(&self, post_ids: &[Uuid], user_id: Uuid, mode: BatchMode) -> Result<Vec<Uuid>> {
+        debug!("User {} marking {} posts read", user_id, post_ids.len());
+
+        let ids = serde_json::to_string(&post_ids.iter().map(Uuid::to_string).collect::<Vec<_>>())?;
+        let read_at = self.clock.now().to_rfc3339();
+
+        let mut tx = self.pool.begin().await?;
+        let visible: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM posts
+            WHERE id IN (SELECT value FROM json_each(?2))
+              AND deleted_at IS NULL
+              AND (visibility != 'private' OR author_id = ?1)
+              AND (status = 'published' OR author_id = ?1)
+            "#,
+        )
+        .bind(user_id.to_string())
+        .bind(&ids)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if mode == BatchMode::Partial || visible.len() == post_ids.len() {
+            for post_id in &visible {
+                sqlx::query(
+                    r#"
+                    INSERT OR IGNORE INTO post_reads (post_id, user_id, read_at) VALUES (?, ?, ?)
+                    "#,
+                )
+                .bind(post_id)
+                .bind(user_id.to_string())
+                .bind(&read_at)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        tx.commit().await?;
+
+        visible.iter().map(|id| Ok(Uuid::parse_str(id)?)).collect()
+    }
+
+    /// Sets `read` on each post for a signed-in `viewer`; anonymous viewers have no read state
+    pub async fn with_read_state(&self, viewer: Option<Uuid>, mut posts: Vec<PostResponse>) -> Result<Vec<PostResponse>> {
+        let Some(viewer) = viewer else {
+            return Ok(posts);
+        };
+        if posts.is_empty() {
+            return Ok(posts);
+        }
+
+        let ids = serde_json::to_string(&posts.iter().map(|post| post.id.to_string()).collect::<Vec<_>>())?;
+        let read: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT post_id FROM post_reads
+            WHERE user_id = ? AND post_id IN (SELECT value FROM json_each(?))
+            "#,
+        )
+        .bind(viewer.to_string())
+        .bind(ids)
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        for post in &mut posts {
+            let id = post.id.to_string();
+            post.read = Some(read.contains(&id));
+        }
+        Ok(posts)
+    }
+
+    // Number of posts `find_liked_by` pages through
+    pub async fn count_liked_by(&self, user_id: Uuid) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM post_likes l
+            JOIN posts p ON l.post_id = p.id
+            WHERE l.user_id = ?1 AND p.deleted_at IS NULL
+              AND (p.visibility != 'private' OR p.author_id = ?1)
+              AND (p.status = 'published' OR p.author_id = ?1)
+            "#,
+        )
+        .bind(user_id.to_string())
+        .fetch_one(&self.read_pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    // Number of posts `find_feed` pages through
+    pub async fn count_feed(&self, user_id: Uuid) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM follows f
+            JOIN posts p ON p.author_id = f.followee_id
+            WHERE f.follower_id = ?1 AND p.deleted_at IS NULL
+              AND p.visibility = 'public' AND p.status = 'published'
+            "#,
+        )
+        .bind(user_id.to_string())
+        .fetch_one(&self.read_pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    // Listed posts by the users `user_id` follows, newest first
+    pub async fn find_feed(&self, user_id: Uuid, limit: u32, offset: u32) -> Result<Vec<PostResponse>> {
+        debug!("Finding feed of user: {}", user_id);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT 
+                p.id, p.title, p.slug, p.content, p.author_id, p.visibility, p.status, p.published_at, p.edited_at, p.created_at, p.updated_at,
+                u.name as author_name, u.username as author_username, u.email as author_email, u.created_at as author_created_at, u.updated_at as author_updated_at,
+                EXISTS(SELECT 1 FROM post_covers c WHERE c.post_id = p.id) as has_cover,
+                (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = p.id) as tags
+            FROM follows f
+            JOIN posts p ON p.author_id = f.followee_id
+            JOIN users u ON p.author_id = u.id
+            WHERE f.follower_id = ?1 AND p.deleted_at IS NULL
+              AND p.visibility = 'public' AND p.status = 'published'
+            ORDER BY p.created_at DESC, p.id DESC
+            LIMIT ?2 OFFSET ?3
+            "#,
+        )
+        .bind(user_id.to_string())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        rows.iter().map(|row| self.map_post_with_author(row)).collect()
+    }
+
+    // Number of posts `find_unread_by` pages through
+    pub async fn count_unread_by(&self, user_id: Uuid) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM posts p
+            WHERE p.deleted_at IS NULL
+              AND (p.visibility = 'public' OR (p.visibility = 'private' AND p.author_id = ?1))
+              AND (p.status = 'published' OR p.author_id = ?1)
+              AND NOT EXISTS(SELECT 1 FROM post_reads r WHERE r.post_id = p.id AND r.user_id = ?1)
+            "#,
+        )
+        .bind(user_id.to_string())
+        .fetch_one(&self.read_pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    // Listed posts (public plus the caller's private ones) `user_id` hasn't marked read, newest first
+    pub async fn find_unread_by(&self, user_id: Uuid, limit: u32, offset: u32) -> Result<Vec<PostResponse>> {
+        debug!("Finding posts unread by user: {}", user_id);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT 
+                p.id, p.title, p.slug, p.content, p.author_id, p.visibility, p.status, p.published_at, p.edited_at, p.created_at, p.updated_at,
+                u.name as author_name, u.username as author_username, u.email as author_email, u.created_at as author_created_at, u.updated_at as author_updated_at,
+                EXISTS(SELECT 1 FROM post_covers c WHERE c.post_id = p.id) as has_cover,
+                (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = p.id) as tags
             FROM posts p
             JOIN users u ON p.author_id = u.id
-            ORDER BY p.created_at DESC
+            WHERE p.deleted_at IS NULL
+              AND (p.visibility = 'public' OR (p.visibility = 'private' AND p.author_id = ?1))
+              AND (p.status = 'published' OR p.author_id = ?1)
+              AND NOT EXISTS(SELECT 1 FROM post_reads r WHERE r.post_id = p.id AND r.user_id = ?1)
+            ORDER BY p.created_at DESC, p.id DESC
+            LIMIT ?2 OFFSET ?3
             "#,
         )
-        .fetch_all(&self.pool)
+        .bind(user_id.to_string())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.read_pool)
         .await?;
 
-        let posts: Result<Vec<PostResponse>> = rows
-            .into_iter()
+        rows.iter()
             .map(|row| {
-                let author = UserResponse {
-                    id: Uuid::parse_str(&row.get::<String, _>("author_id"))?,
-                    name: row.get("author_name"),
-                    email: row.get("author_email"),
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("author_created_at"))?.with_timezone(&Utc),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("author_updated_at"))?.with_timezone(&Utc),
-                };
-
-                Ok(PostResponse {
-                    id: Uuid::parse_str(&row.get::<String, _>("id"))?,
-                    title: row.get("title"),
-                    content: row.get("content"),
-                    author,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
-                })
+                let mut post = self.map_post_with_author(row)?;
+                post.read = Some(false);
+                Ok(post)
+            })
+            .collect()
+    }
+
+    // Number of rows `tag_counts` pages through
+    pub async fn count_tags(&self) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(DISTINCT t.tag)
+            FROM post_tags t
+            JOIN posts p ON p.id = t.post_id
+            WHERE p.visibility = 'public' AND p.status = 'published' AND p.deleted_at IS NULL
+            "#,
+        )
+        .fetch_one(&self.read_pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    // Tags with how many public posts use them, most used first
+    pub async fn tag_counts(&self, limit: u32, offset: u32) -> Result<Vec<TagCount>> {
+        debug!("Counting tags (limit {}, offset {})", limit, offset);
+        
+        let rows = sqlx::query(
+            r#"
+            SELECT t.tag, COUNT(*) as count
+            FROM post_tags t
+            JOIN posts p ON p.id = t.post_id
+            WHERE p.visibility = 'public' AND p.status = 'published' AND p.deleted_at IS NULL
+            GROUP BY t.tag
+            ORDER BY count DESC, t.tag ASC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| TagCount {
+                tag: row.get("tag"),
+                count: row.get("count"),
             })
-            .collect();
+            .collect())
+    }
+
+    // Listing: public posts plus the viewer's own private ones; unlisted posts are reachable only by id.
+    // `filter` narrows, orders and pages it
+    pub async fn get_all_posts(&self, viewer: Option<Uuid>, filter: &PostListFilter) -> Result<Vec<PostResponse>> {
+        debug!("Getting all posts ({:?})", filter);
+        
+        let order_by = Self::order_by(filter.sort);
+        let sql = format!(
+            r#"
+            SELECT 
+                p.id, p.title, p.slug, p.content, p.author_id, p.visibility, p.status, p.published_at, p.edited_at, p.created_at, p.updated_at,
+                u.name as author_name, u.username as author_username, u.email as author_email, u.created_at as author_created_at, u.updated_at as author_updated_at,
+                EXISTS(SELECT 1 FROM post_covers c WHERE c.post_id = p.id) as has_cover,
+                (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = p.id) as tags
+            FROM posts p
+            JOIN users u ON p.author_id = u.id
+            WHERE p.deleted_at IS NULL
+              AND (p.visibility = 'public' OR (p.visibility = 'private' AND p.author_id = ?1))
+              AND (p.status = 'published' OR p.author_id = ?1)
+              AND {LISTING_FILTERS}
+            ORDER BY {order_by}
+            LIMIT ?5 OFFSET ?6
+            "#,
+        );
+        let filter = filter.clone();
+        let rows = within_deadline(&self.read_pool, self.deadline, move |conn| {
+            async move {
+                Self::bind_listing(sqlx::query(&sql), viewer, &filter)
+                    .fetch_all(conn)
+                    .await
+            }
+            .boxed()
+        })
+        .await?;
+
+        rows.iter().map(|row| self.map_post_with_author(row)).collect()
+    }
+
+    // How many posts the listing matches across all pages; `filter`'s sort and page are ignored
+    pub async fn count_posts(&self, viewer: Option<Uuid>, filter: &PostListFilter) -> Result<i64> {
+        debug!("Counting posts ({:?})", filter);
+
+        let sql = format!(
+            r#"
+            SELECT COUNT(*)
+            FROM posts p
+            WHERE p.deleted_at IS NULL
+              AND (p.visibility = 'public' OR (p.visibility = 'private' AND p.author_id = ?1))
+              AND (p.status = 'published' OR p.author_id = ?1)
+              AND {LISTING_FILTERS}
+            "#,
+        );
+        let filter = filter.clone();
+        let count = within_deadline(&self.read_pool, self.deadline, move |conn| {
+            async move {
+                Self::bind_filters(sqlx::query(&sql), viewer, &filter)
+                    .fetch_one(conn)
+                    .await
+                    .map(|row| row.get::<i64, _>(0))
+            }
+            .boxed()
+        })
+        .await?;
+
+        Ok(count)
+    }
+
+    // Lean listing without the users join; posts carry only their author_id
+    pub async fn get_all_posts_lean(&self, viewer: Option<Uuid>, filter: &PostListFilter) -> Result<Vec<Post>> {
+        debug!("Getting all posts without author info ({:?})", filter);
+        
+        let order_by = Self::order_by(filter.sort);
+        let sql = format!(
+            r#"
+            SELECT p.id, p.title, p.slug, p.content, p.author_id, p.visibility, p.status, p.published_at, p.edited_at, p.created_at, p.updated_at,
+                (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = p.id) as tags
+            FROM posts p
+            WHERE p.deleted_at IS NULL
+              AND (p.visibility = 'public' OR (p.visibility = 'private' AND p.author_id = ?1))
+              AND (p.status = 'published' OR p.author_id = ?1)
+              AND {LISTING_FILTERS}
+            ORDER BY {order_by}
+            LIMIT ?5 OFFSET ?6
+            "#,
+        );
+        let filter = filter.clone();
+        let rows = within_deadline(&self.read_pool, self.deadline, move |conn| {
+            async move {
+                Self::bind_listing(sqlx::query(&sql), viewer, &filter)
+                    .fetch_all(conn)
+                    .await
+            }
+            .boxed()
+        })
+        .await?;
 
-        posts
+        rows.iter().map(Self::map_post).collect()
     }
-} 
\ No newline at end of file
+
+    // Binds ?1 to ?4 of a listing query: viewer, then the LISTING_FILTERS parameters
+    fn bind_filters<'q>(
+        query: Query<'q, Sqlite, SqliteArguments<'q>>,
+        viewer: Option<Uuid>,
+        filter: &PostListFilter,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        query
+            .bind(viewer.map(|id| id.to_string()))
+            .bind(filter.tag.clone())
+            .bind(filter.author.clone())
+            .bind(filter.since.map(|since| since.to_rfc3339()))
+    }
+
+    // Binds ?1 to ?6 of a listing query: what bind_filters does, then the page
+    fn bind_listing<'q>(
+        query: Query<'q, Sqlite, SqliteArguments<'q>>,
+        viewer: Option<Uuid>,
+        filter: &PostListFilter,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        Self::bind_filters(query, viewer, filter)
+            // SQLite reads a negative LIMIT as no limit
+            .bind(filter.limit.map_or(-1, i64::from))
+            .bind(filter.offset)
+    }
+
+    // ORDER BY for a listing of `posts p`; the id tie-break keeps pages stable
+    fn order_by(sort: PostSort) -> String {
+        let column = match sort.field {
+            PostSortField::CreatedAt => "created_at",
+            PostSortField::UpdatedAt => "updated_at",
+            PostSortField::Title => "title COLLATE NOCASE",
+        };
+        let direction = if sort.descending { "DESC" } else { "ASC" };
+        format!("p.{column} {direction}, p.id {direction}")
+    }
+
+    // Every live post regardless of visibility, oldest first, read row by row from a cursor
+    pub fn stream_all(&self) -> impl Stream<Item = Result<Post>> + '_ {
+        debug!("Streaming all posts");
+
+        sqlx::query(
+            r#"
+            SELECT id, title, slug, content, author_id, visibility, status, published_at, edited_at, created_at, updated_at,
+                (SELECT json_group_array(t.tag) FROM post_tags t WHERE t.post_id = posts.id) as tags
+            FROM posts
+            WHERE deleted_at IS NULL
+            ORDER BY created_at ASC
+            "#,
+        )
+        .fetch(&self.read_pool)
+        .map(|row| Self::map_post(&row?))
+    }
+
+    fn map_post(row: &SqliteRow) -> Result<Post> {
+        Ok(Post {
+            id: get_uuid(row, "id")?,
+            title: row.get("title"),
+            slug: row.get("slug"),
+            content: row.get("content"),
+            author_id: get_uuid(row, "author_id")?,
+            visibility: Self::map_visibility(row)?,
+            status: Self::map_status(row)?,
+            published_at: get_optional_datetime(row, "published_at")?,
+            edited_at: get_optional_datetime(row, "edited_at")?,
+            tags: Self::map_tags(row)?,
+            created_at: get_datetime(row, "created_at")?,
+            updated_at: get_datetime(row, "updated_at")?,
+        })
+    }
+
+    // Maps a row from the posts/users join into a PostResponse
+    fn map_post_with_author(&self, row: &SqliteRow) -> Result<PostResponse> {
+        let author = PostAuthor::Full(UserResponse {
+            id: get_uuid(row, "author_id")?,
+            username: row.get("author_username"),
+            name: row.get("author_name"),
+            email: row.get("author_email"),
+            created_at: get_datetime(row, "author_created_at")?,
+            updated_at: get_datetime(row, "author_updated_at")?,
+        });
+
+        let id = get_uuid(row, "id")?;
+        let cover_url = row.get::<bool, _>("has_cover").then(|| format!("{}/posts/{}/cover", self.api_prefix, id));
+        let content: String = row.get("content");
+        let edited_at = get_optional_datetime(row, "edited_at")?;
+
+        Ok(PostResponse {
+            id,
+            title: row.get("title"),
+            slug: row.get("slug"),
+            reading_time_minutes: Self::reading_time_minutes(&content, self.reading_wpm),
+            content,
+            author,
+            visibility: Self::map_visibility(row)?,
+            status: Self::map_status(row)?,
+            published_at: get_optional_datetime(row, "published_at")?,
+            edited_at,
+            tags: Self::map_tags(row)?,
+            cover_url,
+            read: None,
+            is_edited: edited_at.is_some(),
+            created_at: get_datetime(row, "created_at")?,
+            updated_at: get_datetime(row, "updated_at")?,
+        })
+    }
+
+    // Whole minutes, rounded up, and never less than one
+    fn reading_time_minutes(content: &str, reading_wpm: u32) -> u32 {
+        let words = content.split_whitespace().count() as u32;
+        words.div_ceil(reading_wpm).max(1)
+    }
+
+    fn map_visibility(row: &SqliteRow) -> Result<Visibility> {
+        row.get::<String, _>("visibility").parse().map_err(anyhow::Error::msg)
+    }
+
+    fn map_status(row: &SqliteRow) -> Result<PostStatus> {
+        row.get::<String, _>("status").parse().map_err(anyhow::Error::msg)
+    }
+
+    // `tags` column is a JSON array built by json_group_array
+    fn map_tags(row: &SqliteRow) -> Result<Vec<String>> {
+        Ok(serde_json::from_str(&row.get::<String, _>("tags"))?)
+    }
+}
@@ -0,0 +1,105 @@
+use sqlx::{sqlite::{SqlitePool, SqliteRow}, Row};
+use anyhow::Result;
+use uuid::Uuid;
+use chrono::Utc;
+use crate::model::model::{PostReport, ReportedPost};
+use crate::db::row::{get_datetime, get_uuid};
+use crate::db::sql_db::retry_busy;
+use tracing::{debug, info};
+
+pub struct SqlReportRepository {
+    pool: SqlitePool,
+}
+
+impl SqlReportRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        debug!("Creating new SqlReportRepository");
+        Self { pool }
+    }
+
+    /// Fails with a unique violation if `reporter_id` already reported the post
+    pub async fn create_report(&self, post_id: Uuid, reporter_id: Uuid, reason: String) -> Result<PostReport> {
+        info!("Recording report of post {} by user {}", post_id, reporter_id);
+
+        let report = PostReport {
+            id: Uuid::new_v4(),
+            post_id,
+            reporter_id,
+            reason,
+            created_at: Utc::now(),
+        };
+
+        retry_busy(|| {
+            sqlx::query(
+                r#"
+                INSERT INTO post_reports (id, post_id, reporter_id, reason, created_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(report.id.to_string())
+            .bind(report.post_id.to_string())
+            .bind(report.reporter_id.to_string())
+            .bind(&report.reason)
+            .bind(report.created_at.to_rfc3339())
+            .execute(&self.pool)
+        })
+        .await?;
+
+        debug!("Report created successfully: id={}", report.id);
+        Ok(report)
+    }
+
+    // Live posts with at least one report, most reported first
+    pub async fn list_reported_posts(&self, limit: u32, offset: u32) -> Result<Vec<ReportedPost>> {
+        debug!("Listing reported posts (limit {}, offset {})", limit, offset);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT r.post_id, p.title, p.author_id,
+                COUNT(*) as report_count,
+                MAX(r.created_at) as last_reported_at,
+                json_group_array(r.reason) as reasons
+            FROM post_reports r
+            JOIN posts p ON p.id = r.post_id
+            WHERE p.deleted_at IS NULL
+            GROUP BY r.post_id
+            ORDER BY report_count DESC, last_reported_at DESC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::map_reported_post).collect()
+    }
+
+    // Number of posts `list_reported_posts` pages through
+    pub async fn count_reported_posts(&self) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(DISTINCT r.post_id)
+            FROM post_reports r
+            JOIN posts p ON p.id = r.post_id
+            WHERE p.deleted_at IS NULL
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    fn map_reported_post(row: &SqliteRow) -> Result<ReportedPost> {
+        Ok(ReportedPost {
+            post_id: get_uuid(row, "post_id")?,
+            title: row.get("title"),
+            author_id: get_uuid(row, "author_id")?,
+            report_count: row.get("report_count"),
+            // `reasons` column is a JSON array built by json_group_array
+            reasons: serde_json::from_str(&row.get::<String, _>("reasons"))?,
+            last_reported_at: get_datetime(row, "last_reported_at")?,
+        })
+    }
+}
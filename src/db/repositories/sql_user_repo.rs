@@ -1,52 +1,127 @@
-use sqlx::{sqlite::SqlitePool, Row};
+use sqlx::{sqlite::{SqlitePool, SqliteRow}, Row};
 use anyhow::Result;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
-use crate::model::model::{User, CreateUserRequest, UpdateUserRequest, UserResponse};
+use std::sync::Arc;
+use crate::model::model::{User, CreateUserRequest, UpdateUserRequest, UserResponse, Role, AuditAction};
+use crate::db::repositories::sql_audit_repo::SqlAuditRepository;
+use crate::db::repositories::sql_post_repo::SqlPostRepository;
+use crate::config::PostDeletion;
+use crate::helpers::clock::{Clock, SystemClock};
+use crate::helpers::validation::{is_reserved_username, username_from_email};
+use crate::db::row::{get_datetime, get_uuid, parse_datetime};
+use crate::db::sql_db::retry_busy;
 use tracing::{debug, info};
 
+/// Most users `get_all_users` returns in one page, whatever the caller asks for
+pub const MAX_USERS_PER_PAGE: u32 = 100;
+
 pub struct SqlUserRepository {
     pool: SqlitePool,
+    // Pool for find_*/get_all_* style reads; the primary unless a replica is attached
+    read_pool: SqlitePool,
+    clock: Arc<dyn Clock>,
 }
 
 impl SqlUserRepository {
     pub fn new(pool: SqlitePool) -> Self {
         debug!("Creating new SqlUserRepository");
-        Self { pool }
+        Self { read_pool: pool.clone(), pool, clock: Arc::new(SystemClock) }
+    }
+
+    /// Sends reads to `read_pool` (a replica); writes keep using the primary
+    pub fn with_read_pool(mut self, read_pool: SqlitePool) -> Self {
+        self.read_pool = read_pool;
+        self
     }
 
-    pub async fn create_user(&self, user_data: CreateUserRequest, hashed_password: String) -> Result<User> {
+    /// Stamps created_at, updated_at and deletion times from `clock` instead of the wall clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn create_user(&self, user_data: CreateUserRequest, hashed_password: String, role: Role) -> Result<User> {
         let id = Uuid::new_v4();
-        let now = Utc::now();
+        let now = self.clock.now();
         
         info!("Creating new user with email: {}", user_data.email);
+
+        // A requested username is used as is; otherwise one is derived from the email
+        let username = match user_data.username {
+            Some(username) => username,
+            None => self.unique_username(&username_from_email(&user_data.email)).await?,
+        };
         
         let user = User {
             id,
+            username,
             name: user_data.name,
             email: user_data.email,
             password: hashed_password,
+            role,
+            email_verified: false,
             created_at: now,
             updated_at: now,
         };
 
-        sqlx::query(
+        retry_busy(|| {
+            sqlx::query(
+                r#"
+                INSERT INTO users (id, username, name, email, password, role, created_at, updated_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(user.id.to_string())
+            .bind(&user.username)
+            .bind(&user.name)
+            .bind(&user.email)
+            .bind(&user.password)
+            .bind(user.role.as_str())
+            .bind(user.created_at.to_rfc3339())
+            .bind(user.updated_at.to_rfc3339())
+            .execute(&self.pool)
+        })
+        .await?;
+
+        debug!("User created successfully: id={}", user.id);
+        Ok(user)
+    }
+
+    // Following someone twice keeps the first follow
+    pub async fn follow(&self, follower_id: Uuid, followee_id: Uuid) -> Result<()> {
+        debug!("User {} following user {}", follower_id, followee_id);
+
+        retry_busy(|| {
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO follows (follower_id, followee_id, created_at) VALUES (?, ?, ?)
+                "#,
+            )
+            .bind(follower_id.to_string())
+            .bind(followee_id.to_string())
+            .bind(self.clock.now().to_rfc3339())
+            .execute(&self.pool)
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn unfollow(&self, follower_id: Uuid, followee_id: Uuid) -> Result<bool> {
+        debug!("User {} unfollowing user {}", follower_id, followee_id);
+
+        let result = sqlx::query(
             r#"
-            INSERT INTO users (id, name, email, password, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?)
+            DELETE FROM follows WHERE follower_id = ? AND followee_id = ?
             "#,
         )
-        .bind(user.id.to_string())
-        .bind(&user.name)
-        .bind(&user.email)
-        .bind(&user.password)
-        .bind(user.created_at.to_rfc3339())
-        .bind(user.updated_at.to_rfc3339())
+        .bind(follower_id.to_string())
+        .bind(followee_id.to_string())
         .execute(&self.pool)
         .await?;
 
-        debug!("User created successfully: id={}", user.id);
-        Ok(user)
+        Ok(result.rows_affected() > 0)
     }
 
     pub async fn find_by_id(&self, id: Uuid) -> Result<Option<User>> {
@@ -54,24 +129,17 @@ impl SqlUserRepository {
         
         let row = sqlx::query(
             r#"
-            SELECT id, name, email, password, created_at, updated_at
-            FROM users WHERE id = ?
+            SELECT id, username, name, email, password, role, email_verified_at, created_at, updated_at
+            FROM users WHERE id = ? AND deleted_at IS NULL
             "#,
         )
         .bind(id.to_string())
-        .fetch_optional(&self.pool)
+        .fetch_optional(&self.read_pool)
         .await?;
 
         match row {
             Some(row) => {
-                let user = User {
-                    id: Uuid::parse_str(&row.get::<String, _>("id"))?,
-                    name: row.get("name"),
-                    email: row.get("email"),
-                    password: row.get("password"),
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
-                };
+                let user = Self::map_user(&row)?;
                 debug!("User with id {} found", id);
                 Ok(Some(user))
             }
@@ -87,24 +155,17 @@ impl SqlUserRepository {
         
         let row = sqlx::query(
             r#"
-            SELECT id, name, email, password, created_at, updated_at
-            FROM users WHERE email = ?
+            SELECT id, username, name, email, password, role, email_verified_at, created_at, updated_at
+            FROM users WHERE email = ? AND deleted_at IS NULL
             "#,
         )
         .bind(email)
-        .fetch_optional(&self.pool)
+        .fetch_optional(&self.read_pool)
         .await?;
 
         match row {
             Some(row) => {
-                let user = User {
-                    id: Uuid::parse_str(&row.get::<String, _>("id"))?,
-                    name: row.get("name"),
-                    email: row.get("email"),
-                    password: row.get("password"),
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
-                };
+                let user = Self::map_user(&row)?;
                 debug!("User with email {} found", email);
                 Ok(Some(user))
             }
@@ -115,16 +176,68 @@ impl SqlUserRepository {
         }
     }
 
+    pub async fn find_by_username(&self, username: &str) -> Result<Option<User>> {
+        debug!("Finding user by username: {}", username);
+
+        let row = sqlx::query(
+            r#"
+            SELECT id, username, name, email, password, role, email_verified_at, created_at, updated_at
+            FROM users WHERE username = ? AND deleted_at IS NULL
+            "#,
+        )
+        .bind(username)
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        row.as_ref().map(Self::map_user).transpose()
+    }
+
+    // Usernames stay taken after a soft delete
+    pub async fn username_exists(&self, username: &str) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM users WHERE username = ?)
+            "#,
+        )
+        .bind(username)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(exists)
+    }
+
+    // `base`, or `base_2`, `base_3`, ... for the first one that is neither taken nor reserved
+    async fn unique_username(&self, base: &str) -> Result<String> {
+        let taken: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT username FROM users WHERE username LIKE ? || '%'
+            "#,
+        )
+        .bind(base)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let username = std::iter::once(base.to_string())
+            .chain((2..).map(|n| format!("{}_{}", base, n)))
+            .find(|candidate| !taken.contains(candidate) && !is_reserved_username(candidate))
+            .expect("an unbounded sequence has a free username");
+        Ok(username)
+    }
+
     pub async fn update_user(&self, id: Uuid, update_data: UpdateUserRequest) -> Result<Option<User>> {
         info!("Updating user with id: {}", id);
         
         // First check if user exists
         let existing_user = self.find_by_id(id).await?;
-        if existing_user.is_none() {
-            return Ok(None);
+        match existing_user {
+            Some(user) => Ok(Some(self.apply_user_update(user, update_data).await?)),
+            None => Ok(None),
         }
+    }
 
-        let mut user = existing_user.unwrap();
+    // Applies an update to an already-loaded user, writing only if something changed
+    pub async fn apply_user_update(&self, mut user: User, update_data: UpdateUserRequest) -> Result<User> {
+        let id = user.id;
         let mut updated = false;
 
         if let Some(name) = update_data.name {
@@ -133,6 +246,10 @@ impl SqlUserRepository {
         }
 
         if let Some(email) = update_data.email {
+            // A new address has to be verified again
+            if email != user.email {
+                user.email_verified = false;
+            }
             user.email = email;
             updated = true;
         }
@@ -143,12 +260,13 @@ impl SqlUserRepository {
         }
 
         if updated {
-            user.updated_at = Utc::now();
+            user.updated_at = self.clock.now();
             
             sqlx::query(
                 r#"
                 UPDATE users 
-                SET name = ?, email = ?, password = ?, updated_at = ?
+                SET name = ?, email = ?, password = ?, updated_at = ?,
+                    email_verified_at = CASE WHEN ? THEN email_verified_at ELSE NULL END
                 WHERE id = ?
                 "#,
             )
@@ -156,6 +274,7 @@ impl SqlUserRepository {
             .bind(&user.email)
             .bind(&user.password)
             .bind(user.updated_at.to_rfc3339())
+            .bind(user.email_verified)
             .bind(id.to_string())
             .execute(&self.pool)
             .await?;
@@ -163,22 +282,285 @@ impl SqlUserRepository {
             debug!("User with id {} updated successfully", id);
         }
 
-        Ok(Some(user))
+        Ok(user)
+    }
+
+    // Replaces the stored hash without touching updated_at (used for transparent rehashing)
+    pub async fn set_password(&self, id: Uuid, hashed_password: &str) -> Result<()> {
+        info!("Replacing password hash for user: {}", id);
+        
+        sqlx::query(
+            r#"
+            UPDATE users SET password = ? WHERE id = ?
+            "#,
+        )
+        .bind(hashed_password)
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
     }
 
-    pub async fn delete_user(&self, id: Uuid) -> Result<bool> {
-        info!("Deleting user with id: {}", id);
+    pub async fn set_email_verified(&self, id: Uuid) -> Result<()> {
+        info!("Marking email verified for user: {}", id);
         
+        sqlx::query(
+            r#"
+            UPDATE users SET email_verified_at = COALESCE(email_verified_at, ?) WHERE id = ?
+            "#,
+        )
+        .bind(self.clock.now().to_rfc3339())
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Role change, audited as `actor_id`
+    pub async fn set_role(&self, id: Uuid, role: Role, actor_id: Uuid) -> Result<Option<User>> {
+        info!("Setting role of user {} to {}", id, role.as_str());
+        
+        let mut tx = self.pool.begin().await?;
+
         let result = sqlx::query(
             r#"
-            DELETE FROM users WHERE id = ?
+            UPDATE users SET role = ?, updated_at = ? WHERE id = ? AND deleted_at IS NULL
             "#,
         )
+        .bind(role.as_str())
+        .bind(self.clock.now().to_rfc3339())
         .bind(id.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            debug!("No user with id {} found to update role", id);
+            return Ok(None);
+        }
+
+        SqlAuditRepository::record(&mut tx, actor_id, AuditAction::UserRoleChanged, id).await?;
+        tx.commit().await?;
+
+        self.find_by_id(id).await
+    }
+
+    pub async fn count_admins(&self) -> Result<i64> {
+        debug!("Counting admin users");
+        
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM users WHERE role = ? AND deleted_at IS NULL
+            "#,
+        )
+        .bind(Role::Admin.as_str())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    pub async fn set_last_seen(&self, id: Uuid, seen_at: DateTime<Utc>) -> Result<()> {
+        debug!("Recording activity for user: {}", id);
+        
+        retry_busy(|| {
+            sqlx::query(
+                r#"
+                UPDATE users SET last_seen_at = ? WHERE id = ?
+                "#,
+            )
+            .bind(seen_at.to_rfc3339())
+            .bind(id.to_string())
+            .execute(&self.pool)
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    // Records a new session for `user_id`, then revokes their oldest live ones beyond `max`
+    pub async fn start_session(&self, id: Uuid, user_id: Uuid, expires_at: DateTime<Utc>, max: u32) -> Result<()> {
+        debug!("Starting session {} for user: {}", id, user_id);
+
+        let now = self.clock.now();
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (id, user_id, created_at, expires_at) VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(id.to_string())
+        .bind(user_id.to_string())
+        .bind(now.to_rfc3339())
+        .bind(expires_at.to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+
+        // Newest first; rowid breaks ties between logins in the same instant
+        let revoked = sqlx::query(
+            r#"
+            UPDATE sessions SET revoked_at = ?1
+            WHERE id IN (
+                SELECT id FROM sessions
+                WHERE user_id = ?2 AND revoked_at IS NULL AND expires_at > ?1
+                ORDER BY created_at DESC, rowid DESC
+                LIMIT -1 OFFSET ?3
+            )
+            "#,
+        )
+        .bind(now.to_rfc3339())
+        .bind(user_id.to_string())
+        .bind(max)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        if revoked.rows_affected() > 0 {
+            info!("Revoked {} oldest session(s) of user {}", revoked.rows_affected(), user_id);
+        }
+        Ok(())
+    }
+
+    // Drops sessions past their expiry; their tokens no longer verify, revoked or not
+    pub async fn prune_sessions(&self) -> Result<u64> {
+        debug!("Pruning expired sessions");
+
+        let result = sqlx::query(
+            r#"
+            DELETE FROM sessions WHERE expires_at <= ?
+            "#,
+        )
+        .bind(self.clock.now().to_rfc3339())
         .execute(&self.pool)
         .await?;
 
+        Ok(result.rows_affected())
+    }
+
+    pub async fn is_session_revoked(&self, id: Uuid) -> Result<bool> {
+        let revoked: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM sessions WHERE id = ? AND revoked_at IS NOT NULL)
+            "#,
+        )
+        .bind(id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(revoked)
+    }
+
+    // Whether `id` still has an account; tokens outlive deleted ones
+    pub async fn is_live(&self, id: Uuid) -> Result<bool> {
+        let live: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM users WHERE id = ? AND deleted_at IS NULL)
+            "#,
+        )
+        .bind(id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(live)
+    }
+
+    pub async fn find_last_seen(&self, id: Uuid) -> Result<Option<DateTime<Utc>>> {
+        debug!("Finding last activity for user: {}", id);
+        
+        let last_seen: Option<Option<String>> = sqlx::query_scalar(
+            r#"
+            SELECT last_seen_at FROM users WHERE id = ?
+            "#,
+        )
+        .bind(id.to_string())
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        last_seen.flatten().as_deref().map(parse_datetime).transpose()
+    }
+
+    // Deletes the user and their posts together so no post is left pointing at a missing author,
+    // audited as `actor_id`
+    pub async fn delete_user(&self, id: Uuid, post_deletion: PostDeletion, actor_id: Uuid) -> Result<bool> {
+        info!("Deleting user with id: {} ({:?} post deletion)", id, post_deletion);
+        
+        let mut tx = self.pool.begin().await?;
+
+        let result = match post_deletion {
+            // Soft-deleted posts still reference the user, so its row stays as a tombstone
+            // with the email and username released for re-registration. Comments by a deleted user are hidden by
+            // their queries; likes, follows and sessions carry nothing worth keeping and go now
+            PostDeletion::Soft => {
+                let now = self.clock.now().to_rfc3339();
+                SqlPostRepository::record_tombstones(&mut tx, "author_id = ?1 AND deleted_at IS NULL", id, &now).await?;
+
+                for statement in [
+                    "UPDATE posts SET deleted_at = ?2, updated_at = ?2 WHERE author_id = ?1 AND deleted_at IS NULL",
+                    "UPDATE sessions SET revoked_at = ?2 WHERE user_id = ?1 AND revoked_at IS NULL",
+                    "DELETE FROM post_likes WHERE user_id = ?1",
+                    "DELETE FROM follows WHERE follower_id = ?1 OR followee_id = ?1",
+                ] {
+                    sqlx::query(statement)
+                        .bind(id.to_string())
+                        .bind(&now)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+
+                sqlx::query(
+                    r#"
+                    UPDATE users
+                    SET deleted_at = ?, email = 'deleted:' || id, username = 'deleted_' || replace(id, '-', ''), password = ''
+                    WHERE id = ? AND deleted_at IS NULL
+                    "#,
+                )
+                .bind(&now)
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await?
+            },
+            PostDeletion::Hard => {
+                let now = self.clock.now().to_rfc3339();
+                SqlPostRepository::record_tombstones(&mut tx, "author_id = ?1 AND deleted_at IS NULL", id, &now).await?;
+
+                for statement in [
+                    "DELETE FROM post_covers WHERE post_id IN (SELECT id FROM posts WHERE author_id = ?1)",
+                    "DELETE FROM post_tags WHERE post_id IN (SELECT id FROM posts WHERE author_id = ?1)",
+                    "DELETE FROM comments WHERE author_id = ?1 OR post_id IN (SELECT id FROM posts WHERE author_id = ?1)",
+                    "DELETE FROM post_likes WHERE user_id = ?1 OR post_id IN (SELECT id FROM posts WHERE author_id = ?1)",
+                    "DELETE FROM post_reads WHERE user_id = ?1 OR post_id IN (SELECT id FROM posts WHERE author_id = ?1)",
+                    "DELETE FROM post_reports WHERE reporter_id = ?1 OR post_id IN (SELECT id FROM posts WHERE author_id = ?1)",
+                    "DELETE FROM follows WHERE follower_id = ?1 OR followee_id = ?1",
+                    "DELETE FROM sessions WHERE user_id = ?1",
+                    "DELETE FROM posts WHERE author_id = ?1",
+                ] {
+                    sqlx::query(statement)
+                        .bind(id.to_string())
+                        .execute(&mut *tx)
+                        .await?;
+                }
+
+                sqlx::query(
+                    r#"
+                    DELETE FROM users WHERE id = ?
+                    "#,
+                )
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await?
+            },
+        };
+
         let deleted = result.rows_affected() > 0;
+        if deleted {
+            SqlAuditRepository::record(&mut tx, actor_id, AuditAction::UserDeleted, id).await?;
+        }
+
+        tx.commit().await?;
+
         if deleted {
             debug!("User with id {} deleted successfully", id);
         } else {
@@ -188,32 +570,53 @@ impl SqlUserRepository {
         Ok(deleted)
     }
 
-    pub async fn get_all_users(&self) -> Result<Vec<UserResponse>> {
-        debug!("Getting all users");
+    // One page of users, newest first; `limit` is clamped to 1..=MAX_USERS_PER_PAGE
+    pub async fn get_all_users(&self, limit: u32, offset: u32) -> Result<Vec<UserResponse>> {
+        let limit = limit.clamp(1, MAX_USERS_PER_PAGE);
+        debug!("Getting users (limit {}, offset {})", limit, offset);
         
         let rows = sqlx::query(
             r#"
-            SELECT id, name, email, created_at, updated_at
+            SELECT id, username, name, email, created_at, updated_at
             FROM users
-            ORDER BY created_at DESC
+            WHERE deleted_at IS NULL
+            ORDER BY created_at DESC, id
+            LIMIT ? OFFSET ?
             "#,
         )
-        .fetch_all(&self.pool)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.read_pool)
         .await?;
 
         let users: Result<Vec<UserResponse>> = rows
             .into_iter()
             .map(|row| {
                 Ok(UserResponse {
-                    id: Uuid::parse_str(&row.get::<String, _>("id"))?,
+                    id: get_uuid(&row, "id")?,
+                    username: row.get("username"),
                     name: row.get("name"),
                     email: row.get("email"),
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
+                    created_at: get_datetime(&row, "created_at")?,
+                    updated_at: get_datetime(&row, "updated_at")?,
                 })
             })
             .collect();
 
         users
     }
+
+    fn map_user(row: &SqliteRow) -> Result<User> {
+        Ok(User {
+            id: get_uuid(row, "id")?,
+            username: row.get("username"),
+            name: row.get("name"),
+            email: row.get("email"),
+            password: row.get("password"),
+            role: row.get::<String, _>("role").parse().map_err(anyhow::Error::msg)?,
+            email_verified: row.get::<Option<String>, _>("email_verified_at").is_some(),
+            created_at: get_datetime(row, "created_at")?,
+            updated_at: get_datetime(row, "updated_at")?,
+        })
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,67 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{sqlite::SqliteRow, Row};
+use uuid::Uuid;
+
+// Column readers shared by the repositories; ids and timestamps are stored as TEXT
+
+/// The UUID stored in `column`
+pub fn get_uuid(row: &SqliteRow, column: &str) -> Result<Uuid> {
+    let value: String = row.try_get(column)?;
+    Uuid::parse_str(&value).with_context(|| format!("Column {} holds an invalid UUID: {:?}", column, value))
+}
+
+/// The RFC 3339 timestamp stored in `column`
+pub fn get_datetime(row: &SqliteRow, column: &str) -> Result<DateTime<Utc>> {
+    let value: String = row.try_get(column)?;
+    parse_datetime(&value).with_context(|| format!("Column {} holds an invalid timestamp", column))
+}
+
+/// Like `get_datetime`, for a nullable column
+pub fn get_optional_datetime(row: &SqliteRow, column: &str) -> Result<Option<DateTime<Utc>>> {
+    let value: Option<String> = row.try_get(column)?;
+    value
+        .map(|value| parse_datetime(&value).with_context(|| format!("Column {} holds an invalid timestamp", column)))
+        .transpose()
+}
+
+/// A stored RFC 3339 timestamp, for values read without a row (e.g. `query_scalar`)
+pub fn parse_datetime(value: &str) -> Result<DateTime<Utc>> {
+    let parsed = DateTime::parse_from_rfc3339(value).with_context(|| format!("Invalid timestamp: {:?}", value))?;
+    Ok(parsed.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::SqlitePool;
+
+    async fn sample_row(sql: &str) -> SqliteRow {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::query(sql).fetch_one(&pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_row_helpers_read_stored_values() {
+        let row = sample_row(
+            "SELECT '67e55044-10b1-426f-9247-bb680e5fe0c8' AS id, '2024-05-01T12:30:00+02:00' AS created_at, NULL AS deleted_at",
+        )
+        .await;
+
+        assert_eq!(get_uuid(&row, "id").unwrap(), Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap());
+        assert_eq!(get_datetime(&row, "created_at").unwrap().to_rfc3339(), "2024-05-01T10:30:00+00:00");
+        assert_eq!(get_optional_datetime(&row, "created_at").unwrap(), Some(get_datetime(&row, "created_at").unwrap()));
+        assert_eq!(get_optional_datetime(&row, "deleted_at").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_row_helpers_name_the_bad_column() {
+        let row = sample_row("SELECT 'not-a-uuid' AS author_id, 'yesterday' AS updated_at").await;
+
+        let err = get_uuid(&row, "author_id").unwrap_err();
+        assert!(err.to_string().contains("author_id"), "{}", err);
+        let err = get_datetime(&row, "updated_at").unwrap_err();
+        assert!(err.to_string().contains("updated_at"), "{}", err);
+        assert!(get_uuid(&row, "missing").is_err());
+    }
+}
@@ -1,9 +1,14 @@
-use sqlx::sqlite::SqlitePool;
+use futures_util::future::BoxFuture;
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
 use anyhow::Result;
-use tracing::info;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use crate::config::Settings;
 
 pub struct SqlDatabase {
     pool: SqlitePool,
+    read_pool: Option<SqlitePool>,
 }
 
 impl SqlDatabase {
@@ -14,7 +19,14 @@ impl SqlDatabase {
         // Initialize database tables
         Self::init_database(&pool).await?;
         
-        Ok(Self { pool })
+        Ok(Self { pool, read_pool: None })
+    }
+
+    // The replica is expected to mirror the primary's schema, so it isn't initialized here
+    pub async fn with_read_replica(mut self, read_url: &str) -> Result<Self> {
+        self.read_pool = Some(SqlitePool::connect(read_url).await?);
+        info!("Connected to SQLite read replica");
+        Ok(self)
     }
 
     async fn init_database(pool: &SqlitePool) -> Result<()> {
@@ -51,18 +63,412 @@ impl SqlDatabase {
         .execute(pool)
         .await?;
 
+        // Create post cover images table
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS post_covers (
+                post_id TEXT PRIMARY KEY,
+                content_type TEXT NOT NULL,
+                data BLOB NOT NULL,
+                updated_at DATETIME NOT NULL,
+                FOREIGN KEY (post_id) REFERENCES posts (id)
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Create post tags table
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS post_tags (
+                post_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (post_id, tag),
+                FOREIGN KEY (post_id) REFERENCES posts (id)
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Create post comments table
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS comments (
+                id TEXT PRIMARY KEY,
+                post_id TEXT NOT NULL,
+                author_id TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                FOREIGN KEY (post_id) REFERENCES posts (id),
+                FOREIGN KEY (author_id) REFERENCES users (id)
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_comments_post_created ON comments (post_id, created_at)
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Create post likes table (one row per user per post)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS post_likes (
+                post_id TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                PRIMARY KEY (post_id, user_id),
+                FOREIGN KEY (post_id) REFERENCES posts (id),
+                FOREIGN KEY (user_id) REFERENCES users (id)
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_post_likes_user_created ON post_likes (user_id, created_at)
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Create post reads table (which posts each user has marked read)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS post_reads (
+                post_id TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                read_at DATETIME NOT NULL,
+                PRIMARY KEY (post_id, user_id),
+                FOREIGN KEY (post_id) REFERENCES posts (id),
+                FOREIGN KEY (user_id) REFERENCES users (id)
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_post_reads_user ON post_reads (user_id)
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Create post reports table (a user reports a post at most once)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS post_reports (
+                id TEXT PRIMARY KEY,
+                post_id TEXT NOT NULL,
+                reporter_id TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                UNIQUE (post_id, reporter_id),
+                FOREIGN KEY (post_id) REFERENCES posts (id),
+                FOREIGN KEY (reporter_id) REFERENCES users (id)
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Create follows table (who follows whom; nobody follows themselves)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS follows (
+                follower_id TEXT NOT NULL,
+                followee_id TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                PRIMARY KEY (follower_id, followee_id),
+                CHECK (follower_id != followee_id),
+                FOREIGN KEY (follower_id) REFERENCES users (id),
+                FOREIGN KEY (followee_id) REFERENCES users (id)
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_follows_followee ON follows (followee_id)
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Create sessions table (tokens issued while MAX_SESSIONS_PER_USER is set; pruned once expired, revoked or not)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                expires_at DATETIME NOT NULL,
+                revoked_at DATETIME,
+                FOREIGN KEY (user_id) REFERENCES users (id)
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions (user_id)
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Create post tombstones table (no foreign keys: a tombstone outlives its post). Posts that were
+        // deleted or hidden, for the sync feed; was_public says whether anyone besides the author could see them
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS post_tombstones (
+                post_id TEXT PRIMARY KEY,
+                author_id TEXT NOT NULL,
+                was_public BOOLEAN NOT NULL,
+                removed_at DATETIME NOT NULL
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Create admin audit log table (no foreign keys: entries outlive the users and posts they name)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id TEXT PRIMARY KEY,
+                actor_id TEXT NOT NULL,
+                action TEXT NOT NULL,
+                target_id TEXT NOT NULL,
+                created_at DATETIME NOT NULL
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Columns added after the initial schema
+        Self::ensure_column(pool, "users", "last_seen_at", "DATETIME").await?;
+        Self::ensure_column(pool, "users", "role", "TEXT NOT NULL DEFAULT 'user'").await?;
+        Self::ensure_column(pool, "posts", "visibility", "TEXT NOT NULL DEFAULT 'public'").await?;
+        Self::ensure_column(pool, "posts", "deleted_at", "DATETIME").await?;
+        Self::ensure_column(pool, "users", "deleted_at", "DATETIME").await?;
+        Self::ensure_column(pool, "users", "email_verified_at", "DATETIME").await?;
+        Self::ensure_column(pool, "posts", "slug", "TEXT").await?;
+        Self::ensure_column(pool, "users", "username", "TEXT").await?;
+        // Posts from before drafts existed were all public already
+        Self::ensure_column(pool, "posts", "status", "TEXT NOT NULL DEFAULT 'published'").await?;
+        Self::ensure_column(pool, "posts", "published_at", "DATETIME").await?;
+        Self::ensure_column(pool, "posts", "edited_at", "DATETIME").await?;
+
+        sqlx::query(
+            r#"
+            UPDATE posts SET published_at = created_at WHERE status = 'published' AND published_at IS NULL
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Posts from before slugs existed get their id, which is already unique
+        sqlx::query(
+            r#"
+            UPDATE posts SET slug = id WHERE slug IS NULL
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_posts_slug ON posts (slug)
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Users from before usernames existed get their id without dashes: unique and a valid username
+        sqlx::query(
+            r#"
+            UPDATE users SET username = replace(id, '-', '') WHERE username IS NULL
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_users_username ON users (username)
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
         info!("Database tables initialized successfully");
         Ok(())
     }
 
+    // Adds a column to an existing table if it isn't there yet (CREATE TABLE IF NOT EXISTS won't)
+    async fn ensure_column(pool: &SqlitePool, table: &str, column: &str, definition: &str) -> Result<()> {
+        let exists: bool = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?",
+            table
+        ))
+        .bind(column)
+        .fetch_one(pool)
+        .await?;
+
+        if !exists {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(pool)
+                .await?;
+            info!("Added column {}.{}", table, column);
+        }
+
+        Ok(())
+    }
+
     pub fn get_pool(&self) -> &SqlitePool {
         &self.pool
     }
+
+    /// The replica pool, or the primary when no replica is configured
+    pub fn get_read_pool(&self) -> &SqlitePool {
+        self.read_pool.as_ref().unwrap_or(&self.pool)
+    }
 }
 
-pub async fn get_sql_client() -> Result<SqlDatabase> {
-    let database_url = std::env::var("DATABASE_URL")
-        .unwrap_or_else(|_| "sqlite:./api_rust_one.db".to_string());
-    
-    SqlDatabase::new(&database_url).await
-} 
\ No newline at end of file
+/// Whether a repository error is SQLite refusing a row that breaks a UNIQUE constraint
+pub fn is_unique_violation(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<sqlx::Error>() {
+        Some(sqlx::Error::Database(db_error)) => db_error.is_unique_violation(),
+        _ => false,
+    }
+}
+
+/// Like `is_unique_violation`, but only for the constraint on `column` (as `table.column`)
+pub fn is_unique_violation_on(error: &anyhow::Error, column: &str) -> bool {
+    // SQLite names the columns in the message, e.g. "UNIQUE constraint failed: users.email"
+    is_unique_violation(error) && error.to_string().contains(column)
+}
+
+// Attempts `retry_busy` makes before returning the busy error, and the delay before the first retry (doubled each time)
+const BUSY_RETRY_ATTEMPTS: u32 = 5;
+const BUSY_RETRY_BASE_DELAY: Duration = Duration::from_millis(20);
+
+/// Whether SQLite refused a statement because another connection holds the lock ("database is locked")
+pub fn is_busy(error: &sqlx::Error) -> bool {
+    let sqlx::Error::Database(db_error) = error else {
+        return false;
+    };
+    // Extended result codes keep the primary code (SQLITE_BUSY = 5, SQLITE_LOCKED = 6) in the low byte
+    db_error
+        .code()
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| matches!(code & 0xff, 5 | 6))
+}
+
+/// Runs a write, retrying with exponential backoff while the database is busy.
+/// `operation` is called once per attempt, so it must build the query afresh; a transaction is only
+/// retried whole, when `operation` begins and commits it itself.
+pub async fn retry_busy<T, F, Fut>(mut operation: F) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+    let mut delay = BUSY_RETRY_BASE_DELAY;
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Err(e) if attempt < BUSY_RETRY_ATTEMPTS && is_busy(&e) => {
+                warn!("Database busy (attempt {} of {}), retrying in {:?}", attempt, BUSY_RETRY_ATTEMPTS, delay);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+/// A query was cut off because the request's time budget ran out
+#[derive(Debug, thiserror::Error)]
+#[error("query cancelled at the request deadline")]
+pub struct DeadlineExceeded;
+
+// Virtual machine instructions SQLite runs between checks of the deadline
+const DEADLINE_CHECK_OPS: i32 = 1000;
+
+// SQLITE_INTERRUPT (9): the progress handler stopped the statement
+fn is_interrupted(error: &sqlx::Error) -> bool {
+    let sqlx::Error::Database(db_error) = error else {
+        return false;
+    };
+    db_error
+        .code()
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| code & 0xff == 9)
+}
+
+/// Runs `query` on a connection from `pool`, with SQLite interrupting the statement once `deadline` passes.
+/// The query runs on its own task, so it is still stopped at the deadline (and its connection handed back
+/// to the pool clean) if the caller is dropped first, e.g. by the request timeout.
+pub async fn within_deadline<T, F>(pool: &SqlitePool, deadline: Option<Instant>, query: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: for<'c> FnOnce(&'c mut SqliteConnection) -> BoxFuture<'c, Result<T, sqlx::Error>> + Send + 'static,
+{
+    let Some(deadline) = deadline else {
+        let mut conn = pool.acquire().await?;
+        return Ok(query(&mut conn).await?);
+    };
+
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(DeadlineExceeded.into());
+    }
+
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        let mut conn = tokio::time::timeout(remaining, pool.acquire())
+            .await
+            .map_err(|_| DeadlineExceeded)??;
+
+        conn.lock_handle().await?.set_progress_handler(DEADLINE_CHECK_OPS, move || Instant::now() < deadline);
+        let result = query(&mut conn).await;
+        // The handler would interrupt every later query on this connection
+        conn.lock_handle().await?.remove_progress_handler();
+
+        match result {
+            Err(e) if is_interrupted(&e) => {
+                warn!("Query interrupted at the request deadline");
+                Err(DeadlineExceeded.into())
+            },
+            result => Ok(result?),
+        }
+    })
+    .await?
+}
+
+pub async fn get_sql_client(settings: &Settings) -> Result<SqlDatabase> {
+    let db = SqlDatabase::new(&settings.database_url).await?;
+
+    match &settings.database_read_url {
+        Some(read_url) => db.with_read_replica(read_url).await,
+        None => Ok(db),
+    }
+}
@@ -0,0 +1,71 @@
+use axum::extract::{State, Extension, Query};
+use std::sync::Arc;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+use crate::db::repositories::sql_comment_repo::SqlCommentRepository;
+use crate::db::repositories::sql_post_repo::SqlPostRepository;
+use crate::db::repositories::sql_user_repo::SqlUserRepository;
+use crate::helpers::extractors::PathId;
+use crate::helpers::messages::Lang;
+use crate::helpers::response::{UnifiedResponse, success_response, not_found_response_generic, sql_error_response_generic};
+use crate::model::model::{ActivityItem, ActivityQuery};
+use crate::state::ReadPool;
+use tracing::{info, error};
+
+// Characters of a comment shown as its timeline summary
+const COMMENT_SUMMARY_CHARS: usize = 100;
+
+// A user's posts and comments merged newest first; each source is asked for a full page so the merge can't skip items
+pub async fn get_user_activity(
+    State(pool): State<Arc<SqlitePool>>,
+    State(ReadPool(read_pool)): State<ReadPool>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    PathId(user_id): PathId,
+    Query(query): Query<ActivityQuery>,
+    lang: Lang
+) -> UnifiedResponse<Vec<ActivityItem>> {
+    info!("Handler: Getting activity of user: {}", user_id);
+
+    let users = SqlUserRepository::new((*pool).clone()).with_read_pool((*read_pool).clone());
+    match users.find_by_id(user_id).await {
+        Ok(Some(_)) => {},
+        Ok(None) => return not_found_response_generic(lang.text("user.not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to get user for activity: {}", e);
+            return sql_error_response_generic(e, "Failed to get user");
+        }
+    }
+
+    let limit = query.limit();
+    let posts = SqlPostRepository::new((*pool).clone()).with_read_pool((*read_pool).clone());
+    let comments = SqlCommentRepository::new((*pool).clone()).with_read_pool((*read_pool).clone());
+
+    let (posts, comments) = match tokio::try_join!(
+        posts.find_activity_by_author(user_id, viewer, query.before, limit),
+        comments.find_by_author(user_id, viewer, query.before, limit),
+    ) {
+        Ok(found) => found,
+        Err(e) => {
+            error!("Handler: Failed to get user activity: {}", e);
+            return sql_error_response_generic(e, "Failed to get user activity");
+        }
+    };
+
+    let mut items: Vec<ActivityItem> = posts
+        .into_iter()
+        .map(|post| ActivityItem::Post { id: post.id, created_at: post.created_at, summary: post.title })
+        .chain(comments.into_iter().map(|comment| ActivityItem::Comment {
+            id: comment.id,
+            post_id: comment.post_id,
+            created_at: comment.created_at,
+            summary: comment.content.chars().take(COMMENT_SUMMARY_CHARS).collect(),
+        }))
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.created_at()));
+    items.truncate(limit as usize);
+
+    success_response(
+        lang.format("activity.retrieved", &[&items.len().to_string()]),
+        items
+    )
+}
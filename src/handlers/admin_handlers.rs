@@ -0,0 +1,269 @@
+use axum::{
+    body::Body,
+    extract::{OriginalUri, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+use std::sync::Arc;
+use sqlx::SqlitePool;
+use serde_json::Value;
+use crate::config::Settings;
+use crate::db::repositories::sql_post_repo::SqlPostRepository;
+use crate::db::repositories::sql_user_repo::SqlUserRepository;
+use crate::db::repositories::sql_audit_repo::SqlAuditRepository;
+use crate::db::repositories::sql_report_repo::SqlReportRepository;
+use crate::handlers::auth_handlers::create_account;
+use crate::helpers::cache::PostsCache;
+use crate::helpers::extractors::{AdminRole, JsonBody, PathId, RequireRole};
+use crate::helpers::messages::Lang;
+use crate::helpers::pagination::{total_if, PageLinks};
+use crate::helpers::timestamp;
+use crate::model::model::{AdminUserResponse, AuditEntry, CreateUserRequest, LogLevelResponse, PageQuery, ReportedPost, Role, SetLogLevelRequest, SetRoleRequest, UserResponse};
+use crate::helpers::response::{UnifiedResponse, success_response, error_response_generic, not_found_response_generic, sql_error_response_generic, error_response_with_status};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{info, warn, error};
+use tracing_subscriber::filter::LevelFilter;
+
+// Lines buffered between the DB cursor and the client; bounds export memory use
+const EXPORT_BUFFER_LINES: usize = 64;
+
+pub async fn admin_delete_post(
+    State(pool): State<Arc<SqlitePool>>,
+    State(posts_cache): State<Arc<PostsCache>>,
+    admin: RequireRole<AdminRole>,
+    PathId(id): PathId,
+    lang: Lang
+) -> UnifiedResponse<Value> {
+    info!("Handler: Admin {} deleting post: {}", admin.user_id, id);
+
+    let repo = SqlPostRepository::new((*pool).clone());
+    
+    match repo.delete_any(id, admin.user_id).await {
+        Ok(true) => {
+            posts_cache.invalidate();
+            success_response(lang.text("post.deleted").to_string(), Value::Null)
+        },
+        Ok(false) => {
+            not_found_response_generic(lang.text("post.not_found").to_string())
+        },
+        Err(e) => {
+            error!("Handler: Failed to delete post as admin: {}", e);
+            sql_error_response_generic(e, "Failed to delete post")
+        }
+    }
+}
+
+// Account creation that stays available when public registration is closed
+pub async fn admin_create_user(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    admin: RequireRole<AdminRole>,
+    lang: Lang,
+    JsonBody(payload): JsonBody<CreateUserRequest>
+) -> UnifiedResponse<UserResponse> {
+    info!("Handler: Admin {} creating user: {}", admin.user_id, payload.email);
+
+    create_account(&pool, &settings, lang, payload).await
+}
+
+// Promotes or demotes a user; there must always be at least one admin left
+pub async fn admin_set_user_role(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    admin: RequireRole<AdminRole>,
+    PathId(id): PathId,
+    lang: Lang,
+    JsonBody(payload): JsonBody<SetRoleRequest>
+) -> UnifiedResponse<AdminUserResponse> {
+    info!("Handler: Admin {} setting role of user {} to {}", admin.user_id, id, payload.role.as_str());
+
+    let repo = SqlUserRepository::new((*pool).clone()).with_clock(settings.clock.clone());
+
+    let user = match repo.find_by_id(id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return not_found_response_generic(lang.text("user.not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to load user for role change: {}", e);
+            return sql_error_response_generic(e, "Failed to update user role");
+        }
+    };
+
+    if user.role == Role::Admin && payload.role != Role::Admin {
+        match repo.count_admins().await {
+            Ok(count) if count <= 1 => {
+                return error_response_with_status(StatusCode::CONFLICT, "Conflict".to_string(), lang.text("user.last_admin").to_string());
+            },
+            Ok(_) => {},
+            Err(e) => {
+                error!("Handler: Failed to count admins: {}", e);
+                return sql_error_response_generic(e, "Failed to update user role");
+            }
+        }
+    }
+
+    match repo.set_role(id, payload.role, admin.user_id).await {
+        Ok(Some(user)) => {
+            let response = AdminUserResponse {
+                role: user.role,
+                user: UserResponse {
+                    id: user.id,
+                    username: user.username,
+                    name: user.name,
+                    email: user.email,
+                    created_at: user.created_at,
+                    updated_at: user.updated_at,
+                },
+            };
+            success_response(lang.text("user.role_updated").to_string(), response)
+        },
+        Ok(None) => {
+            not_found_response_generic(lang.text("user.not_found").to_string())
+        },
+        Err(e) => {
+            error!("Handler: Failed to update user role: {}", e);
+            sql_error_response_generic(e, "Failed to update user role")
+        }
+    }
+}
+
+// Swaps the process-wide log filter, e.g. to debug during an incident; lasts until the next change or restart
+pub async fn admin_set_log_level(
+    State(settings): State<Arc<Settings>>,
+    admin: RequireRole<AdminRole>,
+    lang: Lang,
+    JsonBody(payload): JsonBody<SetLogLevelRequest>
+) -> UnifiedResponse<LogLevelResponse> {
+    let Ok(level) = payload.level.trim().parse::<LevelFilter>() else {
+        return error_response_generic("Bad Request".to_string(), lang.text("admin.log_level_invalid").to_string());
+    };
+
+    // Logged before the switch so it shows up even when the new level hides it
+    warn!("Handler: Admin {} setting log level to {}", admin.user_id, level);
+
+    match settings.log_filter.set(level) {
+        Ok(()) => {
+            let level = settings.log_filter.level();
+            success_response(lang.format("admin.log_level_updated", &[&level]), LogLevelResponse { level })
+        },
+        Err(e) => {
+            error!("Handler: Failed to set log level: {}", e);
+            error_response_with_status(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal Error".to_string(),
+                lang.text("admin.log_level_failed").to_string(),
+            )
+        }
+    }
+}
+
+// Audit log of admin actions, newest first
+pub async fn admin_get_audit_log(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    admin: RequireRole<AdminRole>,
+    OriginalUri(uri): OriginalUri,
+    Query(page): Query<PageQuery>,
+    lang: Lang
+) -> (Option<PageLinks>, UnifiedResponse<Vec<AuditEntry>>) {
+    info!("Handler: Admin {} reading audit log", admin.user_id);
+
+    let repo = SqlAuditRepository::new((*pool).clone());
+
+    let total = match total_if(settings.pagination_links, repo.count()).await {
+        Ok(total) => total,
+        Err(e) => {
+            error!("Handler: Failed to count audit entries: {}", e);
+            return (None, sql_error_response_generic(e, "Failed to read audit log"));
+        }
+    };
+
+    match repo.list(page.limit(), page.offset()).await {
+        Ok(entries) => {
+            (
+                total.and_then(|total| PageLinks::for_page(&uri, &page, total)),
+                success_response(
+                    lang.format("audit.list_retrieved", &[&entries.len().to_string()]),
+                    entries
+                ),
+            )
+        },
+        Err(e) => {
+            error!("Handler: Failed to read audit log: {}", e);
+            (None, sql_error_response_generic(e, "Failed to read audit log"))
+        }
+    }
+}
+
+pub async fn admin_get_reports(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    admin: RequireRole<AdminRole>,
+    OriginalUri(uri): OriginalUri,
+    Query(page): Query<PageQuery>,
+    lang: Lang
+) -> (Option<PageLinks>, UnifiedResponse<Vec<ReportedPost>>) {
+    info!("Handler: Admin {} reading reported posts", admin.user_id);
+
+    let repo = SqlReportRepository::new((*pool).clone());
+
+    let total = match total_if(settings.pagination_links, repo.count_reported_posts()).await {
+        Ok(total) => total,
+        Err(e) => {
+            error!("Handler: Failed to count reported posts: {}", e);
+            return (None, sql_error_response_generic(e, "Failed to read reported posts"));
+        }
+    };
+
+    match repo.list_reported_posts(page.limit(), page.offset()).await {
+        Ok(posts) => {
+            (
+                total.and_then(|total| PageLinks::for_page(&uri, &page, total)),
+                success_response(
+                    lang.format("report.list_retrieved", &[&posts.len().to_string()]),
+                    posts
+                ),
+            )
+        },
+        Err(e) => {
+            error!("Handler: Failed to read reported posts: {}", e);
+            (None, sql_error_response_generic(e, "Failed to read reported posts"))
+        }
+    }
+}
+
+// Newline-delimited JSON of every post, streamed so memory stays flat however many posts there are
+pub async fn admin_export_posts(
+    State(pool): State<Arc<SqlitePool>>,
+    admin: RequireRole<AdminRole>
+) -> Response {
+    info!("Handler: Admin {} exporting posts", admin.user_id);
+
+    let (tx, rx) = mpsc::channel::<Result<String, std::io::Error>>(EXPORT_BUFFER_LINES);
+    let format = timestamp::current();
+
+    tokio::spawn(timestamp::scope(format, async move {
+        let repo = SqlPostRepository::new((*pool).clone());
+        let mut posts = Box::pin(repo.stream_all());
+
+        while let Some(post) = posts.next().await {
+            let line = post.and_then(|post| Ok(serde_json::to_string(&post)? + "\n"));
+            let failed = line.is_err();
+            if let Err(e) = &line {
+                error!("Handler: Post export failed: {}", e);
+            }
+
+            // A failed line aborts the body so the client sees a truncated export, not a complete one
+            let line = line.map_err(|e| std::io::Error::other(e.to_string()));
+            if tx.send(line).await.is_err() || failed {
+                break;
+            }
+        }
+    }));
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(ReceiverStream::new(rx)),
+    ).into_response()
+}
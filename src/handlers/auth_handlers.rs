@@ -1,90 +1,210 @@
 use axum::{
-    extract::{State, Extension},
-    Json,
+    extract::{State, Query},
+    http::StatusCode,
+    Extension,
 };
+use std::net::{IpAddr, Ipv4Addr};
 use std::sync::Arc;
 use sqlx::SqlitePool;
 use uuid::Uuid;
+use chrono::SubsecRound;
+use serde_json::Value;
 use crate::model::model::{
-    CreateUserRequest, LoginRequest, LoginResponse, UpdateUserRequest, UserResponse
+    CreateUserRequest, LoginRequest, LoginResponse, UpdateUserRequest, User, UserResponse, Role, ProfileResponse, VerifyEmailRequest, EmailQuery, EmailAvailability
 };
+use crate::config::Settings;
 use crate::db::repositories::sql_user_repo::SqlUserRepository;
+use crate::db::sql_db::{is_unique_violation, is_unique_violation_on};
 use crate::helpers::auth::AuthHelper;
-use crate::helpers::validation::validate_user_registration;
-use crate::helpers::response::{UnifiedResponse, success_response, error_response_generic, not_found_response_generic, sql_error_response_generic};
-use tracing::{info, error};
+use crate::helpers::extractors::{AuthUser, JsonBody, TrustedCaller};
+use crate::helpers::messages::Lang;
+use crate::helpers::cache::PostsCache;
+use crate::helpers::middleware::ClientIp;
+use crate::helpers::rate_limit::RateLimiter;
+use crate::helpers::validation::{validate_email, validate_user_registration};
+use crate::helpers::response::{UnifiedResponse, success_response, error_response_generic, error_response_with_status, sql_error_response_generic, unauthorized_response_generic};
+use tracing::{debug, info, error};
 
 pub async fn register_user(
     State(pool): State<Arc<SqlitePool>>,
-    Json(payload): Json<CreateUserRequest>
+    State(settings): State<Arc<Settings>>,
+    lang: Lang,
+    JsonBody(payload): JsonBody<CreateUserRequest>
 ) -> UnifiedResponse<UserResponse> {
     info!("Handler: Registering new user: {}", payload.email);
-    
-    // Validate input
-    if let Err(validation_error) = validate_user_registration(&payload) {
-        return error_response_generic("Validation Error".to_string(), validation_error);
+
+    if let Err(response) = check_registration_open(&settings, &payload.email, lang) {
+        return response;
+    }
+
+    create_account(&pool, &settings, lang, payload).await
+}
+
+// Registers and logs in with one call, answering like POST /auth/login
+pub async fn register_and_login(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    lang: Lang,
+    JsonBody(payload): JsonBody<CreateUserRequest>
+) -> UnifiedResponse<LoginResponse> {
+    info!("Handler: Registering and logging in new user: {}", payload.email);
+
+    if let Err(response) = check_registration_open(&settings, &payload.email, lang) {
+        return response;
     }
 
+    let user = match register_account(&pool, &settings, lang, payload).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
     let repo = SqlUserRepository::new((*pool).clone());
+    let message = lang.format("auth.user_registered", &[&user.name]);
+    start_session(&repo, user, &settings, lang, message).await
+}
+
+// Closed registration still lets the configured admins bootstrap themselves
+fn check_registration_open<T>(settings: &Settings, email: &str, lang: Lang) -> Result<(), UnifiedResponse<T>> {
+    if !settings.registration_open && !settings.is_admin_email(email) {
+        return Err(error_response_with_status(
+            StatusCode::FORBIDDEN,
+            "Forbidden".to_string(),
+            lang.text("auth.registration_closed").to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Shared by self-registration and admin-created accounts
+pub(crate) async fn create_account(
+    pool: &SqlitePool,
+    settings: &Settings,
+    lang: Lang,
+    payload: CreateUserRequest
+) -> UnifiedResponse<UserResponse> {
+    let user = match register_account(pool, settings, lang, payload).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let user_name = user.name.clone();
+    let user_response = UserResponse {
+        id: user.id,
+        username: user.username,
+        name: user.name,
+        email: user.email,
+        created_at: user.created_at,
+        updated_at: user.updated_at,
+    };
+
+    success_response(
+        lang.format("auth.user_registered", &[&user_name]),
+        user_response
+    )
+}
+
+// Validates and stores a new account, or gives the error response to send instead
+async fn register_account<T>(
+    pool: &SqlitePool,
+    settings: &Settings,
+    lang: Lang,
+    mut payload: CreateUserRequest
+) -> Result<User, UnifiedResponse<T>> {
+    // Usernames are case-insensitive, so they're stored lowercase
+    payload.username = payload.username.map(|username| username.trim().to_lowercase());
+
+    // Validate input
+    if let Err(validation_error) = validate_user_registration(&payload, settings.blocked_email_domains(), lang) {
+        return Err(error_response_generic("Validation Error".to_string(), validation_error));
+    }
+
+    let repo = SqlUserRepository::new(pool.clone()).with_clock(settings.clock.clone());
     
     // Check existing
     match repo.find_by_email(&payload.email).await {
         Ok(Some(_)) => {
-            return error_response_generic("Conflict".to_string(), "User with this email already exists".to_string());
+            return Err(error_response_with_status(StatusCode::CONFLICT, "Conflict".to_string(), lang.text("auth.email_taken").to_string()));
         },
         Ok(None) => {},
         Err(e) => {
             error!("Handler: Failed to check existing user: {}", e);
-            return sql_error_response_generic(e, "Failed to check existing user");
+            return Err(sql_error_response_generic(e, "Failed to check existing user"));
+        }
+    }
+
+    if let Some(username) = &payload.username {
+        match repo.username_exists(username).await {
+            Ok(true) => {
+                return Err(error_response_with_status(StatusCode::CONFLICT, "Conflict".to_string(), lang.text("auth.username_taken").to_string()));
+            },
+            Ok(false) => {},
+            Err(e) => {
+                error!("Handler: Failed to check existing username: {}", e);
+                return Err(sql_error_response_generic(e, "Failed to check existing user"));
+            }
         }
     }
 
     // Hash password
-    let hashed_password = match AuthHelper::hash_password(&payload.password) {
+    let hashed_password = match AuthHelper::hash_password_blocking(payload.password.clone(), settings.password_hasher).await {
         Ok(hashed) => hashed,
         Err(e) => {
             error!("Handler: Failed to hash password: {}", e);
-            return error_response_generic("Internal Error".to_string(), "Failed to process password".to_string());
+            return Err(error_response_generic("Internal Error".to_string(), lang.text("auth.password_processing_failed").to_string()));
         }
     };
 
+    // Bootstrap admins come from config
+    let role = if settings.is_admin_email(&payload.email) {
+        Role::Admin
+    } else {
+        Role::User
+    };
+
     // Create user
-    match repo.create_user(payload.clone(), hashed_password).await {
+    match repo.create_user(payload.clone(), hashed_password, role).await {
         Ok(user) => {
-            let user_name = user.name.clone();
-            let user_response = UserResponse {
-                id: user.id,
-                name: user.name,
-                email: user.email,
-                created_at: user.created_at,
-                updated_at: user.updated_at,
-            };
-            
-            success_response(
-                format!("User: {} registered successfully", user_name),
-                user_response
-            )
+            if let Err(e) = send_verification(&user, settings) {
+                error!("Handler: Failed to generate verification token: {}", e);
+            }
+            Ok(user)
+        },
+        // A concurrent registration took the email or username between the checks above and the insert
+        Err(e) if is_unique_violation_on(&e, "users.username") => {
+            Err(error_response_with_status(StatusCode::CONFLICT, "Conflict".to_string(), lang.text("auth.username_taken").to_string()))
+        },
+        Err(e) if is_unique_violation(&e) => {
+            Err(error_response_with_status(StatusCode::CONFLICT, "Conflict".to_string(), lang.text("auth.email_taken").to_string()))
         },
         Err(e) => {
             error!("Handler: Failed to create user: {}", e);
-            sql_error_response_generic(e, "Failed to create user")
+            Err(sql_error_response_generic(e, "Failed to create user"))
         }
     }
 }
 
 pub async fn login_user(
     State(pool): State<Arc<SqlitePool>>,
-    Json(payload): Json<LoginRequest>
+    State(settings): State<Arc<Settings>>,
+    lang: Lang,
+    JsonBody(payload): JsonBody<LoginRequest>
 ) -> UnifiedResponse<LoginResponse> {
     info!("Handler: User login attempt: {}", payload.email);
 
+    // Obviously invalid input never reaches the DB or bcrypt
+    if payload.email.trim().is_empty() || payload.password.is_empty() {
+        return error_response_generic("Validation Error".to_string(), lang.text("auth.credentials_required").to_string());
+    }
+
     let repo = SqlUserRepository::new((*pool).clone());
     
     // Find user
     let user = match repo.find_by_email(&payload.email).await {
         Ok(Some(user)) => user,
         Ok(None) => {
-            return error_response_generic("Unauthorized".to_string(), "Invalid email or password".to_string());
+            // Unknown emails pay for a password check too, so timing doesn't reveal which emails exist
+            AuthHelper::dummy_verify_blocking(payload.password, settings.password_hasher).await;
+            return unauthorized_response_generic(lang.text("auth.invalid_credentials").to_string());
         },
         Err(e) => {
             error!("Handler: Failed to find user: {}", e);
@@ -93,111 +213,288 @@ pub async fn login_user(
     };
 
     // Verify password
-    match AuthHelper::verify_password(&payload.password, &user.password) {
+    match AuthHelper::verify_password_blocking(payload.password.clone(), user.password.clone()).await {
         Ok(true) => {
-            // Generate token
-            let token = match AuthHelper::generate_token(user.id) {
-                Ok(token) => token,
-                Err(e) => {
-                    error!("Handler: Failed to generate token: {}", e);
-                    return error_response_generic("Internal Error".to_string(), "Failed to generate authentication token".to_string());
+            // Move legacy hashes to the configured algorithm while we have the plaintext
+            if AuthHelper::needs_rehash(&user.password, settings.password_hasher) {
+                match AuthHelper::hash_password_blocking(payload.password, settings.password_hasher).await {
+                    Ok(hashed) => {
+                        if let Err(e) = repo.set_password(user.id, &hashed).await {
+                            error!("Handler: Failed to store upgraded password hash: {}", e);
+                        }
+                    },
+                    Err(e) => error!("Handler: Failed to upgrade password hash: {}", e),
                 }
-            };
-
-            let user_response = UserResponse {
-                id: user.id,
-                name: user.name,
-                email: user.email,
-                created_at: user.created_at,
-                updated_at: user.updated_at,
-            };
-
-            let login_response = LoginResponse {
-                token,
-                user: user_response,
-            };
+            }
 
-            success_response("Login successful".to_string(), login_response)
+            start_session(&repo, user, &settings, lang, lang.text("auth.login_success").to_string()).await
         },
         Ok(false) => {
-            error_response_generic("Unauthorized".to_string(), "Invalid email or password".to_string())
+            unauthorized_response_generic(lang.text("auth.invalid_credentials").to_string())
         },
         Err(e) => {
             error!("Handler: Failed to verify password: {}", e);
-            error_response_generic("Internal Error".to_string(), "Failed to verify password".to_string())
+            error_response_generic("Internal Error".to_string(), lang.text("auth.password_verification_failed").to_string())
         }
     }
 }
 
-pub async fn get_profile(
-    State(pool): State<Arc<SqlitePool>>,
-    Extension(user_id): Extension<Uuid>
-) -> UnifiedResponse<UserResponse> {
-    info!("Handler: Getting profile for user: {}", user_id);
-
-    let repo = SqlUserRepository::new((*pool).clone());
-    
-    match repo.find_by_id(user_id).await {
-        Ok(Some(user)) => {
-            let user_response = UserResponse {
-                id: user.id,
-                name: user.name,
-                email: user.email,
-                created_at: user.created_at,
-                updated_at: user.updated_at,
-            };
-            
-            success_response("Profile retrieved successfully".to_string(), user_response)
-        },
-        Ok(None) => {
-            not_found_response_generic("User not found".to_string())
-        },
+// Issues an access token for `user` and starts their idle window; the success body of a login
+async fn start_session(
+    repo: &SqlUserRepository,
+    user: User,
+    settings: &Settings,
+    lang: Lang,
+    message: String
+) -> UnifiedResponse<LoginResponse> {
+    // Generate token; the advertised expiry is what goes in its `exp` claim
+    let expires_at = (settings.clock.now() + settings.access_token_ttl).trunc_subsecs(0);
+    // Sessions are only tracked while there is a cap to enforce
+    let session_id = settings.max_sessions_per_user.map(|_| Uuid::new_v4());
+    let token = match AuthHelper::generate_token_expiring(user.id, user.role, expires_at, session_id, settings) {
+        Ok(token) => token,
         Err(e) => {
-            error!("Handler: Failed to get user profile: {}", e);
-            sql_error_response_generic(e, "Failed to get user profile")
+            error!("Handler: Failed to generate token: {}", e);
+            return error_response_generic("Internal Error".to_string(), lang.text("auth.token_generation_failed").to_string());
         }
+    };
+
+    if let (Some(session_id), Some(max)) = (session_id, settings.max_sessions_per_user) {
+        if let Err(e) = repo.start_session(session_id, user.id, expires_at, max).await {
+            error!("Handler: Failed to record session: {}", e);
+            return sql_error_response_generic(e, "Failed to start session");
+        }
+    }
+
+    // Logging in starts a fresh idle window
+    if let Err(e) = repo.set_last_seen(user.id, settings.clock.now()).await {
+        error!("Handler: Failed to record login activity: {}", e);
     }
+
+    let user_response = UserResponse {
+        id: user.id,
+        username: user.username,
+        name: user.name,
+        email: user.email,
+        created_at: user.created_at,
+        updated_at: user.updated_at,
+    };
+
+    let login_response = LoginResponse {
+        token,
+        token_type: "Bearer".to_string(),
+        expires_at,
+        user: user_response,
+    };
+
+    success_response(message, login_response)
+}
+
+pub async fn get_profile(
+    AuthUser(user): AuthUser,
+    lang: Lang
+) -> UnifiedResponse<ProfileResponse> {
+    info!("Handler: Getting profile for user: {}", user.id);
+
+    let profile = ProfileResponse {
+        verified: user.email_verified,
+        user: UserResponse {
+            id: user.id,
+            username: user.username,
+            name: user.name,
+            email: user.email,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        },
+    };
+    
+    success_response(lang.text("auth.profile_retrieved").to_string(), profile)
 }
 
 pub async fn update_profile(
     State(pool): State<Arc<SqlitePool>>,
-    Extension(user_id): Extension<Uuid>,
-    Json(payload): Json<UpdateUserRequest>
-) -> UnifiedResponse<UserResponse> {
-    info!("Handler: Updating profile for user: {}", user_id);
+    State(settings): State<Arc<Settings>>,
+    State(posts_cache): State<Arc<PostsCache>>,
+    AuthUser(user): AuthUser,
+    lang: Lang,
+    JsonBody(payload): JsonBody<UpdateUserRequest>
+) -> UnifiedResponse<ProfileResponse> {
+    info!("Handler: Updating profile for user: {}", user.id);
 
-    let repo = SqlUserRepository::new((*pool).clone());
+    if payload.is_empty() {
+        return error_response_generic("Bad Request".to_string(), lang.text("request.empty_update").to_string());
+    }
+
+    let repo = SqlUserRepository::new((*pool).clone()).with_clock(settings.clock.clone());
     
     // Hash password
     let mut update_data = payload;
-    if let Some(password) = &update_data.password {
-        match AuthHelper::hash_password(password) {
+    if let Some(password) = update_data.password.take() {
+        match AuthHelper::hash_password_blocking(password, settings.password_hasher).await {
             Ok(hashed) => update_data.password = Some(hashed),
             Err(e) => {
                 error!("Handler: Failed to hash password: {}", e);
-                return error_response_generic("Internal Error".to_string(), "Failed to process password".to_string());
+                return error_response_generic("Internal Error".to_string(), lang.text("auth.password_processing_failed").to_string());
             }
         }
     }
     
-    match repo.update_user(user_id, update_data).await {
-        Ok(Some(user)) => {
-            let user_response = UserResponse {
-                id: user.id,
-                name: user.name,
-                email: user.email,
-                created_at: user.created_at,
-                updated_at: user.updated_at,
+    match repo.apply_user_update(user, update_data).await {
+        Ok(user) => {
+            // Expanded feeds embed the author's name and email
+            posts_cache.invalidate();
+
+            let profile = ProfileResponse {
+                verified: user.email_verified,
+                user: UserResponse {
+                    id: user.id,
+                    username: user.username,
+                    name: user.name,
+                    email: user.email,
+                    created_at: user.created_at,
+                    updated_at: user.updated_at,
+                },
             };
             
-            success_response("Profile updated successfully".to_string(), user_response)
-        },
-        Ok(None) => {
-            not_found_response_generic("User not found".to_string())
+            success_response(lang.text("auth.profile_updated").to_string(), profile)
         },
         Err(e) => {
             error!("Handler: Failed to update user profile: {}", e);
             sql_error_response_generic(e, "Failed to update user profile")
         }
     }
-} 
\ No newline at end of file
+}
+
+pub async fn delete_profile(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    State(posts_cache): State<Arc<PostsCache>>,
+    AuthUser(user): AuthUser,
+    lang: Lang
+) -> UnifiedResponse<Value> {
+    info!("Handler: Deleting account for user: {}", user.id);
+
+    let repo = SqlUserRepository::new((*pool).clone()).with_clock(settings.clock.clone());
+
+    match repo.delete_user(user.id, settings.user_post_deletion, user.id).await {
+        Ok(_) => {
+            posts_cache.invalidate();
+            success_response(lang.text("auth.account_deleted").to_string(), Value::Null)
+        },
+        Err(e) => {
+            error!("Handler: Failed to delete account: {}", e);
+            sql_error_response_generic(e, "Failed to delete account")
+        }
+    }
+}
+
+// There's no mailer yet, so the verification token is only logged for out-of-band delivery
+fn send_verification(user: &User, settings: &Settings) -> anyhow::Result<()> {
+    let token = AuthHelper::generate_email_verification_token(user.id, &user.email, settings)?;
+    debug!("Email verification token for {}: {}", user.email, token);
+    Ok(())
+}
+
+// Issues a fresh verification token for the caller; already verified accounts get a 200 without one
+pub async fn resend_verification(
+    State(settings): State<Arc<Settings>>,
+    State(limiter): State<Arc<RateLimiter<Uuid>>>,
+    AuthUser(user): AuthUser,
+    TrustedCaller(trusted): TrustedCaller,
+    lang: Lang
+) -> UnifiedResponse<Value> {
+    info!("Handler: Resending verification for user: {}", user.id);
+
+    if user.email_verified {
+        return success_response(lang.text("auth.already_verified").to_string(), Value::Null);
+    }
+
+    if !trusted && !limiter.check(user.id) {
+        return error_response_with_status(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too Many Requests".to_string(),
+            lang.text("auth.verification_rate_limited").to_string(),
+        );
+    }
+
+    match send_verification(&user, &settings) {
+        Ok(()) => success_response(lang.text("auth.verification_sent").to_string(), Value::Null),
+        Err(e) => {
+            error!("Handler: Failed to generate verification token: {}", e);
+            error_response_generic("Internal Error".to_string(), lang.text("auth.token_generation_failed").to_string())
+        }
+    }
+}
+
+pub async fn verify_email(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    lang: Lang,
+    JsonBody(payload): JsonBody<VerifyEmailRequest>
+) -> UnifiedResponse<Value> {
+    let invalid = || error_response_generic("Bad Request".to_string(), lang.text("auth.verification_invalid").to_string());
+
+    let (user_id, email) = match AuthHelper::validate_email_verification_token(&payload.token, &settings) {
+        Ok(identity) => identity,
+        Err(e) => {
+            error!("Handler: Invalid verification token: {}", e);
+            return invalid();
+        }
+    };
+    info!("Handler: Verifying email for user: {}", user_id);
+
+    let repo = SqlUserRepository::new((*pool).clone()).with_clock(settings.clock.clone());
+
+    // The token only counts for the address it was issued for
+    match repo.find_by_id(user_id).await {
+        Ok(Some(user)) if user.email == email => {},
+        Ok(_) => return invalid(),
+        Err(e) => {
+            error!("Handler: Failed to find user for verification: {}", e);
+            return sql_error_response_generic(e, "Failed to verify email");
+        }
+    }
+
+    match repo.set_email_verified(user_id).await {
+        Ok(()) => success_response(lang.text("auth.email_verified").to_string(), Value::Null),
+        Err(e) => {
+            error!("Handler: Failed to mark email verified: {}", e);
+            sql_error_response_generic(e, "Failed to verify email")
+        }
+    }
+}
+
+pub async fn email_available(
+    State(pool): State<Arc<SqlitePool>>,
+    State(limiter): State<Arc<RateLimiter>>,
+    client_ip: Option<Extension<ClientIp>>,
+    TrustedCaller(trusted): TrustedCaller,
+    lang: Lang,
+    Query(query): Query<EmailQuery>
+) -> UnifiedResponse<EmailAvailability> {
+    // Throttled per client so the endpoint can't be used to enumerate accounts; clients without a known IP share one bucket
+    let ip = client_ip.map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |Extension(ClientIp(ip))| ip);
+    if !trusted && !limiter.check(ip) {
+        return error_response_with_status(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too Many Requests".to_string(),
+            lang.text("auth.email_check_rate_limited").to_string(),
+        );
+    }
+
+    if !validate_email(&query.email) {
+        return error_response_generic("Validation Error".to_string(), lang.text("auth.email_invalid").to_string());
+    }
+
+    let repo = SqlUserRepository::new((*pool).clone());
+
+    match repo.find_by_email(&query.email).await {
+        Ok(user) => {
+            success_response(lang.text("auth.email_checked").to_string(), EmailAvailability { available: user.is_none() })
+        },
+        Err(e) => {
+            error!("Handler: Failed to check email availability: {}", e);
+            sql_error_response_generic(e, "Failed to check email availability")
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use axum::{
+    extract::{State, Extension, Query},
+    http::StatusCode,
+};
+use std::sync::Arc;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+use crate::db::repositories::sql_comment_repo::SqlCommentRepository;
+use crate::db::repositories::sql_post_repo::SqlPostRepository;
+use crate::helpers::extractors::{JsonBody, PathId};
+use crate::helpers::messages::Lang;
+use crate::state::ReadPool;
+use crate::helpers::response::{UnifiedResponse, success_response, error_response_generic, error_response_with_status, not_found_response_generic, sql_error_response_generic};
+use crate::model::model::{Comment, CommentCount, CommentQuery, CreateCommentRequest};
+use tracing::{info, error};
+
+// Whether `post_id` exists and `viewer` may see it (comments follow their post's visibility)
+async fn post_visible_to(pool: &SqlitePool, post_id: Uuid, viewer: Option<Uuid>) -> anyhow::Result<bool> {
+    let post = SqlPostRepository::new(pool.clone()).find_by_id(post_id).await?;
+    Ok(post.is_some_and(|post| post.is_visible_to(viewer)))
+}
+
+pub async fn create_comment(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(post_id): PathId,
+    lang: Lang,
+    JsonBody(payload): JsonBody<CreateCommentRequest>
+) -> UnifiedResponse<Comment> {
+    info!("Handler: Creating comment on post {} for user: {}", post_id, user_id);
+
+    if payload.content.trim().is_empty() {
+        return error_response_generic("Bad Request".to_string(), lang.text("comment.content_empty").to_string());
+    }
+
+    match post_visible_to(&pool, post_id, Some(user_id)).await {
+        Ok(true) => {},
+        Ok(false) => return not_found_response_generic(lang.text("post.not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to get post for comment: {}", e);
+            return sql_error_response_generic(e, "Failed to get post");
+        }
+    }
+
+    let repo = SqlCommentRepository::new((*pool).clone());
+
+    match repo.create_comment(post_id, user_id, payload.content).await {
+        Ok(comment) => success_response(lang.text("comment.created").to_string(), comment),
+        Err(e) => {
+            error!("Handler: Failed to create comment: {}", e);
+            sql_error_response_generic(e, "Failed to create comment")
+        }
+    }
+}
+
+pub async fn get_comments(
+    State(pool): State<Arc<SqlitePool>>,
+    State(ReadPool(read_pool)): State<ReadPool>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    PathId(post_id): PathId,
+    Query(query): Query<CommentQuery>,
+    lang: Lang
+) -> UnifiedResponse<Vec<Comment>> {
+    info!("Handler: Getting comments of post: {}", post_id);
+
+    match post_visible_to(&read_pool, post_id, viewer).await {
+        Ok(true) => {},
+        Ok(false) => return not_found_response_generic(lang.text("post.not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to get post for comments: {}", e);
+            return sql_error_response_generic(e, "Failed to get post");
+        }
+    }
+
+    let repo = SqlCommentRepository::new((*pool).clone()).with_read_pool((*read_pool).clone());
+
+    match repo.list_for_post(post_id, query.since, query.order.unwrap_or_default(), query.limit(), query.offset()).await {
+        Ok(comments) => {
+            success_response(
+                lang.format("comment.list_retrieved", &[&comments.len().to_string()]),
+                comments
+            )
+        },
+        Err(e) => {
+            error!("Handler: Failed to get comments: {}", e);
+            sql_error_response_generic(e, "Failed to get comments")
+        }
+    }
+}
+
+// Just the number of comments, for feed cards that don't need the comments themselves
+pub async fn get_comment_count(
+    State(pool): State<Arc<SqlitePool>>,
+    State(ReadPool(read_pool)): State<ReadPool>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    PathId(post_id): PathId,
+    lang: Lang
+) -> UnifiedResponse<CommentCount> {
+    info!("Handler: Counting comments of post: {}", post_id);
+
+    match post_visible_to(&read_pool, post_id, viewer).await {
+        Ok(true) => {},
+        Ok(false) => {
+            return error_response_with_status(
+                StatusCode::NOT_FOUND,
+                "Not Found".to_string(),
+                lang.text("post.not_found").to_string(),
+            );
+        },
+        Err(e) => {
+            error!("Handler: Failed to get post for comment count: {}", e);
+            return sql_error_response_generic(e, "Failed to get post");
+        }
+    }
+
+    let repo = SqlCommentRepository::new((*pool).clone()).with_read_pool((*read_pool).clone());
+
+    match repo.count_for_post(post_id).await {
+        Ok(count) => {
+            success_response(
+                lang.format("comment.count_retrieved", &[&count.to_string()]),
+                CommentCount { post_id, count }
+            )
+        },
+        Err(e) => {
+            error!("Handler: Failed to count comments: {}", e);
+            sql_error_response_generic(e, "Failed to count comments")
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use crate::helpers::errors::{error_catalog, ErrorCatalogEntry};
+use crate::helpers::messages::Lang;
+use crate::helpers::response::{UnifiedResponse, success_response};
+use tracing::info;
+
+pub async fn get_errors(lang: Lang) -> UnifiedResponse<Vec<ErrorCatalogEntry>> {
+    info!("Handler: Getting error catalog");
+
+    let catalog = error_catalog();
+    success_response(lang.format("error.catalog_retrieved", &[&catalog.len().to_string()]), catalog)
+}
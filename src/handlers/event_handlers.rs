@@ -0,0 +1,87 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Response,
+    },
+};
+use futures_util::Stream;
+use std::convert::Infallible;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+use crate::helpers::timestamp;
+use crate::model::model::PostEvent;
+use crate::state::PostEvents;
+use tracing::{info, warn, error};
+
+pub async fn posts_ws(
+    State(post_events): State<PostEvents>,
+    ws: WebSocketUpgrade
+) -> Response {
+    info!("Handler: New WebSocket subscriber for posts");
+
+    // Subscribe before the upgrade so nothing published during the handshake is missed
+    let receiver = post_events.subscribe();
+    let format = timestamp::current();
+    ws.on_upgrade(move |socket| timestamp::scope(format, stream_post_events(socket, receiver)))
+}
+
+async fn stream_post_events(mut socket: WebSocket, mut receiver: broadcast::Receiver<PostEvent>) {
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Ok(event) => {
+                    let payload = match serde_json::to_string(&event) {
+                        Ok(payload) => payload,
+                        Err(e) => {
+                            error!("Failed to serialize post event: {}", e);
+                            continue;
+                        }
+                    };
+                    if socket.send(Message::Text(payload.into())).await.is_err() {
+                        break;
+                    }
+                },
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("WebSocket subscriber lagged, skipped {} post events", skipped);
+                },
+                Err(RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {},
+            },
+        }
+    }
+
+    info!("WebSocket subscriber for posts disconnected");
+}
+
+pub async fn posts_sse(
+    State(post_events): State<PostEvents>
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    info!("Handler: New SSE subscriber for posts");
+
+    // Frames are serialized after the handler returns, outside the request's timestamp format scope
+    let format = timestamp::current();
+
+    // The stream (and its receiver) is dropped by axum as soon as the client goes away
+    let stream = BroadcastStream::new(post_events.subscribe()).filter_map(move |event| match event {
+        Ok(PostEvent::PostCreated(post)) => match timestamp::sync_scope(format, || Event::default().event("post_created").json_data(&post)) {
+            Ok(event) => Some(Ok(event)),
+            Err(e) => {
+                error!("Failed to serialize post event: {}", e);
+                None
+            }
+        },
+        Err(lagged) => {
+            warn!("SSE subscriber {}", lagged);
+            None
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
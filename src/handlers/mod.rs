@@ -1,3 +1,15 @@
+#[allow(clippy::module_inception)]
 pub mod handlers;
 pub mod auth_handlers;
 pub mod post_handlers;
+pub mod event_handlers;
+pub mod admin_handlers;
+pub mod schema_handlers;
+pub mod tag_handlers;
+pub mod error_handlers;
+pub mod comment_handlers;
+pub mod activity_handlers;
+pub mod version_handlers;
+pub mod user_handlers;
+pub mod post_v2_handlers;
+pub mod report_handlers;
@@ -1,48 +1,200 @@
 use axum::{
-    extract::{State, Extension, Path},
-    Json,
+    extract::{rejection::QueryRejection, OriginalUri, State, Extension, Query, Multipart},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
 };
 use std::sync::Arc;
 use sqlx::SqlitePool;
 use uuid::Uuid;
+use chrono::SecondsFormat;
 use serde_json::Value;
 use crate::model::model::{
-    CreatePostRequest, UpdatePostRequest, PostResponse
+    CommentQuery, CreatePostRequest, UpdatePostRequest, BatchDeleteRequest, BatchMode, BatchQuery, BatchResult, MarkReadRequest, PostResponse, PostEvent, PostStatus, PostThread, PostView, PostId, PostWriteResponse, ExpandQuery, ChangesQuery, PostChange, PageQuery, PostListFilter, PostListQuery, QuietQuery, Post, Visibility, ApiResponse
 };
+use crate::db::repositories::sql_comment_repo::SqlCommentRepository;
 use crate::db::repositories::sql_post_repo::SqlPostRepository;
-use crate::helpers::response::{UnifiedResponse, success_response, error_response_generic, not_found_response_generic, sql_error_response_generic};
+use crate::helpers::cache::PostsCache;
+use crate::helpers::extractors::{Deadline, JsonBody, PathId, PreferMinimal};
+use crate::helpers::messages::Lang;
+use crate::helpers::pagination::{total_if, PageLinks, PageStyle};
+use crate::state::{AppState, ReadPool};
+use crate::config::Settings;
+use crate::helpers::validation::{detect_image_type, normalize_tags, validate_slug, MAX_SLUG_LENGTH, MAX_TAGS_PER_POST, MAX_TAG_LENGTH};
+use crate::helpers::response::{UnifiedResponse, success_response, error_response_generic, error_response_with_status, data_response_with_status, not_found_response_generic, sql_error_response_generic};
 use tracing::{info, error};
 
+// Normalized tags, or the 400 to return when one is unusable or they break the limits
+fn validate_tags<T>(tags: Vec<String>, lang: Lang) -> Result<Vec<String>, UnifiedResponse<T>> {
+    let tags = normalize_tags(tags).map_err(|tag| {
+        error_response_generic("Bad Request".to_string(), lang.format("post.tag_invalid", &[&tag]))
+    })?;
+    if tags.len() > MAX_TAGS_PER_POST {
+        return Err(error_response_generic(
+            "Bad Request".to_string(),
+            lang.format("post.too_many_tags", &[&MAX_TAGS_PER_POST.to_string()]),
+        ));
+    }
+    if tags.iter().any(|tag| tag.chars().count() > MAX_TAG_LENGTH) {
+        return Err(error_response_generic(
+            "Bad Request".to_string(),
+            lang.format("post.tag_too_long", &[&MAX_TAG_LENGTH.to_string()]),
+        ));
+    }
+    Ok(tags)
+}
+
 pub async fn create_post(
-    State(pool): State<Arc<SqlitePool>>,
+    State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
-    Json(payload): Json<CreatePostRequest>
-) -> UnifiedResponse<PostResponse> {
+    PreferMinimal(minimal): PreferMinimal,
+    lang: Lang,
+    JsonBody(payload): JsonBody<CreatePostRequest>
+) -> UnifiedResponse<PostWriteResponse> {
     info!("Handler: Creating new post for user: {}", user_id);
-    
+
+    store_post(&state, user_id, minimal, lang, payload).await
+}
+
+// Copies a post the caller can see into a new post of their own, keeping its visibility and tags
+pub async fn duplicate_post(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(id): PathId,
+    PreferMinimal(minimal): PreferMinimal,
+    lang: Lang
+) -> UnifiedResponse<PostWriteResponse> {
+    info!("Handler: Duplicating post: {} for user: {}", id, user_id);
+
+    let repo = SqlPostRepository::new((*state.pool).clone());
+
+    let source = match repo.find_by_id(id).await {
+        Ok(Some(post)) if post.is_visible_to(Some(user_id)) => post,
+        Ok(_) => return not_found_response_generic(lang.text("post.not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to get post to duplicate: {}", e);
+            return sql_error_response_generic(e, "Failed to get post");
+        }
+    };
+
+    let payload = CreatePostRequest {
+        title: format!("{} (copy)", source.title),
+        content: source.content,
+        visibility: source.visibility,
+        status: PostStatus::Draft,
+        tags: source.tags,
+        slug: None,
+    };
+
+    store_post(&state, user_id, minimal, lang, payload).await
+}
+
+// Validates and stores a new post for `user_id`, shared by plain creation and duplication
+async fn store_post(
+    state: &AppState,
+    user_id: Uuid,
+    minimal: bool,
+    lang: Lang,
+    mut payload: CreatePostRequest
+) -> UnifiedResponse<PostWriteResponse> {
     // Validate input
     if payload.title.trim().is_empty() {
-        return error_response_generic("Bad Request".to_string(), "Post title cannot be empty".to_string());
+        return error_response_generic("Bad Request".to_string(), lang.text("post.title_empty").to_string());
     }
 
     if payload.content.trim().is_empty() {
-        return error_response_generic("Bad Request".to_string(), "Post content cannot be empty".to_string());
+        return error_response_generic("Bad Request".to_string(), lang.text("post.content_empty").to_string());
     }
 
-    let repo = SqlPostRepository::new((*pool).clone());
+    payload.tags = match validate_tags(payload.tags, lang) {
+        Ok(tags) => tags,
+        Err(response) => return response,
+    };
+
+    let AppState { pool, post_events, posts_cache, settings, .. } = state;
+    let repo = SqlPostRepository::new((**pool).clone()).with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref())
+        .with_clock(settings.clock.clone());
+
+    // Optional per-user post cap
+    if let Some(max_posts) = settings.max_posts_per_user {
+        match repo.count_by_author(user_id).await {
+            Ok(count) if count >= max_posts => {
+                return error_response_with_status(
+                    StatusCode::FORBIDDEN,
+                    "Forbidden".to_string(),
+                    lang.format("post.limit_reached", &[&max_posts.to_string()]),
+                );
+            },
+            Ok(_) => {},
+            Err(e) => {
+                error!("Handler: Failed to count user posts: {}", e);
+                return sql_error_response_generic(e, "Failed to count user posts");
+            }
+        }
+    }
+
+    if let Some(slug) = &payload.slug {
+        if !settings.client_slugs {
+            return error_response_generic("Bad Request".to_string(), lang.text("post.slug_not_allowed").to_string());
+        }
+        if !validate_slug(slug) {
+            return error_response_generic("Bad Request".to_string(), lang.format("post.slug_invalid", &[&MAX_SLUG_LENGTH.to_string()]));
+        }
+        match repo.slug_exists(slug).await {
+            Ok(true) => {
+                return error_response_with_status(StatusCode::CONFLICT, "Conflict".to_string(), lang.text("post.slug_taken").to_string());
+            },
+            Ok(false) => {},
+            Err(e) => {
+                error!("Handler: Failed to check post slug: {}", e);
+                return sql_error_response_generic(e, "Failed to check post slug");
+            }
+        }
+    }
+
+    // Optional duplicate-title guard
+    if settings.reject_duplicate_post_titles {
+        match repo.find_by_author_and_title(user_id, &payload.title).await {
+            Ok(Some(_)) => {
+                return error_response_with_status(StatusCode::CONFLICT, "Conflict".to_string(), lang.text("post.duplicate_title").to_string());
+            },
+            Ok(None) => {},
+            Err(e) => {
+                error!("Handler: Failed to check duplicate post title: {}", e);
+                return sql_error_response_generic(e, "Failed to check duplicate post title");
+            }
+        }
+    }
     
     match repo.create_post(payload, user_id).await {
         Ok(post) => {
+            posts_cache.invalidate();
+
+            // Live subscribers need the full post, so the re-fetch is only skipped when no event would go out
+            let notify = post.visibility == Visibility::Public && post.status == PostStatus::Published
+                && post_events.receiver_count() > 0;
+            if minimal && !notify {
+                return success_response(lang.format("post.created", &[&post.title]), PostWriteResponse::Minimal(PostId { id: post.id }));
+            }
+
             // Get with author
             match repo.find_by_id_with_author(post.id).await {
                 Ok(Some(post_response)) => {
-                    success_response(
-                        format!("Post '{}' created successfully", post.title),
-                        post_response
-                    )
+                    // Notify live subscribers of public posts; an error here only means nobody is listening
+                    if post_response.visibility == Visibility::Public && post_response.status == PostStatus::Published {
+                        let public = post_response.clone().for_viewer(None, state.settings.expose_author_email);
+                        let _ = post_events.send(PostEvent::PostCreated(public));
+                    }
+
+                    let body = if minimal {
+                        PostWriteResponse::Minimal(PostId { id: post.id })
+                    } else {
+                        PostWriteResponse::Full(Box::new(post_response))
+                    };
+                    success_response(lang.format("post.created", &[&post.title]), body)
                 },
                 Ok(None) => {
-                    error_response_generic("Internal Error".to_string(), "Post created but failed to retrieve with author info".to_string())
+                    error_response_generic("Internal Error".to_string(), lang.text("post.created_fetch_failed").to_string())
                 },
                 Err(e) => {
                     error!("Handler: Failed to get post with author: {}", e);
@@ -57,20 +209,42 @@ pub async fn create_post(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn get_post(
     State(pool): State<Arc<SqlitePool>>,
-    Path(id): Path<Uuid>
-) -> UnifiedResponse<PostResponse> {
+    State(ReadPool(read_pool)): State<ReadPool>,
+    State(settings): State<Arc<Settings>>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    PathId(id): PathId,
+    Query(expand): Query<ExpandQuery>,
+    Deadline(deadline): Deadline,
+    lang: Lang
+) -> UnifiedResponse<PostView> {
     info!("Handler: Getting post: {}", id);
 
-    let repo = SqlPostRepository::new((*pool).clone());
+    let repo = SqlPostRepository::new((*pool).clone()).with_read_pool((*read_pool).clone())
+        .with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref())
+        .with_deadline(deadline);
     
-    match repo.find_by_id_with_author(id).await {
-        Ok(Some(post)) => {
-            success_response("Post retrieved successfully".to_string(), post)
+    let post = if expand.author() {
+        match repo.find_by_id_with_author(id).await {
+            Ok(Some(post)) => repo.with_read_state(viewer, vec![post]).await
+                .map(|mut posts| posts.pop().map(PostView::Expanded)),
+            Ok(None) => Ok(None),
+            Err(e) => Err(e),
+        }
+    } else {
+        repo.find_by_id(id).await.map(|post| post.map(PostView::Lean))
+    };
+
+    match post {
+        // Someone else's private post is reported as missing
+        Ok(Some(post)) if post.is_visible_to(viewer) => {
+            success_response(lang.text("post.retrieved").to_string(), post.for_viewer(viewer, settings.expose_author_email))
         },
-        Ok(None) => {
-            not_found_response_generic("Post not found".to_string())
+        Ok(_) => {
+            not_found_response_generic(lang.text("post.not_found").to_string())
         },
         Err(e) => {
             error!("Handler: Failed to get post: {}", e);
@@ -79,18 +253,152 @@ pub async fn get_post(
     }
 }
 
+// A random public, published post; 404 when there is none
+pub async fn get_random_post(
+    State(pool): State<Arc<SqlitePool>>,
+    State(ReadPool(read_pool)): State<ReadPool>,
+    State(settings): State<Arc<Settings>>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    Deadline(deadline): Deadline,
+    lang: Lang
+) -> UnifiedResponse<PostResponse> {
+    info!("Handler: Getting a random post");
+
+    let repo = SqlPostRepository::new((*pool).clone()).with_read_pool((*read_pool).clone())
+        .with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref())
+        .with_deadline(deadline);
+
+    let post = match repo.find_random_with_author().await {
+        Ok(Some(post)) => repo.with_read_state(viewer, vec![post]).await.map(|mut posts| posts.pop()),
+        Ok(None) => Ok(None),
+        Err(e) => Err(e),
+    };
+
+    match post {
+        Ok(Some(post)) => {
+            success_response(lang.text("post.retrieved").to_string(), post.for_viewer(viewer, settings.expose_author_email))
+        },
+        Ok(None) => error_response_with_status(
+            StatusCode::NOT_FOUND,
+            "Not Found".to_string(),
+            lang.text("post.random_none").to_string(),
+        ),
+        Err(e) => {
+            error!("Handler: Failed to get a random post: {}", e);
+            sql_error_response_generic(e, "Failed to get post")
+        }
+    }
+}
+
+// The expanded post with its like and comment counts and one page of comments, for rendering a post page in one request
+#[allow(clippy::too_many_arguments)]
+pub async fn get_post_full(
+    State(pool): State<Arc<SqlitePool>>,
+    State(ReadPool(read_pool)): State<ReadPool>,
+    State(settings): State<Arc<Settings>>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    PathId(id): PathId,
+    Query(query): Query<CommentQuery>,
+    Deadline(deadline): Deadline,
+    lang: Lang
+) -> UnifiedResponse<PostThread> {
+    info!("Handler: Getting post with comments: {}", id);
+
+    let posts = SqlPostRepository::new((*pool).clone()).with_read_pool((*read_pool).clone())
+        .with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref())
+        .with_deadline(deadline);
+
+    let post = match posts.find_by_id_with_author(id).await {
+        // Someone else's private post is reported as missing
+        Ok(Some(post)) if post.is_visible_to(viewer) => post,
+        Ok(_) => {
+            return error_response_with_status(
+                StatusCode::NOT_FOUND,
+                "Not Found".to_string(),
+                lang.text("post.not_found").to_string(),
+            );
+        },
+        Err(e) => {
+            error!("Handler: Failed to get post: {}", e);
+            return sql_error_response_generic(e, "Failed to get post");
+        }
+    };
+
+    let comments = SqlCommentRepository::new((*pool).clone()).with_read_pool((*read_pool).clone());
+    let (mut with_read, like_count, comment_count, page) = match tokio::try_join!(
+        posts.with_read_state(viewer, vec![post]),
+        posts.count_likes(id),
+        comments.count_for_post(id),
+        comments.list_for_post(id, query.since, query.order.unwrap_or_default(), query.limit(), query.offset()),
+    ) {
+        Ok(found) => found,
+        Err(e) => {
+            error!("Handler: Failed to get post thread: {}", e);
+            return sql_error_response_generic(e, "Failed to get post");
+        }
+    };
+
+    let Some(post) = with_read.pop() else {
+        return not_found_response_generic(lang.text("post.not_found").to_string());
+    };
+    let post = post.for_viewer(viewer, settings.expose_author_email);
+
+    success_response(
+        lang.text("post.retrieved").to_string(),
+        PostThread { post, like_count, comment_count, comments: page }
+    )
+}
+
+// Same headers as GET (axum drops the body and keeps Content-Length), but a real 404 when the post is missing
+#[allow(clippy::too_many_arguments)]
+pub async fn head_post(
+    State(pool): State<Arc<SqlitePool>>,
+    read_pool: State<ReadPool>,
+    settings: State<Arc<Settings>>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    PathId(id): PathId,
+    expand: Query<ExpandQuery>,
+    deadline: Deadline,
+    lang: Lang
+) -> Response {
+    match get_post(State(pool), read_pool, settings, Extension(viewer), PathId(id), expand, deadline, lang).await {
+        UnifiedResponse::Success(ApiResponse { data: None, .. }) => StatusCode::NOT_FOUND.into_response(),
+        response => response.into_response(),
+    }
+}
+
 pub async fn get_user_posts(
     State(pool): State<Arc<SqlitePool>>,
-    Extension(user_id): Extension<Uuid>
-) -> UnifiedResponse<Vec<crate::model::model::Post>> {
+    State(ReadPool(read_pool)): State<ReadPool>,
+    State(settings): State<Arc<Settings>>,
+    Extension(user_id): Extension<Uuid>,
+    Query(expand): Query<ExpandQuery>,
+    Query(quiet): Query<QuietQuery>,
+    lang: Lang
+) -> UnifiedResponse<Vec<PostView>> {
     info!("Handler: Getting posts for user: {}", user_id);
 
-    let repo = SqlPostRepository::new((*pool).clone());
+    let repo = SqlPostRepository::new((*pool).clone()).with_read_pool((*read_pool).clone())
+        .with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref());
     
-    match repo.find_by_author(user_id).await {
+    let posts = if expand.author() {
+        match repo.find_by_author_with_author(user_id).await {
+            Ok(posts) => repo.with_read_state(Some(user_id), posts).await
+                .map(|posts| posts.into_iter().map(PostView::Expanded).collect::<Vec<_>>()),
+            Err(e) => Err(e),
+        }
+    } else {
+        repo.find_by_author(user_id).await
+            .map(|posts| posts.into_iter().map(PostView::Lean).collect())
+    };
+
+    match posts {
         Ok(posts) => {
             success_response(
-                format!("Retrieved {} posts", posts.len()),
+                quiet.message(|| lang.format("post.list_retrieved", &[&posts.len().to_string()])),
                 posts
             )
         },
@@ -101,49 +409,175 @@ pub async fn get_user_posts(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn get_all_posts(
-    State(pool): State<Arc<SqlitePool>>
-) -> UnifiedResponse<Vec<PostResponse>> {
+    State(ReadPool(read_pool)): State<ReadPool>,
+    State(settings): State<Arc<Settings>>,
+    State(posts_cache): State<Arc<PostsCache>>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    OriginalUri(uri): OriginalUri,
+    Query(expand): Query<ExpandQuery>,
+    Query(quiet): Query<QuietQuery>,
+    Deadline(deadline): Deadline,
+    lang: Lang,
+    list: Result<Query<PostListQuery>, QueryRejection>
+) -> (Option<PageLinks>, UnifiedResponse<Vec<PostView>>) {
     info!("Handler: Getting all posts");
 
-    let repo = SqlPostRepository::new((*pool).clone());
+    let filter = match list {
+        Ok(Query(list)) => list.validate(settings.default_post_sort, lang),
+        Err(rejection) => Err(lang.format("request.invalid_query", &[&rejection.body_text()])),
+    };
+    let filter = match filter {
+        Ok(filter) => filter,
+        Err(message) => return (None, error_response_generic("Bad Request".to_string(), message)),
+    };
+
+    // Only the anonymous feed is shared between callers, so it's the only one cached (whole and in the default order)
+    let cacheable = viewer.is_none() && filter == PostListFilter::everything(settings.default_post_sort);
+    if let Some(posts) = posts_cache.get(expand.author()).filter(|_| cacheable) {
+        return (None, success_response(
+            quiet.message(|| lang.format("post.list_retrieved", &[&posts.len().to_string()])),
+            posts
+        ));
+    }
+    let generation = posts_cache.generation();
+
+    // Read-only, so the replica serves as the repository's only pool
+    let repo = SqlPostRepository::new((*read_pool).clone()).with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref())
+        .with_deadline(deadline);
+
+    // Only a paged request (one with a limit) gets links
+    let links = match filter.limit {
+        Some(limit) => match total_if(settings.pagination_links, repo.count_posts(viewer, &filter)).await {
+            Ok(total) => total.and_then(|total| {
+                PageLinks::new(&uri, PageStyle::Offset, filter.offset.into(), limit.into(), total.max(0) as u64)
+            }),
+            Err(e) => {
+                error!("Handler: Failed to count posts: {}", e);
+                return (None, sql_error_response_generic(e, "Failed to get all posts"));
+            }
+        },
+        None => None,
+    };
     
-    match repo.get_all_posts().await {
+    let posts = if expand.author() {
+        match repo.get_all_posts(viewer, &filter).await {
+            Ok(posts) => repo.with_read_state(viewer, posts).await.map(|posts| {
+                posts
+                    .into_iter()
+                    .map(|post| PostView::Expanded(post.for_viewer(viewer, settings.expose_author_email)))
+                    .collect::<Vec<_>>()
+            }),
+            Err(e) => Err(e),
+        }
+    } else {
+        repo.get_all_posts_lean(viewer, &filter).await
+            .map(|posts| posts.into_iter().map(PostView::Lean).collect())
+    };
+
+    match &posts {
+        Ok(posts) if cacheable => posts_cache.insert(generation, expand.author(), posts.clone()),
+        _ => {},
+    }
+
+    match posts {
         Ok(posts) => {
-            success_response(
-                format!("Retrieved {} posts", posts.len()),
+            (links, success_response(
+                quiet.message(|| lang.format("post.list_retrieved", &[&posts.len().to_string()])),
                 posts
-            )
+            ))
         },
         Err(e) => {
             error!("Handler: Failed to get all posts: {}", e);
-            sql_error_response_generic(e, "Failed to get all posts")
+            (None, sql_error_response_generic(e, "Failed to get all posts"))
         }
     }
 }
 
-pub async fn update_post(
+#[allow(clippy::too_many_arguments)]
+pub async fn get_post_changes(
     State(pool): State<Arc<SqlitePool>>,
+    State(ReadPool(read_pool)): State<ReadPool>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<ChangesQuery>,
+    Query(quiet): Query<QuietQuery>,
+    Deadline(deadline): Deadline,
+    lang: Lang
+) -> (Option<PageLinks>, UnifiedResponse<Vec<PostChange>>) {
+    info!("Handler: Getting posts changed since: {}", query.since);
+
+    let repo = SqlPostRepository::new((*pool).clone()).with_read_pool((*read_pool).clone())
+        .with_deadline(deadline);
+    let limit = query.limit();
+
+    match repo.find_changed_since(query.since, query.after, limit, viewer).await {
+        Ok((changes, more)) => {
+            // The next page picks up after the last entry of this one
+            let links = changes.last().filter(|_| more).and_then(|last| {
+                PageLinks::next(&uri, &[
+                    ("since", last.updated_at().to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+                    ("after", last.id().to_string()),
+                    ("limit", limit.to_string()),
+                ])
+            });
+            (links, success_response(
+                quiet.message(|| lang.format("post.changes_retrieved", &[&changes.len().to_string()])),
+                changes
+            ))
+        },
+        Err(e) => {
+            error!("Handler: Failed to get post changes: {}", e);
+            (None, sql_error_response_generic(e, "Failed to get post changes"))
+        }
+    }
+}
+
+pub async fn update_post(
+    State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
-    Path(id): Path<Uuid>,
-    Json(payload): Json<UpdatePostRequest>
-) -> UnifiedResponse<PostResponse> {
+    PathId(id): PathId,
+    PreferMinimal(minimal): PreferMinimal,
+    lang: Lang,
+    JsonBody(mut payload): JsonBody<UpdatePostRequest>
+) -> UnifiedResponse<PostWriteResponse> {
     info!("Handler: Updating post: {} for user: {}", id, user_id);
 
-    let repo = SqlPostRepository::new((*pool).clone());
+    if payload.is_empty() {
+        return error_response_generic("Bad Request".to_string(), lang.text("request.empty_update").to_string());
+    }
+
+    if let Some(tags) = payload.tags.take() {
+        match validate_tags(tags, lang) {
+            Ok(tags) => payload.tags = Some(tags),
+            Err(response) => return response,
+        }
+    }
+
+    let repo = SqlPostRepository::new((*state.pool).clone()).with_reading_wpm(state.settings.reading_wpm)
+        .with_api_prefix(state.settings.api_prefix.as_deref())
+        .with_clock(state.settings.clock.clone());
     
     match repo.update_post(id, user_id, payload).await {
         Ok(Some(post)) => {
+            state.posts_cache.invalidate();
+
+            if minimal {
+                return success_response(lang.format("post.updated", &[&post.title]), PostWriteResponse::Minimal(PostId { id: post.id }));
+            }
+
             // Get with author
             match repo.find_by_id_with_author(post.id).await {
                 Ok(Some(post_response)) => {
                     success_response(
-                        format!("Post '{}' updated successfully", post.title),
-                        post_response
+                        lang.format("post.updated", &[&post.title]),
+                        PostWriteResponse::Full(Box::new(post_response))
                     )
                 },
                 Ok(None) => {
-                    error_response_generic("Internal Error".to_string(), "Post updated but failed to retrieve with author info".to_string())
+                    error_response_generic("Internal Error".to_string(), lang.text("post.updated_fetch_failed").to_string())
                 },
                 Err(e) => {
                     error!("Handler: Failed to get updated post with author: {}", e);
@@ -152,7 +586,7 @@ pub async fn update_post(
             }
         },
         Ok(None) => {
-            not_found_response_generic("Post not found or you don't have permission to update it".to_string())
+            not_found_response_generic(lang.text("post.update_not_found").to_string())
         },
         Err(e) => {
             error!("Handler: Failed to update post: {}", e);
@@ -161,25 +595,671 @@ pub async fn update_post(
     }
 }
 
+pub async fn publish_post(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(id): PathId,
+    lang: Lang
+) -> UnifiedResponse<PostResponse> {
+    info!("Handler: Publishing post: {} for user: {}", id, user_id);
+
+    set_post_status(&state, user_id, id, PostStatus::Published, lang).await
+}
+
+pub async fn unpublish_post(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(id): PathId,
+    lang: Lang
+) -> UnifiedResponse<PostResponse> {
+    info!("Handler: Unpublishing post: {} for user: {}", id, user_id);
+
+    set_post_status(&state, user_id, id, PostStatus::Draft, lang).await
+}
+
+// Moves the caller's post between draft and published; someone else's post is reported as missing
+async fn set_post_status(
+    state: &AppState,
+    user_id: Uuid,
+    id: Uuid,
+    status: PostStatus,
+    lang: Lang
+) -> UnifiedResponse<PostResponse> {
+    let repo = SqlPostRepository::new((*state.pool).clone()).with_reading_wpm(state.settings.reading_wpm)
+        .with_api_prefix(state.settings.api_prefix.as_deref())
+        .with_clock(state.settings.clock.clone());
+
+    let was_published = match repo.find_by_id(id).await {
+        Ok(post) => post.is_some_and(|post| post.status == PostStatus::Published),
+        Err(e) => {
+            error!("Handler: Failed to get post: {}", e);
+            return sql_error_response_generic(e, "Failed to get post");
+        }
+    };
+
+    let post = match repo.set_status(id, user_id, status).await {
+        Ok(Some(post)) => post,
+        Ok(None) => return not_found_response_generic(lang.text("post.update_not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to set post status: {}", e);
+            return sql_error_response_generic(e, "Failed to set post status");
+        }
+    };
+    state.posts_cache.invalidate();
+
+    let post_response = match repo.find_by_id_with_author(post.id).await {
+        Ok(Some(post_response)) => post_response,
+        Ok(None) => {
+            return error_response_generic("Internal Error".to_string(), lang.text("post.updated_fetch_failed").to_string());
+        },
+        Err(e) => {
+            error!("Handler: Failed to get post with author: {}", e);
+            return sql_error_response_generic(e, "Failed to get post with author");
+        }
+    };
+
+    // A public post going live is news to subscribers, as if it had just been created
+    if !was_published && post_response.status == PostStatus::Published && post_response.visibility == Visibility::Public {
+        let public = post_response.clone().for_viewer(None, state.settings.expose_author_email);
+        let _ = state.post_events.send(PostEvent::PostCreated(public));
+    }
+
+    let message = match status {
+        PostStatus::Published => lang.format("post.published", &[&post.title]),
+        PostStatus::Draft => lang.format("post.unpublished", &[&post.title]),
+    };
+    success_response(message, post_response)
+}
+
+// Soft delete: the post goes to the owner's trash, from where it can be restored or purged
 pub async fn delete_post(
     State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    State(posts_cache): State<Arc<PostsCache>>,
     Extension(user_id): Extension<Uuid>,
-    Path(id): Path<Uuid>
+    PathId(id): PathId,
+    lang: Lang
 ) -> UnifiedResponse<Value> {
     info!("Handler: Deleting post: {} for user: {}", id, user_id);
 
-    let repo = SqlPostRepository::new((*pool).clone());
+    let repo = SqlPostRepository::new((*pool).clone()).with_clock(settings.clock.clone());
     
     match repo.delete_post(id, user_id).await {
         Ok(true) => {
-            success_response("Post deleted successfully".to_string(), Value::Null)
+            posts_cache.invalidate();
+            success_response(lang.text("post.deleted").to_string(), Value::Null)
         },
         Ok(false) => {
-            not_found_response_generic("Post not found or you don't have permission to delete it".to_string())
+            not_found_response_generic(lang.text("post.delete_not_found").to_string())
         },
         Err(e) => {
             error!("Handler: Failed to delete post: {}", e);
             sql_error_response_generic(e, "Failed to delete post")
         }
     }
-} 
\ No newline at end of file
+}
+
+// The caller's soft-deleted posts
+pub async fn get_trashed_posts(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user_id): Extension<Uuid>,
+    Query(quiet): Query<QuietQuery>,
+    lang: Lang
+) -> UnifiedResponse<Vec<Post>> {
+    info!("Handler: Getting trashed posts for user: {}", user_id);
+
+    let repo = SqlPostRepository::new((*pool).clone());
+
+    match repo.find_trashed_by(user_id).await {
+        Ok(posts) => success_response(
+            quiet.message(|| lang.format("post.trash_retrieved", &[&posts.len().to_string()])),
+            posts
+        ),
+        Err(e) => {
+            error!("Handler: Failed to get trashed posts: {}", e);
+            sql_error_response_generic(e, "Failed to get trashed posts")
+        }
+    }
+}
+
+// Takes a post back out of the owner's trash; anything else is a 404
+pub async fn restore_post(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    State(posts_cache): State<Arc<PostsCache>>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(id): PathId,
+    lang: Lang
+) -> UnifiedResponse<Post> {
+    info!("Handler: Restoring post: {} for user: {}", id, user_id);
+
+    let repo = SqlPostRepository::new((*pool).clone()).with_clock(settings.clock.clone());
+
+    match repo.restore_post(id, user_id).await {
+        Ok(Some(post)) => {
+            posts_cache.invalidate();
+            success_response(lang.text("post.restored").to_string(), post)
+        },
+        Ok(None) => error_response_with_status(
+            StatusCode::NOT_FOUND,
+            "Not Found".to_string(),
+            lang.text("post.purge_not_found").to_string(),
+        ),
+        Err(e) => {
+            error!("Handler: Failed to restore post: {}", e);
+            sql_error_response_generic(e, "Failed to restore post")
+        }
+    }
+}
+
+// Empties a soft-deleted post out of the owner's trash; live posts are a 404 like missing ones
+pub async fn purge_post(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(id): PathId,
+    lang: Lang
+) -> UnifiedResponse<Value> {
+    info!("Handler: Purging post: {} for user: {}", id, user_id);
+
+    let repo = SqlPostRepository::new((*pool).clone());
+
+    match repo.purge_post(id, user_id).await {
+        Ok(true) => success_response(lang.text("post.purged").to_string(), Value::Null),
+        Ok(false) => error_response_with_status(
+            StatusCode::NOT_FOUND,
+            "Not Found".to_string(),
+            lang.text("post.purge_not_found").to_string(),
+        ),
+        Err(e) => {
+            error!("Handler: Failed to purge post: {}", e);
+            sql_error_response_generic(e, "Failed to purge post")
+        }
+    }
+}
+
+// Each distinct id with the index of its first appearance in the request
+fn indexed_ids(ids: Vec<Uuid>) -> Vec<(usize, Uuid)> {
+    let mut seen = std::collections::HashSet::new();
+    ids.into_iter().enumerate().filter(|(_, id)| seen.insert(*id)).collect()
+}
+
+// Reply for a finished batch; an atomic batch with failures was rolled back, so nothing succeeded
+fn batch_response(mode: BatchMode, mut result: BatchResult<Uuid>, lang: Lang, message: String) -> UnifiedResponse<BatchResult<Uuid>> {
+    if mode == BatchMode::Atomic && !result.is_complete() {
+        result.succeeded.clear();
+        return data_response_with_status(
+            StatusCode::CONFLICT,
+            lang.format("batch.rolled_back", &[&result.failed.len().to_string()]),
+            result,
+        );
+    }
+    success_response(message, result)
+}
+
+// Most ids one batch delete may name
+const MAX_BATCH_DELETE: usize = 100;
+
+pub async fn batch_delete_posts(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    State(posts_cache): State<Arc<PostsCache>>,
+    Extension(user_id): Extension<Uuid>,
+    Query(batch): Query<BatchQuery>,
+    lang: Lang,
+    JsonBody(payload): JsonBody<BatchDeleteRequest>
+) -> UnifiedResponse<BatchResult<Uuid>> {
+    info!("Handler: Batch deleting {} posts for user: {}", payload.ids.len(), user_id);
+
+    let items = indexed_ids(payload.ids);
+    if items.is_empty() || items.len() > MAX_BATCH_DELETE {
+        return error_response_generic(
+            "Bad Request".to_string(),
+            lang.format("post.batch_size", &[&MAX_BATCH_DELETE.to_string()]),
+        );
+    }
+
+    let repo = SqlPostRepository::new((*pool).clone()).with_clock(settings.clock.clone());
+    let ids: Vec<Uuid> = items.iter().map(|(_, id)| *id).collect();
+
+    match repo.delete_many(&ids, user_id, batch.mode).await {
+        Ok(deleted) => {
+            let result = BatchResult::for_ids(&items, &deleted, lang.text("post.delete_not_found"));
+            // A rolled-back atomic batch deleted nothing
+            let applied = batch.mode == BatchMode::Partial || result.is_complete();
+            if applied && !deleted.is_empty() {
+                posts_cache.invalidate();
+            }
+            let message = lang.format("post.batch_deleted", &[&deleted.len().to_string()]);
+            batch_response(batch.mode, result, lang, message)
+        },
+        Err(e) => {
+            error!("Handler: Failed to batch delete posts: {}", e);
+            sql_error_response_generic(e, "Failed to delete posts")
+        }
+    }
+}
+
+pub async fn upload_post_cover(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    State(posts_cache): State<Arc<PostsCache>>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(id): PathId,
+    lang: Lang,
+    mut multipart: Multipart
+) -> UnifiedResponse<PostResponse> {
+    info!("Handler: Uploading cover for post: {} by user: {}", id, user_id);
+
+    let repo = SqlPostRepository::new((*pool).clone()).with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref())
+        .with_clock(settings.clock.clone());
+
+    // Only the owner may set the cover
+    match repo.owned_by(id, user_id).await {
+        Ok(true) => {},
+        Ok(false) => {
+            return not_found_response_generic(lang.text("post.update_not_found").to_string());
+        },
+        Err(e) => {
+            error!("Handler: Failed to get post for cover upload: {}", e);
+            return sql_error_response_generic(e, "Failed to get post");
+        }
+    }
+
+    let unsupported = || error_response_with_status(
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        "Unsupported Media Type".to_string(),
+        lang.text("post.cover_unsupported").to_string(),
+    );
+    let too_large = || error_response_with_status(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "Payload Too Large".to_string(),
+        lang.format("post.cover_too_large", &[&settings.max_cover_bytes.to_string()]),
+    );
+
+    let mut cover = None;
+    loop {
+        let mut field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => {
+                return error_response_with_status(e.status(), "Bad Request".to_string(), e.body_text());
+            }
+        };
+        if field.name() != Some("cover") {
+            continue;
+        }
+
+        let content_type = field.content_type().unwrap_or_default().to_ascii_lowercase();
+        if !content_type.starts_with("image/") {
+            return unsupported();
+        }
+
+        // Read in chunks so an oversized upload is cut off at the limit
+        let mut data = Vec::new();
+        loop {
+            match field.chunk().await {
+                Ok(Some(chunk)) => {
+                    if data.len() + chunk.len() > settings.max_cover_bytes {
+                        return too_large();
+                    }
+                    data.extend_from_slice(&chunk);
+                },
+                Ok(None) => break,
+                Err(e) => {
+                    return error_response_with_status(e.status(), "Bad Request".to_string(), e.body_text());
+                }
+            }
+        }
+
+        // The bytes must actually be the image type that was declared
+        match detect_image_type(&data) {
+            Some(detected) if detected == content_type || (detected == "image/jpeg" && content_type == "image/jpg") => {
+                cover = Some((detected, data));
+            },
+            _ => return unsupported(),
+        }
+        break;
+    }
+
+    let (content_type, data) = match cover {
+        Some(cover) => cover,
+        None => {
+            return error_response_generic("Bad Request".to_string(), lang.text("post.cover_missing").to_string());
+        }
+    };
+
+    if let Err(e) = repo.set_cover(id, content_type, &data).await {
+        error!("Handler: Failed to store cover: {}", e);
+        return sql_error_response_generic(e, "Failed to store cover image");
+    }
+    posts_cache.invalidate();
+
+    match repo.find_by_id_with_author(id).await {
+        Ok(Some(post_response)) => {
+            success_response(lang.text("post.cover_uploaded").to_string(), post_response)
+        },
+        Ok(None) => {
+            not_found_response_generic(lang.text("post.not_found").to_string())
+        },
+        Err(e) => {
+            error!("Handler: Failed to get post with author: {}", e);
+            sql_error_response_generic(e, "Failed to get post with author")
+        }
+    }
+}
+
+pub async fn get_post_cover(
+    State(pool): State<Arc<SqlitePool>>,
+    State(ReadPool(read_pool)): State<ReadPool>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    PathId(id): PathId,
+    lang: Lang
+) -> Response {
+    info!("Handler: Getting cover for post: {}", id);
+
+    let repo = SqlPostRepository::new((*pool).clone()).with_read_pool((*read_pool).clone());
+
+    // Covers follow the visibility of their post
+    let cover = match repo.find_by_id(id).await {
+        Ok(Some(post)) if post.is_visible_to(viewer) => repo.find_cover(id).await,
+        Ok(_) => Ok(None),
+        Err(e) => Err(e),
+    };
+
+    match cover {
+        Ok(Some((content_type, data))) => {
+            ([(header::CONTENT_TYPE, content_type)], data).into_response()
+        },
+        Ok(None) => {
+            error_response_with_status::<Value>(
+                StatusCode::NOT_FOUND,
+                "Not Found".to_string(),
+                lang.text("post.cover_not_found").to_string(),
+            ).into_response()
+        },
+        Err(e) => {
+            error!("Handler: Failed to get cover: {}", e);
+            sql_error_response_generic::<Value>(e, "Failed to get cover image").into_response()
+        }
+    }
+}
+
+// Just the content, for integrations that want the markdown without the JSON envelope
+pub async fn get_post_raw(
+    State(pool): State<Arc<SqlitePool>>,
+    State(ReadPool(read_pool)): State<ReadPool>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    PathId(id): PathId,
+    lang: Lang
+) -> Response {
+    info!("Handler: Getting raw content of post: {}", id);
+
+    let repo = SqlPostRepository::new((*pool).clone()).with_read_pool((*read_pool).clone());
+
+    match repo.find_by_id(id).await {
+        Ok(Some(post)) if post.is_visible_to(viewer) => {
+            ([(header::CONTENT_TYPE, "text/markdown; charset=utf-8")], post.content).into_response()
+        },
+        Ok(_) => {
+            error_response_with_status::<Value>(
+                StatusCode::NOT_FOUND,
+                "Not Found".to_string(),
+                lang.text("post.not_found").to_string(),
+            ).into_response()
+        },
+        Err(e) => {
+            error!("Handler: Failed to get raw post: {}", e);
+            sql_error_response_generic::<Value>(e, "Failed to get post").into_response()
+        }
+    }
+}
+
+pub async fn like_post(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(id): PathId,
+    lang: Lang
+) -> UnifiedResponse<Value> {
+    info!("Handler: User {} liking post: {}", user_id, id);
+
+    let repo = SqlPostRepository::new((*pool).clone()).with_clock(settings.clock.clone());
+
+    // Only posts the caller can see may be liked
+    match repo.find_by_id(id).await {
+        Ok(Some(post)) if post.is_visible_to(Some(user_id)) => {},
+        Ok(_) => return not_found_response_generic(lang.text("post.not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to get post to like: {}", e);
+            return sql_error_response_generic(e, "Failed to get post");
+        }
+    }
+
+    match repo.like_post(id, user_id).await {
+        Ok(()) => success_response(lang.text("post.liked").to_string(), Value::Null),
+        Err(e) => {
+            error!("Handler: Failed to like post: {}", e);
+            sql_error_response_generic(e, "Failed to like post")
+        }
+    }
+}
+
+pub async fn unlike_post(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(id): PathId,
+    lang: Lang
+) -> UnifiedResponse<Value> {
+    info!("Handler: User {} unliking post: {}", user_id, id);
+
+    let repo = SqlPostRepository::new((*pool).clone());
+
+    match repo.unlike_post(id, user_id).await {
+        Ok(true) => success_response(lang.text("post.unliked").to_string(), Value::Null),
+        Ok(false) => not_found_response_generic(lang.text("post.like_not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to unlike post: {}", e);
+            sql_error_response_generic(e, "Failed to unlike post")
+        }
+    }
+}
+
+pub async fn get_liked_posts(
+    State(ReadPool(read_pool)): State<ReadPool>,
+    State(settings): State<Arc<Settings>>,
+    Extension(user_id): Extension<Uuid>,
+    OriginalUri(uri): OriginalUri,
+    Query(page): Query<PageQuery>,
+    Query(quiet): Query<QuietQuery>,
+    lang: Lang
+) -> (Option<PageLinks>, UnifiedResponse<Vec<PostResponse>>) {
+    info!("Handler: Getting posts liked by user: {}", user_id);
+
+    let repo = SqlPostRepository::new((*read_pool).clone()).with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref());
+
+    let total = match total_if(settings.pagination_links, repo.count_liked_by(user_id)).await {
+        Ok(total) => total,
+        Err(e) => {
+            error!("Handler: Failed to count liked posts: {}", e);
+            return (None, sql_error_response_generic(e, "Failed to get liked posts"));
+        }
+    };
+
+    let posts = match repo.find_liked_by(user_id, page.limit(), page.offset()).await {
+        Ok(posts) => repo.with_read_state(Some(user_id), posts).await,
+        Err(e) => Err(e),
+    };
+
+    match posts {
+        Ok(posts) => {
+            let posts: Vec<PostResponse> = posts
+                .into_iter()
+                .map(|post| post.for_viewer(Some(user_id), settings.expose_author_email))
+                .collect();
+            (
+                total.and_then(|total| PageLinks::for_page(&uri, &page, total)),
+                success_response(
+                    quiet.message(|| lang.format("post.liked_list_retrieved", &[&posts.len().to_string()])),
+                    posts
+                ),
+            )
+        },
+        Err(e) => {
+            error!("Handler: Failed to get liked posts: {}", e);
+            (None, sql_error_response_generic(e, "Failed to get liked posts"))
+        }
+    }
+}
+
+// Posts by the people the caller follows, newest first
+pub async fn get_feed(
+    State(ReadPool(read_pool)): State<ReadPool>,
+    State(settings): State<Arc<Settings>>,
+    Extension(user_id): Extension<Uuid>,
+    OriginalUri(uri): OriginalUri,
+    Query(page): Query<PageQuery>,
+    Query(quiet): Query<QuietQuery>,
+    lang: Lang
+) -> (Option<PageLinks>, UnifiedResponse<Vec<PostResponse>>) {
+    info!("Handler: Getting feed of user: {}", user_id);
+
+    let repo = SqlPostRepository::new((*read_pool).clone()).with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref());
+
+    let total = match total_if(settings.pagination_links, repo.count_feed(user_id)).await {
+        Ok(total) => total,
+        Err(e) => {
+            error!("Handler: Failed to count feed posts: {}", e);
+            return (None, sql_error_response_generic(e, "Failed to get feed"));
+        }
+    };
+
+    let posts = match repo.find_feed(user_id, page.limit(), page.offset()).await {
+        Ok(posts) => repo.with_read_state(Some(user_id), posts).await,
+        Err(e) => Err(e),
+    };
+
+    match posts {
+        Ok(posts) => {
+            let posts: Vec<PostResponse> = posts
+                .into_iter()
+                .map(|post| post.for_viewer(Some(user_id), settings.expose_author_email))
+                .collect();
+            (
+                total.and_then(|total| PageLinks::for_page(&uri, &page, total)),
+                success_response(
+                    quiet.message(|| lang.format("post.feed_retrieved", &[&posts.len().to_string()])),
+                    posts
+                ),
+            )
+        },
+        Err(e) => {
+            error!("Handler: Failed to get feed: {}", e);
+            (None, sql_error_response_generic(e, "Failed to get feed"))
+        }
+    }
+}
+
+pub async fn mark_post_read(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(id): PathId,
+    lang: Lang
+) -> UnifiedResponse<Value> {
+    info!("Handler: User {} marking post read: {}", user_id, id);
+
+    let repo = SqlPostRepository::new((*pool).clone()).with_clock(settings.clock.clone());
+
+    // Only posts the caller can see may be marked read
+    match repo.mark_read(&[id], user_id, BatchMode::Partial).await {
+        Ok(read) if read.is_empty() => not_found_response_generic(lang.text("post.not_found").to_string()),
+        Ok(_) => success_response(lang.text("post.marked_read").to_string(), Value::Null),
+        Err(e) => {
+            error!("Handler: Failed to mark post read: {}", e);
+            sql_error_response_generic(e, "Failed to mark post read")
+        }
+    }
+}
+
+// Most ids one bulk mark-as-read may name
+const MAX_BATCH_READ: usize = 100;
+
+pub async fn mark_posts_read(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    Extension(user_id): Extension<Uuid>,
+    Query(batch): Query<BatchQuery>,
+    lang: Lang,
+    JsonBody(payload): JsonBody<MarkReadRequest>
+) -> UnifiedResponse<BatchResult<Uuid>> {
+    info!("Handler: User {} marking {} posts read", user_id, payload.ids.len());
+
+    let items = indexed_ids(payload.ids);
+    if items.is_empty() || items.len() > MAX_BATCH_READ {
+        return error_response_generic(
+            "Bad Request".to_string(),
+            lang.format("post.batch_size", &[&MAX_BATCH_READ.to_string()]),
+        );
+    }
+
+    let repo = SqlPostRepository::new((*pool).clone()).with_clock(settings.clock.clone());
+    let ids: Vec<Uuid> = items.iter().map(|(_, id)| *id).collect();
+
+    match repo.mark_read(&ids, user_id, batch.mode).await {
+        Ok(read) => {
+            let result = BatchResult::for_ids(&items, &read, lang.text("post.not_found"));
+            let message = lang.format("post.batch_marked_read", &[&read.len().to_string()]);
+            batch_response(batch.mode, result, lang, message)
+        },
+        Err(e) => {
+            error!("Handler: Failed to mark posts read: {}", e);
+            sql_error_response_generic(e, "Failed to mark posts read")
+        }
+    }
+}
+
+pub async fn get_unread_posts(
+    State(ReadPool(read_pool)): State<ReadPool>,
+    State(settings): State<Arc<Settings>>,
+    Extension(user_id): Extension<Uuid>,
+    OriginalUri(uri): OriginalUri,
+    Query(page): Query<PageQuery>,
+    Query(quiet): Query<QuietQuery>,
+    lang: Lang
+) -> (Option<PageLinks>, UnifiedResponse<Vec<PostResponse>>) {
+    info!("Handler: Getting posts unread by user: {}", user_id);
+
+    let repo = SqlPostRepository::new((*read_pool).clone()).with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref());
+
+    let total = match total_if(settings.pagination_links, repo.count_unread_by(user_id)).await {
+        Ok(total) => total,
+        Err(e) => {
+            error!("Handler: Failed to count unread posts: {}", e);
+            return (None, sql_error_response_generic(e, "Failed to get unread posts"));
+        }
+    };
+
+    match repo.find_unread_by(user_id, page.limit(), page.offset()).await {
+        Ok(posts) => {
+            let posts: Vec<PostResponse> = posts
+                .into_iter()
+                .map(|post| post.for_viewer(Some(user_id), settings.expose_author_email))
+                .collect();
+            (
+                total.and_then(|total| PageLinks::for_page(&uri, &page, total)),
+                success_response(
+                    quiet.message(|| lang.format("post.unread_list_retrieved", &[&posts.len().to_string()])),
+                    posts
+                ),
+            )
+        },
+        Err(e) => {
+            error!("Handler: Failed to get unread posts: {}", e);
+            (None, sql_error_response_generic(e, "Failed to get unread posts"))
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use axum::extract::{State, Extension, Query};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::config::Settings;
+use crate::db::repositories::sql_post_repo::SqlPostRepository;
+use crate::helpers::extractors::{Deadline, PathId};
+use crate::helpers::messages::Lang;
+use crate::helpers::response::{UnifiedResponse, success_response, not_found_response_generic, sql_error_response_generic};
+use crate::model::model::{PostListFilter, PostV2Response, QuietQuery};
+use crate::state::ReadPool;
+use tracing::{info, error};
+
+// /v2 reads share the v1 repository queries; only the response mapping differs
+
+pub async fn get_all_posts(
+    State(ReadPool(read_pool)): State<ReadPool>,
+    State(settings): State<Arc<Settings>>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    Query(quiet): Query<QuietQuery>,
+    Deadline(deadline): Deadline,
+    lang: Lang
+) -> UnifiedResponse<Vec<PostV2Response>> {
+    info!("Handler: Getting all posts (v2)");
+
+    let repo = SqlPostRepository::new((*read_pool).clone()).with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref())
+        .with_deadline(deadline);
+
+    match repo.get_all_posts(viewer, &PostListFilter::everything(settings.default_post_sort)).await {
+        Ok(posts) => {
+            let posts: Vec<PostV2Response> = posts.into_iter().map(PostV2Response::from).collect();
+            success_response(
+                quiet.message(|| lang.format("post.list_retrieved", &[&posts.len().to_string()])),
+                posts
+            )
+        },
+        Err(e) => {
+            error!("Handler: Failed to get all posts (v2): {}", e);
+            sql_error_response_generic(e, "Failed to get all posts")
+        }
+    }
+}
+
+pub async fn get_post(
+    State(ReadPool(read_pool)): State<ReadPool>,
+    State(settings): State<Arc<Settings>>,
+    Extension(viewer): Extension<Option<Uuid>>,
+    PathId(id): PathId,
+    Deadline(deadline): Deadline,
+    lang: Lang
+) -> UnifiedResponse<PostV2Response> {
+    info!("Handler: Getting post (v2): {}", id);
+
+    let repo = SqlPostRepository::new((*read_pool).clone()).with_reading_wpm(settings.reading_wpm)
+        .with_api_prefix(settings.api_prefix.as_deref())
+        .with_deadline(deadline);
+
+    match repo.find_by_id_with_author(id).await {
+        // Someone else's private post is reported as missing
+        Ok(Some(post)) if post.is_visible_to(viewer) => {
+            success_response(lang.text("post.retrieved").to_string(), PostV2Response::from(post))
+        },
+        Ok(_) => {
+            not_found_response_generic(lang.text("post.not_found").to_string())
+        },
+        Err(e) => {
+            error!("Handler: Failed to get post (v2): {}", e);
+            sql_error_response_generic(e, "Failed to get post")
+        }
+    }
+}
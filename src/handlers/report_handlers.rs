@@ -0,0 +1,62 @@
+use axum::{
+    extract::{State, Extension},
+    http::StatusCode,
+};
+use std::sync::Arc;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+use crate::db::repositories::sql_post_repo::SqlPostRepository;
+use crate::db::repositories::sql_report_repo::SqlReportRepository;
+use crate::db::sql_db::is_unique_violation;
+use crate::helpers::extractors::{JsonBody, PathId};
+use crate::helpers::messages::Lang;
+use crate::helpers::response::{UnifiedResponse, success_response, error_response_generic, error_response_with_status, not_found_response_generic, sql_error_response_generic};
+use crate::model::model::{PostReport, ReportPostRequest};
+use tracing::{info, error};
+
+// Longest reason a report may carry, in characters
+const MAX_REPORT_REASON_CHARS: usize = 500;
+
+pub async fn report_post(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(post_id): PathId,
+    lang: Lang,
+    JsonBody(payload): JsonBody<ReportPostRequest>
+) -> UnifiedResponse<PostReport> {
+    info!("Handler: User {} reporting post: {}", user_id, post_id);
+
+    let reason = payload.reason.trim().to_string();
+    if reason.is_empty() {
+        return error_response_generic("Bad Request".to_string(), lang.text("report.reason_empty").to_string());
+    }
+    if reason.chars().count() > MAX_REPORT_REASON_CHARS {
+        return error_response_generic(
+            "Bad Request".to_string(),
+            lang.format("report.reason_too_long", &[&MAX_REPORT_REASON_CHARS.to_string()]),
+        );
+    }
+
+    // Only posts the caller can see may be reported
+    match SqlPostRepository::new((*pool).clone()).find_by_id(post_id).await {
+        Ok(Some(post)) if post.is_visible_to(Some(user_id)) => {},
+        Ok(_) => return not_found_response_generic(lang.text("post.not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to get post to report: {}", e);
+            return sql_error_response_generic(e, "Failed to get post");
+        }
+    }
+
+    let repo = SqlReportRepository::new((*pool).clone());
+
+    match repo.create_report(post_id, user_id, reason).await {
+        Ok(report) => success_response(lang.text("report.created").to_string(), report),
+        Err(e) if is_unique_violation(&e) => {
+            error_response_with_status(StatusCode::CONFLICT, "Conflict".to_string(), lang.text("report.duplicate").to_string())
+        },
+        Err(e) => {
+            error!("Handler: Failed to report post: {}", e);
+            sql_error_response_generic(e, "Failed to report post")
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use axum::extract::Path;
+use schemars::schema_for;
+use serde_json::Value;
+use crate::helpers::messages::Lang;
+use crate::helpers::response::{UnifiedResponse, success_response, not_found_response_generic};
+use crate::model::model::{BatchDeleteRequest, CreateCommentRequest, CreatePostRequest, CreateUserRequest, LoginRequest, MarkReadRequest, ReportPostRequest, UpdatePostRequest, UpdateUserRequest};
+use tracing::info;
+
+// JSON Schema for a request body, looked up by the name used in the URL
+fn request_schema(name: &str) -> Option<Value> {
+    let schema = match name {
+        "create_user" => schema_for!(CreateUserRequest),
+        "update_user" => schema_for!(UpdateUserRequest),
+        "login" => schema_for!(LoginRequest),
+        "create_post" => schema_for!(CreatePostRequest),
+        "update_post" => schema_for!(UpdatePostRequest),
+        "create_comment" => schema_for!(CreateCommentRequest),
+        "batch_delete_posts" => schema_for!(BatchDeleteRequest),
+        "report_post" => schema_for!(ReportPostRequest),
+        "mark_posts_read" => schema_for!(MarkReadRequest),
+        _ => return None,
+    };
+    serde_json::to_value(schema).ok()
+}
+
+pub async fn get_schema(
+    Path(name): Path<String>,
+    lang: Lang
+) -> UnifiedResponse<Value> {
+    info!("Handler: Getting schema for: {}", name);
+
+    match request_schema(&name) {
+        Some(schema) => success_response(lang.format("schema.retrieved", &[&name]), schema),
+        None => not_found_response_generic(lang.format("schema.not_found", &[&name])),
+    }
+}
@@ -0,0 +1,49 @@
+use axum::extract::{OriginalUri, State, Query};
+use std::sync::Arc;
+use sqlx::SqlitePool;
+use crate::config::Settings;
+use crate::db::repositories::sql_post_repo::SqlPostRepository;
+use crate::helpers::messages::Lang;
+use crate::helpers::pagination::{total_if, PageLinks};
+use crate::state::ReadPool;
+use crate::helpers::response::{UnifiedResponse, success_response, sql_error_response_generic};
+use crate::model::model::{PageQuery, QuietQuery, TagCount};
+use tracing::{info, error};
+
+pub async fn get_tags(
+    State(pool): State<Arc<SqlitePool>>,
+    State(ReadPool(read_pool)): State<ReadPool>,
+    State(settings): State<Arc<Settings>>,
+    OriginalUri(uri): OriginalUri,
+    Query(page): Query<PageQuery>,
+    Query(quiet): Query<QuietQuery>,
+    lang: Lang
+) -> (Option<PageLinks>, UnifiedResponse<Vec<TagCount>>) {
+    info!("Handler: Getting tag counts");
+
+    let repo = SqlPostRepository::new((*pool).clone()).with_read_pool((*read_pool).clone());
+
+    let total = match total_if(settings.pagination_links, repo.count_tags()).await {
+        Ok(total) => total,
+        Err(e) => {
+            error!("Handler: Failed to count tags: {}", e);
+            return (None, sql_error_response_generic(e, "Failed to get tag counts"));
+        }
+    };
+
+    match repo.tag_counts(page.limit(), page.offset()).await {
+        Ok(tags) => {
+            (
+                total.and_then(|total| PageLinks::for_page(&uri, &page, total)),
+                success_response(
+                    quiet.message(|| lang.format("tag.list_retrieved", &[&tags.len().to_string()])),
+                    tags
+                ),
+            )
+        },
+        Err(e) => {
+            error!("Handler: Failed to get tag counts: {}", e);
+            (None, sql_error_response_generic(e, "Failed to get tag counts"))
+        }
+    }
+}
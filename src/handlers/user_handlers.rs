@@ -0,0 +1,88 @@
+use axum::extract::{State, Path, Extension};
+use std::sync::Arc;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+use serde_json::Value;
+use crate::config::Settings;
+use crate::db::repositories::sql_user_repo::SqlUserRepository;
+use crate::helpers::extractors::PathId;
+use crate::helpers::messages::Lang;
+use crate::helpers::response::{UnifiedResponse, success_response, error_response_generic, not_found_response_generic, sql_error_response_generic};
+use crate::model::model::PublicUser;
+use crate::state::ReadPool;
+use tracing::{info, error};
+
+pub async fn get_user_by_username(
+    State(ReadPool(read_pool)): State<ReadPool>,
+    Path(username): Path<String>,
+    lang: Lang
+) -> UnifiedResponse<PublicUser> {
+    info!("Handler: Getting user by username: {}", username);
+
+    let repo = SqlUserRepository::new((*read_pool).clone());
+
+    match repo.find_by_username(&username.to_lowercase()).await {
+        Ok(Some(user)) => success_response(
+            lang.text("user.retrieved").to_string(),
+            PublicUser { id: user.id, username: user.username, name: user.name, created_at: user.created_at }
+        ),
+        Ok(None) => not_found_response_generic(lang.text("user.not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to get user by username: {}", e);
+            sql_error_response_generic(e, "Failed to get user")
+        }
+    }
+}
+
+pub async fn follow_user(
+    State(pool): State<Arc<SqlitePool>>,
+    State(settings): State<Arc<Settings>>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(id): PathId,
+    lang: Lang
+) -> UnifiedResponse<Value> {
+    info!("Handler: User {} following user: {}", user_id, id);
+
+    if id == user_id {
+        return error_response_generic("Bad Request".to_string(), lang.text("user.cannot_follow_self").to_string());
+    }
+
+    let repo = SqlUserRepository::new((*pool).clone()).with_clock(settings.clock.clone());
+
+    match repo.find_by_id(id).await {
+        Ok(Some(_)) => {},
+        Ok(None) => return not_found_response_generic(lang.text("user.not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to get user to follow: {}", e);
+            return sql_error_response_generic(e, "Failed to get user");
+        }
+    }
+
+    match repo.follow(user_id, id).await {
+        Ok(()) => success_response(lang.text("user.followed").to_string(), Value::Null),
+        Err(e) => {
+            error!("Handler: Failed to follow user: {}", e);
+            sql_error_response_generic(e, "Failed to follow user")
+        }
+    }
+}
+
+pub async fn unfollow_user(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(user_id): Extension<Uuid>,
+    PathId(id): PathId,
+    lang: Lang
+) -> UnifiedResponse<Value> {
+    info!("Handler: User {} unfollowing user: {}", user_id, id);
+
+    let repo = SqlUserRepository::new((*pool).clone());
+
+    match repo.unfollow(user_id, id).await {
+        Ok(true) => success_response(lang.text("user.unfollowed").to_string(), Value::Null),
+        Ok(false) => not_found_response_generic(lang.text("user.follow_not_found").to_string()),
+        Err(e) => {
+            error!("Handler: Failed to unfollow user: {}", e);
+            sql_error_response_generic(e, "Failed to unfollow user")
+        }
+    }
+}
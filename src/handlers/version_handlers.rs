@@ -0,0 +1,20 @@
+use chrono::DateTime;
+use crate::helpers::messages::Lang;
+use crate::helpers::response::{UnifiedResponse, success_response};
+use crate::model::model::VersionInfo;
+use tracing::info;
+
+// Filled in by build.rs; the commit is missing when building outside a git checkout
+const COMMIT: Option<&str> = option_env!("GIT_HASH");
+const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
+
+pub async fn get_version(lang: Lang) -> UnifiedResponse<VersionInfo> {
+    info!("Handler: Getting version");
+
+    let version = VersionInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        commit: COMMIT.unwrap_or("unknown").to_string(),
+        built_at: BUILD_TIMESTAMP.parse().ok().and_then(|seconds| DateTime::from_timestamp(seconds, 0)).unwrap_or_default(),
+    };
+    success_response(lang.format("version.retrieved", &[&version.version]), version)
+}
@@ -1,63 +1,235 @@
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use bcrypt::{hash, verify, DEFAULT_COST};
-use chrono::{Duration, Utc};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use chrono::{DateTime, Duration, Utc};
 use uuid::Uuid;
-use crate::model::model::Claims;
+use crate::config::{PasswordAlgorithm, Settings};
+use crate::model::model::{Claims, Role, VerificationClaims};
 use anyhow::Result;
+use std::sync::OnceLock;
 use tracing::info;
 
-const JWT_SECRET: &str = "your-secret-key-change-in-production";
+const VERIFY_EMAIL_PURPOSE: &str = "verify_email";
+const ARGON2_PREFIX: &str = "$argon2";
+
+// Verified against when the login email is unknown, one per algorithm
+static DUMMY_BCRYPT_HASH: OnceLock<String> = OnceLock::new();
+static DUMMY_ARGON2_HASH: OnceLock<String> = OnceLock::new();
 
 pub struct AuthHelper;
 
 impl AuthHelper {
-    pub fn hash_password(password: &str) -> Result<String> {
-        let hashed = hash(password, DEFAULT_COST)?;
+    pub fn hash_password(password: &str, algorithm: PasswordAlgorithm) -> Result<String> {
+        let hashed = match algorithm {
+            PasswordAlgorithm::Bcrypt => hash(password, DEFAULT_COST)?,
+            PasswordAlgorithm::Argon2 => {
+                let salt = SaltString::generate(&mut OsRng);
+                Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string()
+            }
+        };
         Ok(hashed)
     }
 
+    // The algorithm is read off the stored hash, so bcrypt and Argon2 users can coexist
     pub fn verify_password(password: &str, hash: &str) -> Result<bool> {
-        let is_valid = verify(password, hash)?;
-        Ok(is_valid)
+        if !hash.starts_with(ARGON2_PREFIX) {
+            return Ok(verify(password, hash)?);
+        }
+
+        match Argon2::default().verify_password(password.as_bytes(), &PasswordHash::new(hash)?) {
+            Ok(()) => Ok(true),
+            Err(argon2::password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
     }
 
-    pub fn generate_token(user_id: Uuid) -> Result<String> {
-        let expiration = Utc::now()
-            .checked_add_signed(Duration::hours(24))
-            .expect("valid timestamp")
-            .timestamp() as usize;
+    /// Whether a hash that just verified should be replaced with one from `algorithm`
+    pub fn needs_rehash(hash: &str, algorithm: PasswordAlgorithm) -> bool {
+        algorithm == PasswordAlgorithm::Argon2 && !hash.starts_with(ARGON2_PREFIX)
+    }
+
+    // Hashing is CPU-heavy, so request handlers run it on the blocking pool instead of a runtime worker
+    pub async fn hash_password_blocking(password: String, algorithm: PasswordAlgorithm) -> Result<String> {
+        tokio::task::spawn_blocking(move || Self::hash_password(&password, algorithm)).await?
+    }
+
+    pub async fn verify_password_blocking(password: String, hash: String) -> Result<bool> {
+        tokio::task::spawn_blocking(move || Self::verify_password(&password, &hash)).await?
+    }
+
+    /// Does the same work as verifying a real password and throws the result away,
+    /// so a login for an unknown email takes as long as one with a wrong password
+    pub async fn dummy_verify_blocking(password: String, algorithm: PasswordAlgorithm) {
+        let verified = tokio::task::spawn_blocking(move || {
+            let hash = Self::dummy_hash(algorithm)?;
+            Self::verify_password(&password, hash)
+        })
+        .await;
+        if let Ok(Err(e)) = verified {
+            info!("Dummy password verification failed: {}", e);
+        }
+    }
+
+    // Hashed once with the same cost as real passwords
+    fn dummy_hash(algorithm: PasswordAlgorithm) -> Result<&'static str> {
+        let cell = match algorithm {
+            PasswordAlgorithm::Bcrypt => &DUMMY_BCRYPT_HASH,
+            PasswordAlgorithm::Argon2 => &DUMMY_ARGON2_HASH,
+        };
+        if let Some(hash) = cell.get() {
+            return Ok(hash);
+        }
+        let hash = Self::hash_password("dummy-password", algorithm)?;
+        Ok(cell.get_or_init(|| hash))
+    }
+
+    pub fn generate_token(user_id: Uuid, role: Role, settings: &Settings) -> Result<String> {
+        Self::generate_token_with_ttl(user_id, role, settings.access_token_ttl, settings)
+    }
+
+    pub fn generate_token_with_ttl(user_id: Uuid, role: Role, ttl: Duration, settings: &Settings) -> Result<String> {
+        let expires_at = settings.clock.now().checked_add_signed(ttl).expect("valid timestamp");
+        Self::generate_token_expiring(user_id, role, expires_at, None, settings)
+    }
+
+    /// Access token carrying `expires_at` (to the second) as its `exp` claim, and `session_id` as its `jti`
+    pub fn generate_token_expiring(
+        user_id: Uuid,
+        role: Role,
+        expires_at: DateTime<Utc>,
+        session_id: Option<Uuid>,
+        settings: &Settings,
+    ) -> Result<String> {
+        let expiration = expires_at.timestamp() as usize;
 
         let claims = Claims {
             sub: user_id.to_string(),
             exp: expiration,
-            iat: Utc::now().timestamp() as usize,
+            iat: settings.clock.now().timestamp() as usize,
+            role,
+            iss: settings.jwt_issuer.clone(),
+            aud: settings.jwt_audience.clone(),
+            jti: session_id.map(|id| id.to_string()),
         };
 
         let token = encode(
             &Header::default(),
             &claims,
-            &EncodingKey::from_secret(JWT_SECRET.as_ref()),
+            &EncodingKey::from_secret(settings.jwt_secret.as_bytes()),
         )?;
 
         info!("Generated JWT token for user: {}", user_id);
         Ok(token)
     }
 
-    pub fn validate_token(token: &str) -> Result<Claims> {
+    // Only tokens issued by and for this service (per the configured issuer and audience) pass
+    pub fn validate_token(token: &str, settings: &Settings) -> Result<Claims> {
+        let mut validation = Validation::default();
+        validation.set_issuer(&[&settings.jwt_issuer]);
+        validation.set_audience(&[&settings.jwt_audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+        validation.validate_exp = false;
+
         let token_data = decode::<Claims>(
             token,
-            &DecodingKey::from_secret(JWT_SECRET.as_ref()),
-            &Validation::default(),
+            &DecodingKey::from_secret(settings.jwt_secret.as_bytes()),
+            &validation,
         )?;
 
+        Self::check_expiry(token_data.claims.exp, validation.leeway, settings)?;
         Ok(token_data.claims)
     }
 
-    pub fn extract_user_id_from_token(token: &str) -> Result<Uuid> {
-        let claims = Self::validate_token(token)?;
+    // `exp` is checked here rather than by jsonwebtoken so it follows the configured clock
+    fn check_expiry(exp: usize, leeway: u64, settings: &Settings) -> Result<()> {
+        if (exp as i64).saturating_add(leeway as i64) < settings.clock.now().timestamp() {
+            return Err(jsonwebtoken::errors::Error::from(jsonwebtoken::errors::ErrorKind::ExpiredSignature).into());
+        }
+        Ok(())
+    }
+
+    /// Whether a validation error means the token was well-formed but past its expiry
+    pub fn is_expired_token_error(error: &anyhow::Error) -> bool {
+        error
+            .downcast_ref::<jsonwebtoken::errors::Error>()
+            .is_some_and(|e| matches!(e.kind(), jsonwebtoken::errors::ErrorKind::ExpiredSignature))
+    }
+
+    pub fn extract_user_id_from_token(token: &str, settings: &Settings) -> Result<Uuid> {
+        let claims = Self::validate_token(token, settings)?;
         let user_id = Uuid::parse_str(&claims.sub)?;
         Ok(user_id)
     }
+
+    pub fn generate_email_verification_token(user_id: Uuid, email: &str, settings: &Settings) -> Result<String> {
+        let expiration = settings.clock.now()
+            .checked_add_signed(Duration::hours(48))
+            .expect("valid timestamp")
+            .timestamp() as usize;
+
+        let claims = VerificationClaims {
+            sub: user_id.to_string(),
+            email: email.to_string(),
+            exp: expiration,
+            purpose: VERIFY_EMAIL_PURPOSE.to_string(),
+        };
+
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(settings.jwt_secret.as_bytes()),
+        )?;
+
+        Ok(token)
+    }
+
+    // User id and email a verification token was issued for
+    pub fn validate_email_verification_token(token: &str, settings: &Settings) -> Result<(Uuid, String)> {
+        let mut validation = Validation::default();
+        validation.validate_exp = false;
+
+        let token_data = decode::<VerificationClaims>(
+            token,
+            &DecodingKey::from_secret(settings.jwt_secret.as_bytes()),
+            &validation,
+        )?;
+
+        let claims = token_data.claims;
+        Self::check_expiry(claims.exp, validation.leeway, settings)?;
+        if claims.purpose != VERIFY_EMAIL_PURPOSE {
+            anyhow::bail!("Token is not an email verification token");
+        }
+
+        Ok((Uuid::parse_str(&claims.sub)?, claims.email))
+    }
+
+    // User id, role and session id (when the token belongs to a tracked session)
+    pub fn extract_identity_from_token(token: &str, settings: &Settings) -> Result<(Uuid, Role, Option<Uuid>)> {
+        let claims = Self::validate_token(token, settings)?;
+        let user_id = Uuid::parse_str(&claims.sub)?;
+        let session_id = claims.jti.as_deref().map(Uuid::parse_str).transpose()?;
+        Ok((user_id, claims.role, session_id))
+    }
+
+    /// Whether `key` is one of TRUSTED_API_KEYS. Every key is compared in full, so timing
+    /// doesn't reveal how much of a guess was right
+    pub fn is_trusted_api_key(key: &str, settings: &Settings) -> bool {
+        settings
+            .trusted_api_keys
+            .iter()
+            .fold(false, |trusted, candidate| trusted | constant_time_eq(key.as_bytes(), candidate.as_bytes()))
+    }
+}
+
+// Only the lengths can leak, not where the first differing byte is
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
 }
 
 #[cfg(test)]
@@ -67,16 +239,59 @@ mod tests {
     #[test]
     fn test_password_hashing() {
         let password = "test_password";
-        let hash = AuthHelper::hash_password(password).unwrap();
-        assert!(AuthHelper::verify_password(password, &hash).unwrap());
-        assert!(!AuthHelper::verify_password("wrong_password", &hash).unwrap());
+        for algorithm in [PasswordAlgorithm::Bcrypt, PasswordAlgorithm::Argon2] {
+            let hash = AuthHelper::hash_password(password, algorithm).unwrap();
+            assert!(AuthHelper::verify_password(password, &hash).unwrap());
+            assert!(!AuthHelper::verify_password("wrong_password", &hash).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_needs_rehash() {
+        let bcrypt = AuthHelper::hash_password("pw", PasswordAlgorithm::Bcrypt).unwrap();
+        let argon2 = AuthHelper::hash_password("pw", PasswordAlgorithm::Argon2).unwrap();
+        assert!(argon2.starts_with("$argon2id$"));
+        assert!(AuthHelper::needs_rehash(&bcrypt, PasswordAlgorithm::Argon2));
+        assert!(!AuthHelper::needs_rehash(&argon2, PasswordAlgorithm::Argon2));
+        assert!(!AuthHelper::needs_rehash(&bcrypt, PasswordAlgorithm::Bcrypt));
     }
 
     #[test]
     fn test_jwt_token() {
+        let settings = Settings::default();
         let user_id = Uuid::new_v4();
-        let token = AuthHelper::generate_token(user_id).unwrap();
-        let claims = AuthHelper::validate_token(&token).unwrap();
+        let token = AuthHelper::generate_token(user_id, Role::Admin, &settings).unwrap();
+        let claims = AuthHelper::validate_token(&token, &settings).unwrap();
         assert_eq!(claims.sub, user_id.to_string());
+        assert_eq!(claims.role, Role::Admin);
+        assert_eq!(claims.iss, settings.jwt_issuer);
+        assert_eq!(claims.aud, settings.jwt_audience);
+    }
+
+    #[test]
+    fn test_jwt_issuer_and_audience_must_match() {
+        let settings = Settings {
+            jwt_issuer: "auth-service".to_string(),
+            jwt_audience: "blog-api".to_string(),
+            ..Settings::default()
+        };
+        let token = AuthHelper::generate_token(Uuid::new_v4(), Role::User, &settings).unwrap();
+        assert!(AuthHelper::validate_token(&token, &settings).is_ok());
+
+        let other_audience = Settings { jwt_audience: "billing-api".to_string(), ..settings.clone() };
+        assert!(AuthHelper::validate_token(&token, &other_audience).is_err());
+
+        let other_issuer = Settings { jwt_issuer: "someone-else".to_string(), ..settings.clone() };
+        assert!(AuthHelper::validate_token(&token, &other_issuer).is_err());
+    }
+
+    #[test]
+    fn test_trusted_api_keys() {
+        let settings = Settings { trusted_api_keys: vec!["first-key".to_string(), "second-key".to_string()], ..Settings::default() };
+        assert!(AuthHelper::is_trusted_api_key("first-key", &settings));
+        assert!(AuthHelper::is_trusted_api_key("second-key", &settings));
+        assert!(!AuthHelper::is_trusted_api_key("second-kez", &settings));
+        assert!(!AuthHelper::is_trusted_api_key("first", &settings));
+        assert!(!AuthHelper::is_trusted_api_key("", &Settings::default()));
     }
 } 
\ No newline at end of file
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::model::model::PostView;
+
+/// Short-lived copy of the anonymous `/posts` feed, keyed by whether authors are expanded
+pub struct PostsCache {
+    ttl: Option<Duration>,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    // Bumped by every invalidation so a read that raced with a write can't store what it saw
+    generation: u64,
+    entries: HashMap<bool, (Instant, Vec<PostView>)>,
+}
+
+impl PostsCache {
+    /// `None` disables caching entirely
+    pub fn new(ttl: Option<Duration>) -> Self {
+        Self { ttl, state: Mutex::new(CacheState::default()) }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.ttl.is_some()
+    }
+
+    /// Current generation; pass it back to `insert` after loading the feed
+    pub fn generation(&self) -> u64 {
+        self.lock().generation
+    }
+
+    pub fn get(&self, expanded: bool) -> Option<Vec<PostView>> {
+        let ttl = self.ttl?;
+        let state = self.lock();
+        state
+            .entries
+            .get(&expanded)
+            .filter(|(stored_at, _)| stored_at.elapsed() < ttl)
+            .map(|(_, posts)| posts.clone())
+    }
+
+    pub fn insert(&self, generation: u64, expanded: bool, posts: Vec<PostView>) {
+        if !self.enabled() {
+            return;
+        }
+        let mut state = self.lock();
+        if state.generation == generation {
+            state.entries.insert(expanded, (Instant::now(), posts));
+        }
+    }
+
+    /// Drops every cached feed; called after anything that changes posts or their authors
+    pub fn invalidate(&self) {
+        let mut state = self.lock();
+        state.generation += 1;
+        state.entries.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
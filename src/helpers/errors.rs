@@ -0,0 +1,124 @@
+use axum::http::StatusCode;
+use serde::Serialize;
+
+/// Stable codes for every `error` value an error body can carry
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    BadRequest,
+    ValidationError,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    Conflict,
+    PayloadTooLarge,
+    RequestHeaderFieldsTooLarge,
+    UnsupportedMediaType,
+    TooManyRequests,
+    ServiceUnavailable,
+    GatewayTimeout,
+    DatabaseError,
+    InternalError,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 15] = [
+        ErrorCode::BadRequest,
+        ErrorCode::ValidationError,
+        ErrorCode::Unauthorized,
+        ErrorCode::Forbidden,
+        ErrorCode::NotFound,
+        ErrorCode::MethodNotAllowed,
+        ErrorCode::Conflict,
+        ErrorCode::PayloadTooLarge,
+        ErrorCode::RequestHeaderFieldsTooLarge,
+        ErrorCode::UnsupportedMediaType,
+        ErrorCode::TooManyRequests,
+        ErrorCode::ServiceUnavailable,
+        ErrorCode::GatewayTimeout,
+        ErrorCode::DatabaseError,
+        ErrorCode::InternalError,
+    ];
+
+    /// The `error` field of the response body
+    pub fn error(&self) -> &'static str {
+        match self {
+            ErrorCode::BadRequest => "Bad Request",
+            ErrorCode::ValidationError => "Validation Error",
+            ErrorCode::Unauthorized => "Unauthorized",
+            ErrorCode::Forbidden => "Forbidden",
+            ErrorCode::NotFound => "Not Found",
+            ErrorCode::MethodNotAllowed => "Method Not Allowed",
+            ErrorCode::Conflict => "Conflict",
+            ErrorCode::PayloadTooLarge => "Payload Too Large",
+            ErrorCode::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
+            ErrorCode::UnsupportedMediaType => "Unsupported Media Type",
+            ErrorCode::TooManyRequests => "Too Many Requests",
+            ErrorCode::ServiceUnavailable => "Service Unavailable",
+            ErrorCode::GatewayTimeout => "Gateway Timeout",
+            ErrorCode::DatabaseError => "Database Error",
+            ErrorCode::InternalError => "Internal Error",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::BadRequest | ErrorCode::ValidationError => StatusCode::BAD_REQUEST,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ErrorCode::Conflict => StatusCode::CONFLICT,
+            ErrorCode::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::RequestHeaderFieldsTooLarge => StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            ErrorCode::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ErrorCode::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::GatewayTimeout => StatusCode::GATEWAY_TIMEOUT,
+            // Legacy quirk: handlers still report storage and internal failures with the default
+            // error status, 400, rather than 500; clients should go by the `error` field
+            ErrorCode::DatabaseError | ErrorCode::InternalError => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorCode::BadRequest => "The request is malformed: bad id, body shape, oversized query string, field values or an empty update",
+            ErrorCode::ValidationError => "A field failed validation, e.g. an invalid email or missing credentials",
+            ErrorCode::Unauthorized => "Missing, invalid or expired credentials, or an idle session",
+            ErrorCode::Forbidden => "Authenticated, but not allowed: wrong role, closed registration or a reached limit",
+            ErrorCode::NotFound => "The resource is missing, e.g. a post cover, raw content, random post, full thread or trashed post; older JSON lookups such as GET /posts/{id} and GET /users/{username} still report absence as 200 with null data",
+            ErrorCode::MethodNotAllowed => "The path exists but not for this method; the Allow header lists the supported ones",
+            ErrorCode::Conflict => "The request clashes with existing state, e.g. a taken email or slug, duplicate title or the last admin",
+            ErrorCode::PayloadTooLarge => "The request body exceeds the configured size limit",
+            ErrorCode::RequestHeaderFieldsTooLarge => "The request has more headers, or more header bytes, than the configured limits",
+            ErrorCode::UnsupportedMediaType => "The uploaded content type is not accepted",
+            ErrorCode::TooManyRequests => "The client sent too many requests to a rate-limited endpoint; retry later",
+            ErrorCode::ServiceUnavailable => "The server is at its concurrent request limit and shed this request; retry shortly",
+            ErrorCode::GatewayTimeout => "The request ran past its time budget; any query it had in flight was cancelled",
+            ErrorCode::DatabaseError => "A storage operation failed; the message names the operation. Sent as 400 for legacy reasons",
+            ErrorCode::InternalError => "An unexpected server-side failure, such as hashing or token generation. Sent as 400 for legacy reasons",
+        }
+    }
+}
+
+// One row of GET /errors
+#[derive(Serialize, Clone, Debug)]
+pub struct ErrorCatalogEntry {
+    pub code: ErrorCode,
+    pub error: &'static str,
+    pub status: u16,
+    pub description: &'static str,
+}
+
+pub fn error_catalog() -> Vec<ErrorCatalogEntry> {
+    ErrorCode::ALL
+        .iter()
+        .map(|code| ErrorCatalogEntry {
+            code: *code,
+            error: code.error(),
+            status: code.status().as_u16(),
+            description: code.description(),
+        })
+        .collect()
+}
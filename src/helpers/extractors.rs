@@ -0,0 +1,235 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{rejection::PathRejection, FromRef, FromRequest, FromRequestParts, Path, Request},
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use sqlx::SqlitePool;
+use std::convert::Infallible;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
+use crate::config::Settings;
+use crate::db::repositories::sql_user_repo::SqlUserRepository;
+use crate::helpers::auth::AuthHelper;
+use crate::helpers::messages::Lang;
+use crate::helpers::response::unauthorized_response;
+use crate::model::model::{ErrorResponse, Role, User};
+use tracing::error;
+
+/// The authenticated user, loaded once from the id `auth_middleware` put in the request extensions
+pub struct AuthUser(pub User);
+
+impl<S> FromRequestParts<S> for AuthUser
+where
+    Arc<SqlitePool>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, Json<ErrorResponse>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let lang = Lang::from_headers(&parts.headers);
+        let unauthorized = |key: &'static str| unauthorized_response(lang.text(key).to_string());
+
+        let user_id = match parts.extensions.get::<Uuid>() {
+            Some(user_id) => *user_id,
+            None => return Err(unauthorized("auth.missing_header")),
+        };
+
+        let pool = Arc::<SqlitePool>::from_ref(state);
+        let repo = SqlUserRepository::new((*pool).clone());
+
+        match repo.find_by_id(user_id).await {
+            Ok(Some(user)) => Ok(AuthUser(user)),
+            // Token is valid but the account behind it is gone
+            Ok(None) => Err(unauthorized("auth.user_gone")),
+            Err(e) => {
+                error!("Failed to load authenticated user {}: {}", user_id, e);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Database Error".to_string(),
+                        message: "Failed to load authenticated user".to_string(),
+                    }),
+                ))
+            }
+        }
+    }
+}
+
+/// A role that `RequireRole` can demand
+pub trait RequiredRole {
+    const ROLE: Role;
+    /// Message key for the 403 when the caller lacks the role
+    const DENIED_KEY: &'static str;
+}
+
+pub struct AdminRole;
+
+impl RequiredRole for AdminRole {
+    const ROLE: Role = Role::Admin;
+    const DENIED_KEY: &'static str = "auth.admin_required";
+}
+
+/// An authenticated caller whose token carries role `R`; anyone else gets 403.
+/// Reads the role `auth_middleware` took from the JWT, so a role change applies from the next login.
+pub struct RequireRole<R: RequiredRole> {
+    pub user_id: Uuid,
+    _role: PhantomData<R>,
+}
+
+impl<S, R> FromRequestParts<S> for RequireRole<R>
+where
+    S: Send + Sync,
+    R: RequiredRole,
+{
+    type Rejection = (StatusCode, Json<ErrorResponse>);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let lang = Lang::from_headers(&parts.headers);
+
+        let (user_id, role) = match (parts.extensions.get::<Uuid>(), parts.extensions.get::<Role>()) {
+            (Some(user_id), Some(role)) => (*user_id, *role),
+            _ => return Err(unauthorized_response(lang.text("auth.missing_header").to_string())),
+        };
+
+        if role != R::ROLE {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: "Forbidden".to_string(),
+                    message: lang.text(R::DENIED_KEY).to_string(),
+                }),
+            ));
+        }
+
+        Ok(RequireRole { user_id, _role: PhantomData })
+    }
+}
+
+/// A `{id}` path segment parsed as a UUID; unparsable ids get the standard 400 envelope
+pub struct PathId(pub Uuid);
+
+impl<S> FromRequestParts<S> for PathId
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, Json<ErrorResponse>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Path::<Uuid>::from_request_parts(parts, state).await {
+            Ok(Path(id)) => Ok(PathId(id)),
+            Err(PathRejection::FailedToDeserializePathParams(_)) => Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "Bad Request".to_string(),
+                    message: Lang::from_headers(&parts.headers).text("request.invalid_id").to_string(),
+                }),
+            )),
+            Err(rejection) => {
+                error!("Failed to extract path id: {}", rejection);
+                Err((
+                    rejection.status(),
+                    Json(ErrorResponse {
+                        error: "Internal Error".to_string(),
+                        message: rejection.body_text(),
+                    }),
+                ))
+            }
+        }
+    }
+}
+
+/// A JSON request body like `Json<T>`, except that a missing or blank body gets the standard 400 envelope.
+/// Any other rejection (wrong content type, unparsable JSON, wrong shape) is axum's usual response
+pub struct JsonBody<T>(pub T);
+
+impl<S, T> FromRequest<S> for JsonBody<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, body) = request.into_parts();
+        let bytes = Bytes::from_request(Request::from_parts(parts.clone(), body), state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        if bytes.trim_ascii().is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "Bad Request".to_string(),
+                    message: Lang::from_headers(&parts.headers).text("request.body_required").to_string(),
+                }),
+            ).into_response());
+        }
+
+        match Json::<T>::from_request(Request::from_parts(parts, Body::from(bytes)), state).await {
+            Ok(Json(value)) => Ok(JsonBody(value)),
+            Err(rejection) => Err(rejection.into_response()),
+        }
+    }
+}
+
+/// Whether the client sent `Prefer: return=minimal` and only wants the id of what it wrote
+pub struct PreferMinimal(pub bool);
+
+impl<S> FromRequestParts<S> for PreferMinimal
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let minimal = parts
+            .headers
+            .get_all("prefer")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|preference| preference.trim().eq_ignore_ascii_case("return=minimal"));
+        Ok(PreferMinimal(minimal))
+    }
+}
+
+/// Whether the request carries one of TRUSTED_API_KEYS in `X-Api-Key`; such service callers skip rate limits
+pub struct TrustedCaller(pub bool);
+
+impl<S> FromRequestParts<S> for TrustedCaller
+where
+    Arc<Settings>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let settings = Arc::<Settings>::from_ref(state);
+        let trusted = parts
+            .headers
+            .get("x-api-key")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|key| AuthHelper::is_trusted_api_key(key, &settings));
+        Ok(TrustedCaller(trusted))
+    }
+}
+
+/// When the current request's time budget runs out, as set by `request_timeout_middleware`; `None` means no budget
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Deadline(pub Option<Instant>);
+
+impl<S> FromRequestParts<S> for Deadline
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Deadline>().copied().unwrap_or_default())
+    }
+}
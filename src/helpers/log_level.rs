@@ -0,0 +1,81 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::{
+    filter::{EnvFilter, LevelFilter},
+    reload, Registry,
+};
+
+/// Handle on the process-wide log filter, so PUT /admin/log-level can change it without a restart
+#[derive(Clone)]
+pub struct LogFilter {
+    // None when no subscriber was built with `reloadable`, e.g. in tests; the level is then only recorded
+    handle: Option<reload::Handle<EnvFilter, Registry>>,
+    level: Arc<Mutex<String>>,
+}
+
+impl LogFilter {
+    /// A filter layer that starts from RUST_LOG (info when unset), plus the handle that swaps it later
+    pub fn reloadable() -> (Self, reload::Layer<EnvFilter, Registry>) {
+        let filter = EnvFilter::builder()
+            .with_default_directive(LevelFilter::INFO.into())
+            .from_env_lossy();
+        let level = filter.to_string();
+        let (layer, handle) = reload::Layer::new(filter);
+        (Self { handle: Some(handle), level: Arc::new(Mutex::new(level)) }, layer)
+    }
+
+    /// A filter not attached to any subscriber
+    pub fn detached() -> Self {
+        Self { handle: None, level: Arc::new(Mutex::new(LevelFilter::INFO.to_string().to_lowercase())) }
+    }
+
+    /// The directives currently in effect
+    pub fn level(&self) -> String {
+        self.level.lock().unwrap().clone()
+    }
+
+    /// Replaces the whole filter, RUST_LOG directives included, with `level` for every target
+    pub fn set(&self, level: LevelFilter) -> anyhow::Result<()> {
+        let level = level.to_string().to_lowercase();
+        if let Some(handle) = &self.handle {
+            handle.reload(EnvFilter::new(&level))?;
+        }
+        *self.level.lock().unwrap() = level;
+        Ok(())
+    }
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self::detached()
+    }
+}
+
+impl fmt::Debug for LogFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogFilter")
+            .field("level", &self.level())
+            .field("reloadable", &self.handle.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn test_reload_changes_enabled_levels() {
+        let (filter, layer) = LogFilter::reloadable();
+        let _guard = tracing::subscriber::set_default(Registry::default().with(layer));
+
+        filter.set(LevelFilter::ERROR).unwrap();
+        assert_eq!(filter.level(), "error");
+        assert!(!tracing::enabled!(tracing::Level::WARN));
+
+        filter.set(LevelFilter::DEBUG).unwrap();
+        assert!(tracing::enabled!(tracing::Level::DEBUG));
+        assert!(!tracing::enabled!(tracing::Level::TRACE));
+    }
+}
@@ -0,0 +1,403 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap},
+};
+use std::convert::Infallible;
+use std::future::Future;
+
+tokio::task_local! {
+    static DEFAULT_LANG: Lang;
+}
+
+/// Languages the message catalog has translations for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Es,
+}
+
+impl Lang {
+    pub fn from_code(code: &str) -> Option<Lang> {
+        let primary = code.trim().split(['-', '_']).next().unwrap_or("");
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(Lang::En),
+            "es" => Some(Lang::Es),
+            _ => None,
+        }
+    }
+
+    /// Language used when the client doesn't ask for a supported one (DEFAULT_LANGUAGE; English outside of a request)
+    pub fn default_lang() -> Lang {
+        DEFAULT_LANG.try_with(|lang| *lang).unwrap_or(Lang::En)
+    }
+
+    /// Runs `future` with `lang` as the fallback for clients that don't ask for a supported language
+    pub async fn scope_default<F: Future>(lang: Lang, future: F) -> F::Output {
+        DEFAULT_LANG.scope(lang, future).await
+    }
+
+    /// Picks the supported language with the highest q-value from an Accept-Language header
+    pub fn from_accept_language(value: &str) -> Option<Lang> {
+        let mut best: Option<(Lang, f32)> = None;
+
+        for entry in value.split(',') {
+            let mut parts = entry.split(';');
+            let tag = parts.next().unwrap_or("").trim();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if let Some(lang) = Lang::from_code(tag) {
+                if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                    best = Some((lang, quality));
+                }
+            }
+        }
+
+        best.map(|(lang, _)| lang)
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Lang {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Lang::from_accept_language)
+            .unwrap_or_else(Lang::default_lang)
+    }
+
+    /// Looks up a message by key, falling back to English and then to the key itself
+    pub fn text(self, key: &'static str) -> &'static str {
+        let localized = match self {
+            Lang::En => english(key),
+            Lang::Es => spanish(key),
+        };
+        localized.or_else(|| english(key)).unwrap_or(key)
+    }
+
+    /// Looks up a message and fills its `{}` placeholders in order
+    pub fn format(self, key: &'static str, args: &[&str]) -> String {
+        let mut message = self.text(key).to_string();
+        for arg in args {
+            message = message.replacen("{}", arg, 1);
+        }
+        message
+    }
+}
+
+impl std::str::FromStr for Lang {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Lang::from_code(value).ok_or_else(|| format!("Unsupported language: {}", value))
+    }
+}
+
+impl<S> FromRequestParts<S> for Lang
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Lang::from_headers(&parts.headers))
+    }
+}
+
+fn english(key: &str) -> Option<&'static str> {
+    let message = match key {
+        // Requests
+        "request.invalid_id" => "Invalid id format",
+        "request.invalid_query" => "Invalid query parameters: {}",
+        "request.invalid_limit" => "limit must be between 1 and {}",
+        "request.empty_update" => "No fields to update",
+        "request.method_not_allowed" => "Method not allowed for this resource",
+        "request.body_too_large" => "Request body is too large",
+        "request.json_too_deep" => "JSON body is nested deeper than {} levels",
+        "request.body_required" => "Request body is required",
+        "request.json_too_many_fields" => "JSON body has more than {} fields",
+        "request.query_too_long" => "Query string is longer than {} bytes",
+        "request.unknown_field" => "Unknown field \"{}\"; choose from: {}",
+        "request.query_too_many_params" => "Query string has more than {} parameters",
+        "request.too_many_headers" => "Request has more than {} headers",
+        "request.headers_too_large" => "Request headers are larger than {} bytes",
+        "request.overloaded" => "Server is busy, please retry shortly",
+        "request.timeout" => "The request took too long and was cancelled",
+
+        // Auth
+        "auth.user_registered" => "User: {} registered successfully",
+        "auth.email_taken" => "User with this email already exists",
+        "auth.username_taken" => "This username is already taken",
+        "auth.username_invalid" => "Username must be {} to {} lowercase letters, digits or underscores",
+        "auth.username_reserved" => "Username is reserved",
+        "auth.password_processing_failed" => "Failed to process password",
+        "auth.invalid_credentials" => "Invalid email or password",
+        "auth.credentials_required" => "Email and password are required",
+        "auth.token_generation_failed" => "Failed to generate authentication token",
+        "auth.login_success" => "Login successful",
+        "auth.password_verification_failed" => "Failed to verify password",
+        "auth.profile_retrieved" => "Profile retrieved successfully",
+        "auth.profile_updated" => "Profile updated successfully",
+        "auth.account_deleted" => "Account deleted successfully",
+        "auth.email_verified" => "Email verified successfully",
+        "auth.verification_sent" => "Verification email sent",
+        "auth.already_verified" => "Email is already verified",
+        "auth.verification_rate_limited" => "Too many verification emails requested, please try again later",
+        "auth.verification_invalid" => "Invalid or expired verification token",
+        "auth.missing_header" => "No authorization header found",
+        "auth.invalid_token" => "Invalid token",
+        "auth.token_expired" => "Token has expired",
+        "auth.user_gone" => "The account for this token no longer exists",
+        "auth.session_idle" => "Session expired due to inactivity, please log in again",
+        "auth.session_revoked" => "Session ended by a newer login, please log in again",
+        "auth.admin_required" => "Admin role required",
+        "auth.registration_closed" => "Registration is closed; ask an administrator to create your account",
+        "auth.email_invalid" => "Invalid email format",
+        "auth.email_too_long" => "Email is too long",
+        "auth.email_disposable" => "Disposable email addresses are not allowed",
+        "auth.name_empty" => "Name cannot be empty",
+        "auth.name_too_long" => "Name is too long",
+        "auth.password_too_short" => "Password must be at least {} characters",
+        "auth.password_too_weak" => "Password must contain at least one uppercase letter, one lowercase letter, and one digit",
+        "auth.email_checked" => "Email availability checked",
+        "auth.email_check_rate_limited" => "Too many email checks, please try again later",
+        "user.not_found" => "User not found",
+        "user.retrieved" => "User retrieved successfully",
+        "activity.retrieved" => "Retrieved {} activity items",
+        "user.role_updated" => "User role updated successfully",
+        "user.followed" => "User followed",
+        "user.unfollowed" => "User unfollowed",
+        "user.follow_not_found" => "You don't follow this user",
+        "user.cannot_follow_self" => "You can't follow yourself",
+        "admin.log_level_updated" => "Log level set to {}",
+        "admin.log_level_invalid" => "Log level must be one of trace, debug, info, warn, error or off",
+        "admin.log_level_failed" => "Failed to set log level",
+        "user.last_admin" => "Cannot demote the last remaining admin",
+
+        // Posts
+        "post.title_empty" => "Post title cannot be empty",
+        "post.content_empty" => "Post content cannot be empty",
+        "post.duplicate_title" => "You already have a post with this title",
+        "post.limit_reached" => "You have reached the limit of {} posts",
+        "post.created" => "Post '{}' created successfully",
+        "post.created_fetch_failed" => "Post created but failed to retrieve with author info",
+        "post.retrieved" => "Post retrieved successfully",
+        "post.not_found" => "Post not found",
+        "post.random_none" => "There are no posts to pick from",
+        "post.list_retrieved" => "Retrieved {} posts",
+        "post.invalid_sort" => "Unknown sort {}; use created_at, updated_at or title, prefixed with - for descending",
+        "post.invalid_tag_filter" => "tag must be 1 to {} characters",
+        "post.invalid_author_filter" => "author must be a valid username",
+        "post.changes_retrieved" => "Retrieved {} changed posts",
+        "post.updated" => "Post '{}' updated successfully",
+        "post.updated_fetch_failed" => "Post updated but failed to retrieve with author info",
+        "post.update_not_found" => "Post not found or you don't have permission to update it",
+        "post.deleted" => "Post deleted successfully",
+        "post.delete_not_found" => "Post not found or you don't have permission to delete it",
+        "post.purged" => "Post permanently deleted",
+        "post.restored" => "Post restored",
+        "post.trash_retrieved" => "{} posts in your trash",
+        "post.purge_not_found" => "No post with that id in your trash",
+        "post.batch_deleted" => "Deleted {} posts",
+        "post.batch_size" => "Provide between 1 and {} post ids",
+        "batch.rolled_back" => "{} items failed, so none were applied",
+        "post.cover_uploaded" => "Cover image uploaded successfully",
+        "post.cover_missing" => "A 'cover' file field is required",
+        "post.cover_too_large" => "Cover image must be at most {} bytes",
+        "post.cover_unsupported" => "Cover must be a PNG, JPEG, GIF or WebP image",
+        "post.cover_not_found" => "Cover image not found",
+        "post.too_many_tags" => "A post can have at most {} tags",
+        "post.tag_too_long" => "Tags can be at most {} characters long",
+        "post.tag_invalid" => "Tag \"{}\" has no letters or digits",
+        "post.slug_not_allowed" => "Custom slugs are not enabled",
+        "post.slug_invalid" => "Slug must be lowercase letters, digits and single dashes, at most {} characters",
+        "post.slug_taken" => "A post with this slug already exists",
+        "post.liked" => "Post liked",
+        "post.unliked" => "Post unliked",
+        "post.like_not_found" => "You haven't liked this post",
+        "post.liked_list_retrieved" => "Retrieved {} liked posts",
+        "post.feed_retrieved" => "Retrieved {} posts from people you follow",
+        "post.marked_read" => "Post marked as read",
+        "post.batch_marked_read" => "Marked {} posts as read",
+        "post.unread_list_retrieved" => "Retrieved {} unread posts",
+        "post.published" => "Post '{}' published",
+        "post.unpublished" => "Post '{}' moved back to drafts",
+        "tag.list_retrieved" => "Retrieved {} tags",
+        "comment.content_empty" => "Comment content cannot be empty",
+        "comment.created" => "Comment created successfully",
+        "comment.list_retrieved" => "Retrieved {} comments",
+        "comment.count_retrieved" => "Post has {} comments",
+        "report.reason_empty" => "Report reason cannot be empty",
+        "report.reason_too_long" => "Report reason must be at most {} characters",
+        "report.created" => "Post reported",
+        "report.duplicate" => "You have already reported this post",
+        "report.list_retrieved" => "Retrieved {} reported posts",
+        "audit.list_retrieved" => "Retrieved {} audit entries",
+        "error.catalog_retrieved" => "Retrieved {} error codes",
+        "version.retrieved" => "Version {}",
+
+        // Schemas
+        "schema.retrieved" => "Schema for '{}' retrieved successfully",
+        "schema.not_found" => "No schema named '{}'",
+        _ => return None,
+    };
+    Some(message)
+}
+
+fn spanish(key: &str) -> Option<&'static str> {
+    let message = match key {
+        // Requests
+        "request.invalid_id" => "Formato de id no válido",
+        "request.invalid_query" => "Parámetros de consulta no válidos: {}",
+        "request.invalid_limit" => "limit debe estar entre 1 y {}",
+        "request.empty_update" => "No hay campos para actualizar",
+        "request.method_not_allowed" => "Método no permitido para este recurso",
+        "request.body_too_large" => "El cuerpo de la solicitud es demasiado grande",
+        "request.json_too_deep" => "El cuerpo JSON tiene más de {} niveles de anidamiento",
+        "request.body_required" => "El cuerpo de la petición es obligatorio",
+        "request.json_too_many_fields" => "El cuerpo JSON tiene más de {} campos",
+        "request.query_too_long" => "La cadena de consulta supera los {} bytes",
+        "request.unknown_field" => "Campo desconocido \"{}\"; elige entre: {}",
+        "request.query_too_many_params" => "La cadena de consulta tiene más de {} parámetros",
+        "request.too_many_headers" => "La solicitud tiene más de {} cabeceras",
+        "request.headers_too_large" => "Las cabeceras de la solicitud superan los {} bytes",
+        "request.overloaded" => "El servidor está ocupado, inténtalo de nuevo en breve",
+        "request.timeout" => "La solicitud tardó demasiado y se canceló",
+
+        // Auth
+        "auth.user_registered" => "Usuario: {} registrado correctamente",
+        "auth.email_taken" => "Ya existe un usuario con este correo electrónico",
+        "auth.username_taken" => "Ese nombre de usuario ya está en uso",
+        "auth.username_invalid" => "El nombre de usuario debe tener de {} a {} letras minúsculas, dígitos o guiones bajos",
+        "auth.username_reserved" => "Ese nombre de usuario está reservado",
+        "auth.password_processing_failed" => "No se pudo procesar la contraseña",
+        "auth.invalid_credentials" => "Correo electrónico o contraseña no válidos",
+        "auth.credentials_required" => "El correo electrónico y la contraseña son obligatorios",
+        "auth.token_generation_failed" => "No se pudo generar el token de autenticación",
+        "auth.login_success" => "Inicio de sesión correcto",
+        "auth.password_verification_failed" => "No se pudo verificar la contraseña",
+        "auth.profile_retrieved" => "Perfil obtenido correctamente",
+        "auth.profile_updated" => "Perfil actualizado correctamente",
+        "auth.account_deleted" => "Cuenta eliminada correctamente",
+        "auth.email_verified" => "Correo electrónico verificado correctamente",
+        "auth.verification_sent" => "Correo de verificación enviado",
+        "auth.already_verified" => "El correo electrónico ya está verificado",
+        "auth.verification_rate_limited" => "Demasiadas solicitudes de verificación, inténtalo más tarde",
+        "auth.verification_invalid" => "Token de verificación no válido o caducado",
+        "auth.missing_header" => "No se encontró la cabecera de autorización",
+        "auth.invalid_token" => "Token no válido",
+        "auth.token_expired" => "El token ha caducado",
+        "auth.user_gone" => "La cuenta de este token ya no existe",
+        "auth.session_idle" => "La sesión caducó por inactividad, vuelve a iniciar sesión",
+        "auth.session_revoked" => "Un inicio de sesión más reciente cerró esta sesión, vuelve a iniciar sesión",
+        "auth.admin_required" => "Se requiere el rol de administrador",
+        "auth.registration_closed" => "El registro está cerrado; pide a un administrador que cree tu cuenta",
+        "auth.email_invalid" => "Formato de correo electrónico no válido",
+        "auth.email_too_long" => "El correo electrónico es demasiado largo",
+        "auth.email_disposable" => "No se permiten direcciones de correo electrónico desechables",
+        "auth.name_empty" => "El nombre no puede estar vacío",
+        "auth.name_too_long" => "El nombre es demasiado largo",
+        "auth.password_too_short" => "La contraseña debe tener al menos {} caracteres",
+        "auth.password_too_weak" => "La contraseña debe contener al menos una letra mayúscula, una minúscula y un dígito",
+        "auth.email_checked" => "Disponibilidad del correo electrónico comprobada",
+        "auth.email_check_rate_limited" => "Demasiadas comprobaciones de correo electrónico, inténtalo más tarde",
+        "user.not_found" => "Usuario no encontrado",
+        "user.retrieved" => "Usuario obtenido correctamente",
+        "activity.retrieved" => "Se obtuvieron {} elementos de actividad",
+        "user.role_updated" => "Rol del usuario actualizado correctamente",
+        "user.followed" => "Ahora sigues a este usuario",
+        "user.unfollowed" => "Dejaste de seguir a este usuario",
+        "user.follow_not_found" => "No sigues a este usuario",
+        "user.cannot_follow_self" => "No puedes seguirte a ti mismo",
+        "admin.log_level_updated" => "Nivel de registro establecido en {}",
+        "admin.log_level_invalid" => "El nivel de registro debe ser trace, debug, info, warn, error u off",
+        "admin.log_level_failed" => "No se pudo establecer el nivel de registro",
+        "user.last_admin" => "No se puede degradar al último administrador",
+
+        // Posts
+        "post.title_empty" => "El título de la publicación no puede estar vacío",
+        "post.content_empty" => "El contenido de la publicación no puede estar vacío",
+        "post.duplicate_title" => "Ya tienes una publicación con este título",
+        "post.limit_reached" => "Has alcanzado el límite de {} publicaciones",
+        "post.created" => "Publicación '{}' creada correctamente",
+        "post.created_fetch_failed" => "Publicación creada pero no se pudo obtener con la información del autor",
+        "post.retrieved" => "Publicación obtenida correctamente",
+        "post.not_found" => "Publicación no encontrada",
+        "post.random_none" => "No hay publicaciones entre las que elegir",
+        "post.list_retrieved" => "Se obtuvieron {} publicaciones",
+        "post.invalid_sort" => "Orden desconocido {}; usa created_at, updated_at o title, con - delante para orden descendente",
+        "post.invalid_tag_filter" => "tag debe tener entre 1 y {} caracteres",
+        "post.invalid_author_filter" => "author debe ser un nombre de usuario válido",
+        "post.changes_retrieved" => "Se obtuvieron {} publicaciones modificadas",
+        "post.updated" => "Publicación '{}' actualizada correctamente",
+        "post.updated_fetch_failed" => "Publicación actualizada pero no se pudo obtener con la información del autor",
+        "post.update_not_found" => "Publicación no encontrada o no tienes permiso para actualizarla",
+        "post.deleted" => "Publicación eliminada correctamente",
+        "post.delete_not_found" => "Publicación no encontrada o no tienes permiso para eliminarla",
+        "post.purged" => "Publicación eliminada permanentemente",
+        "post.restored" => "Publicación restaurada",
+        "post.trash_retrieved" => "{} publicaciones en tu papelera",
+        "post.purge_not_found" => "No hay ninguna publicación con ese identificador en tu papelera",
+        "post.batch_deleted" => "Se eliminaron {} publicaciones",
+        "post.batch_size" => "Indica entre 1 y {} identificadores de publicación",
+        "batch.rolled_back" => "Fallaron {} elementos, así que no se aplicó ninguno",
+        "post.cover_uploaded" => "Imagen de portada subida correctamente",
+        "post.cover_missing" => "Se requiere un campo de archivo 'cover'",
+        "post.cover_too_large" => "La imagen de portada debe tener como máximo {} bytes",
+        "post.cover_unsupported" => "La portada debe ser una imagen PNG, JPEG, GIF o WebP",
+        "post.cover_not_found" => "Imagen de portada no encontrada",
+        "post.too_many_tags" => "Una publicación puede tener como máximo {} etiquetas",
+        "post.tag_too_long" => "Las etiquetas pueden tener como máximo {} caracteres",
+        "post.tag_invalid" => "La etiqueta \"{}\" no tiene letras ni dígitos",
+        "post.slug_not_allowed" => "Los slugs personalizados no están habilitados",
+        "post.slug_invalid" => "El slug debe tener letras minúsculas, dígitos y guiones simples, con un máximo de {} caracteres",
+        "post.slug_taken" => "Ya existe una publicación con este slug",
+        "post.liked" => "Te gusta la publicación",
+        "post.unliked" => "Ya no te gusta la publicación",
+        "post.like_not_found" => "No has marcado esta publicación con me gusta",
+        "post.liked_list_retrieved" => "Se obtuvieron {} publicaciones que te gustan",
+        "post.feed_retrieved" => "Se obtuvieron {} publicaciones de personas que sigues",
+        "post.marked_read" => "Publicación marcada como leída",
+        "post.batch_marked_read" => "Se marcaron {} publicaciones como leídas",
+        "post.unread_list_retrieved" => "Se obtuvieron {} publicaciones sin leer",
+        "post.published" => "Publicación '{}' publicada",
+        "post.unpublished" => "Publicación '{}' devuelta a borradores",
+        "tag.list_retrieved" => "Se obtuvieron {} etiquetas",
+        "comment.content_empty" => "El comentario no puede estar vacío",
+        "comment.created" => "Comentario creado correctamente",
+        "comment.list_retrieved" => "Se obtuvieron {} comentarios",
+        "comment.count_retrieved" => "La publicación tiene {} comentarios",
+        "report.reason_empty" => "El motivo de la denuncia no puede estar vacío",
+        "report.reason_too_long" => "El motivo de la denuncia debe tener como máximo {} caracteres",
+        "report.created" => "Publicación denunciada",
+        "report.duplicate" => "Ya has denunciado esta publicación",
+        "report.list_retrieved" => "Se obtuvieron {} publicaciones denunciadas",
+        "audit.list_retrieved" => "Se obtuvieron {} entradas de auditoría",
+        "error.catalog_retrieved" => "Se obtuvieron {} códigos de error",
+        "version.retrieved" => "Versión {}",
+
+        // Schemas
+        "schema.retrieved" => "Esquema de '{}' obtenido correctamente",
+        "schema.not_found" => "No existe un esquema llamado '{}'",
+        _ => return None,
+    };
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_language_picks_highest_quality() {
+        assert_eq!(Lang::from_accept_language("es-ES,es;q=0.9,en;q=0.8"), Some(Lang::Es));
+        assert_eq!(Lang::from_accept_language("fr;q=1.0, en;q=0.5, es;q=0.7"), Some(Lang::Es));
+        assert_eq!(Lang::from_accept_language("de, fr"), None);
+    }
+
+    #[test]
+    fn test_format_fills_placeholders() {
+        assert_eq!(Lang::En.format("post.list_retrieved", &["3"]), "Retrieved 3 posts");
+        assert_eq!(Lang::Es.format("post.list_retrieved", &["3"]), "Se obtuvieron 3 publicaciones");
+    }
+}
@@ -1,93 +1,646 @@
 use axum::{
-    extract::Request,
-    http::{header, StatusCode},
+    body::{Body, HttpBody},
+    extract::{ConnectInfo, Request, State},
+    http::{header, uri::PathAndQuery, HeaderMap, HeaderValue, Method, StatusCode, Uri},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
     Json,
 };
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
 
+use crate::config::{Settings, TrailingSlash};
+use crate::db::repositories::sql_user_repo::SqlUserRepository;
 use crate::helpers::auth::AuthHelper;
-use crate::model::model::ErrorResponse;
-use tracing::{error, info};
+use crate::helpers::errors::ErrorCode;
+use crate::helpers::extractors::Deadline;
+use crate::helpers::messages::Lang;
+use crate::helpers::timestamp;
+use crate::helpers::response::{self, error_response_with_status, unauthorized_response};
+use crate::helpers::validation::{check_json_shape, JsonShapeError};
+use crate::model::model::{ErrorResponse, FieldsQuery, PostFields};
+use crate::state::AppState;
+use serde_json::Value;
+use tower_http::cors::{Any, CorsLayer};
+use tracing::{debug, error, info, trace, warn, Level};
+
+// Same cap axum's Json extractor applies by default
+const JSON_BODY_LIMIT: usize = 2 * 1024 * 1024;
+// Bytes of a body written to the log by `body_logging_middleware`; the rest is elided
+const LOGGED_BODY_LIMIT: usize = 4 * 1024;
+// JSON keys whose values never reach the log (matched case-insensitively, anywhere in the key)
+const REDACTED_KEYS: [&str; 2] = ["password", "token"];
+
+// Token from an `Authorization: Bearer <token>` header. The scheme is matched case-insensitively and
+// surrounding whitespace is ignored, since clients send `bearer` or `Bearer  <token>` too
+fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.trim().split_once(char::is_whitespace)?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then(|| token.to_string())
+}
 
 pub async fn auth_middleware(
+    State(state): State<AppState>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
-    let auth_header = request
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|auth_header| auth_header.to_str().ok())
-        .and_then(|auth_str| {
-            if auth_str.starts_with("Bearer ") {
-                Some(auth_str[7..].to_string())
-            } else {
-                None
-            }
-        });
+    let auth_header = bearer_token(request.headers());
 
     let token = match auth_header {
         Some(token) => token,
         None => {
             error!("No authorization header found");
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ErrorResponse {
-                    error: "Unauthorized".to_string(),
-                    message: "No authorization header found".to_string(),
-                }),
+            return Err(unauthorized_response(
+                Lang::from_headers(request.headers()).text("auth.missing_header").to_string(),
             ));
         }
     };
 
-    let user_id = match AuthHelper::extract_user_id_from_token(&token) {
-        Ok(user_id) => user_id,
+    let (user_id, role, session_id) = match AuthHelper::extract_identity_from_token(&token, &state.settings) {
+        Ok(identity) => identity,
         Err(e) => {
             error!("Invalid token: {}", e);
+            let key = if AuthHelper::is_expired_token_error(&e) {
+                "auth.token_expired"
+            } else {
+                "auth.invalid_token"
+            };
+            return Err(unauthorized_response(
+                Lang::from_headers(request.headers()).text(key).to_string(),
+            ));
+        }
+    };
+
+    match session_rejection(&state, user_id, session_id, true).await {
+        Ok(None) => {},
+        Ok(Some(key)) => {
+            return Err(unauthorized_response(Lang::from_headers(request.headers()).text(key).to_string()));
+        },
+        Err(e) => {
+            error!("Failed to check session of user {}: {}", user_id, e);
             return Err((
-                StatusCode::UNAUTHORIZED,
+                StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse {
-                    error: "Unauthorized".to_string(),
-                    message: "Invalid token".to_string(),
+                    error: "Database Error".to_string(),
+                    message: "Failed to check session".to_string(),
                 }),
             ));
         }
-    };
+    }
 
     info!("Authenticated user: {}", user_id);
     
-    // Add user_id to request extensions
+    // Add user_id and the token's role to request extensions
     request.extensions_mut().insert(user_id);
+    request.extensions_mut().insert(role);
     
     Ok(next.run(request).await)
 }
 
+// Why a token that decoded fine no longer authenticates, as a message key; None while its session is live.
+// `refresh` restarts the idle window of a live session
+async fn session_rejection(
+    state: &AppState,
+    user_id: Uuid,
+    session_id: Option<Uuid>,
+    refresh: bool,
+) -> anyhow::Result<Option<&'static str>> {
+    let repo = SqlUserRepository::new((*state.pool).clone());
+
+    // Tokens of deleted accounts stay valid JWTs until they expire
+    if !repo.is_live(user_id).await? {
+        error!("Token of deleted user {}", user_id);
+        return Ok(Some("auth.user_gone"));
+    }
+
+    // Sessions pushed out by newer logins past MAX_SESSIONS_PER_USER
+    if let Some(session_id) = session_id {
+        if repo.is_session_revoked(session_id).await? {
+            error!("Session {} of user {} was revoked", session_id, user_id);
+            return Ok(Some("auth.session_revoked"));
+        }
+    }
+
+    // Idle-session timeout on top of the token's absolute expiry
+    if let Some(idle_timeout) = state.settings.idle_timeout {
+        let now = state.settings.clock.now();
+        match repo.find_last_seen(user_id).await? {
+            Some(last_seen) if now - last_seen > idle_timeout => {
+                error!("Session for user {} idle since {}", user_id, last_seen);
+                return Ok(Some("auth.session_idle"));
+            },
+            _ if refresh => {
+                if let Err(e) = repo.set_last_seen(user_id, now).await {
+                    error!("Failed to record activity for user {}: {}", user_id, e);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    Ok(None)
+}
+
+// A token that wouldn't pass `auth_middleware` makes the caller anonymous rather than failing the request
 pub async fn optional_auth_middleware(
+    State(state): State<AppState>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
-    let auth_header = request
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|auth_header| auth_header.to_str().ok())
-        .and_then(|auth_str| {
-            if auth_str.starts_with("Bearer ") {
-                Some(auth_str[7..].to_string())
-            } else {
+    let mut viewer = None::<Uuid>;
+
+    if let Some(token) = bearer_token(request.headers()) {
+        if let Ok((user_id, _, session_id)) = AuthHelper::extract_identity_from_token(&token, &state.settings) {
+            // Only authenticated routes count as activity, so optional ones don't keep an idle session alive
+            match session_rejection(&state, user_id, session_id, false).await {
+                Ok(None) => {
+                    info!("Optional authentication successful for user: {}", user_id);
+                    viewer = Some(user_id);
+                },
+                Ok(Some(_)) => {},
+                Err(e) => error!("Failed to check session of user {}: {}", user_id, e),
+            }
+        }
+    }
+
+    request.extensions_mut().insert(viewer);
+    Ok(next.run(request).await)
+}
+
+/// CORS policy: any origin, method and header, with preflight caching and exposed headers from config
+pub fn cors_layer(settings: &Settings) -> CorsLayer {
+    let expose_headers: Vec<header::HeaderName> = settings
+        .cors_expose_headers
+        .iter()
+        .filter_map(|name| match name.parse() {
+            Ok(name) => Some(name),
+            Err(_) => {
+                warn!("Ignoring invalid CORS expose header: {}", name);
                 None
             }
-        });
+        })
+        .collect();
+
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any)
+        .expose_headers(expose_headers);
+
+    match settings.cors_max_age {
+        Some(max_age) => cors.max_age(max_age),
+        None => cors,
+    }
+}
+
+/// Resolved address of the client, stored in request extensions by `client_ip_middleware`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+// Forwarding headers are only honoured when the proxy in front of us is trusted to set them
+pub fn resolve_client_ip(headers: &HeaderMap, peer: Option<SocketAddr>, trust_proxy: bool) -> Option<IpAddr> {
+    if trust_proxy {
+        let forwarded_for = headers
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        let real_ip = || {
+            headers
+                .get("x-real-ip")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|ip| ip.trim().parse().ok())
+        };
+
+        if let Some(ip) = forwarded_for.or_else(real_ip) {
+            return Some(ip);
+        }
+    }
+
+    peer.map(|addr| addr.ip())
+}
+
+pub async fn client_ip_middleware(
+    State(settings): State<Arc<Settings>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+
+    if let Some(ip) = resolve_client_ip(request.headers(), peer, settings.trust_proxy) {
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+
+    next.run(request).await
+}
+
+// Makes the configured timestamp format visible to response serialization
+pub async fn timestamp_format_middleware(
+    State(settings): State<Arc<Settings>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    timestamp::scope(settings.timestamp_format, next.run(request)).await
+}
+
+// Makes DEFAULT_LANGUAGE the fallback for Lang::from_headers while the request runs
+pub async fn default_language_middleware(
+    State(settings): State<Arc<Settings>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    Lang::scope_default(settings.default_language, next.run(request)).await
+}
+
+// Lets database error responses know whether EXPOSE_ERROR_DETAILS is on
+pub async fn error_details_middleware(
+    State(settings): State<Arc<Settings>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    response::error_details_scope(settings.expose_error_details, next.run(request)).await
+}
+
+// Answers a plain (non-preflight) OPTIONS with 204 and the route's methods in `Allow`.
+// The CORS layer answers every OPTIONS itself, so this relies on the Allow header axum's method router adds after it
+pub async fn options_middleware(
+    State(settings): State<Arc<Settings>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let preflight = request.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+    if !settings.options_allow || request.method() != Method::OPTIONS || preflight {
+        return next.run(request).await;
+    }
+
+    let response = next.run(request).await;
+    let Some(allow) = response.headers().get(header::ALLOW).and_then(|value| value.to_str().ok()) else {
+        return response;
+    };
+
+    let mut methods: Vec<&str> = allow.split(',').map(str::trim).filter(|method| !method.is_empty()).collect();
+    methods.push(Method::OPTIONS.as_str());
+    let allow = HeaderValue::from_str(&methods.join(", ")).expect("method names are valid header values");
+    (StatusCode::NO_CONTENT, [(header::ALLOW, allow)]).into_response()
+}
+
+// Applies the trailing slash policy; has to wrap the router so it runs before routing
+pub async fn trailing_slash_middleware(
+    State(settings): State<Arc<Settings>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    if settings.trailing_slash == TrailingSlash::Strict || path == "/" || !path.ends_with('/') {
+        return next.run(request).await;
+    }
+
+    let trimmed = match path.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    };
+    let path_and_query = match request.uri().query() {
+        Some(query) => format!("{}?{}", trimmed, query),
+        None => trimmed.to_string(),
+    };
+
+    if settings.trailing_slash == TrailingSlash::Redirect {
+        // 308 keeps the method and body, unlike 301; the path seen here has API_PREFIX stripped
+        let location = format!("{}{}", settings.api_prefix.as_deref().unwrap_or_default(), path_and_query);
+        return (StatusCode::PERMANENT_REDIRECT, [(header::LOCATION, location)]).into_response();
+    }
+
+    if let Ok(path_and_query) = PathAndQuery::try_from(path_and_query) {
+        let mut parts = request.uri().clone().into_parts();
+        parts.path_and_query = Some(path_and_query);
+        if let Ok(uri) = Uri::from_parts(parts) {
+            *request.uri_mut() = uri;
+        }
+    }
+
+    next.run(request).await
+}
+
+fn has_json_body(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|essence| essence.trim().to_ascii_lowercase())
+        .is_some_and(|essence| essence == "application/json" || essence.ends_with("+json"))
+}
+
+// Rejects structurally abusive JSON bodies (too deep / too many fields) before any extractor parses them
+pub async fn json_guard_middleware(
+    State(settings): State<Arc<Settings>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !has_json_body(request.headers()) {
+        return next.run(request).await;
+    }
+
+    let lang = Lang::from_headers(request.headers());
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, JSON_BODY_LIMIT).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to buffer JSON body: {}", e);
+            return error_response_with_status::<Value>(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Payload Too Large".to_string(),
+                lang.text("request.body_too_large").to_string(),
+            ).into_response();
+        }
+    };
+
+    if let Err(e) = check_json_shape(&bytes, settings.max_json_depth, settings.max_json_fields) {
+        let message = match e {
+            JsonShapeError::TooDeep => lang.format("request.json_too_deep", &[&settings.max_json_depth.to_string()]),
+            JsonShapeError::TooManyFields => lang.format("request.json_too_many_fields", &[&settings.max_json_fields.to_string()]),
+        };
+        return error_response_with_status::<Value>(StatusCode::BAD_REQUEST, "Bad Request".to_string(), message)
+            .into_response();
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+// Replaces the values of secret-looking keys, at any depth
+fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                if REDACTED_KEYS.iter().any(|secret| key.contains(secret)) {
+                    *field = Value::String("[REDACTED]".to_string());
+                } else {
+                    redact_secrets(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+// Body as it should appear in the log: redacted, and cut at LOGGED_BODY_LIMIT.
+// Anything that isn't valid JSON is left out, since it can't be checked for secrets
+fn loggable_body(bytes: &[u8]) -> String {
+    let Ok(mut value) = serde_json::from_slice::<Value>(bytes) else {
+        return format!("<{} bytes, not JSON>", bytes.len());
+    };
+    redact_secrets(&mut value);
+
+    let text = value.to_string();
+    if text.len() <= LOGGED_BODY_LIMIT {
+        return text;
+    }
+    let mut end = LOGGED_BODY_LIMIT;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}... ({} bytes total)", &text[..end], text.len())
+}
+
+// Debug aid behind LOG_BODIES: JSON request and response bodies at trace level. Runs inside json_guard,
+// so request bodies are already buffered; streamed responses (SSE, exports) are never buffered here
+pub async fn body_logging_middleware(
+    State(settings): State<Arc<Settings>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !settings.log_bodies || !tracing::enabled!(Level::TRACE) {
+        return next.run(request).await;
+    }
 
-    if let Some(token) = auth_header {
-        if let Ok(user_id) = AuthHelper::extract_user_id_from_token(&token) {
-            info!("Optional authentication successful for user: {}", user_id);
-            request.extensions_mut().insert(Some(user_id));
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let request = if has_json_body(request.headers()) {
+        let (parts, body) = request.into_parts();
+        match axum::body::to_bytes(body, JSON_BODY_LIMIT).await {
+            Ok(bytes) => {
+                trace!(%method, %path, "Request body: {}", loggable_body(&bytes));
+                Request::from_parts(parts, Body::from(bytes))
+            }
+            Err(e) => {
+                warn!("Failed to buffer request body for logging: {}", e);
+                return error_response_with_status::<Value>(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "Payload Too Large".to_string(),
+                    Lang::from_headers(&parts.headers).text("request.body_too_large").to_string(),
+                ).into_response();
+            }
+        }
+    } else {
+        request
+    };
+
+    let response = next.run(request).await;
+
+    // Only bodies already fully in memory, i.e. with an exact size
+    let buffered = response.body().size_hint().exact().is_some_and(|size| size as usize <= JSON_BODY_LIMIT);
+    if !buffered || !has_json_body(response.headers()) {
+        return response;
+    }
+
+    let status = response.status().as_u16();
+    let (parts, body) = response.into_parts();
+    match axum::body::to_bytes(body, JSON_BODY_LIMIT).await {
+        Ok(bytes) => {
+            trace!(%method, %path, status, "Response body: {}", loggable_body(&bytes));
+            Response::from_parts(parts, Body::from(bytes))
+        }
+        Err(e) => {
+            error!("Failed to buffer response body for logging: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+// Projects the posts in a successful JSON response down to `?fields=`; unknown fields are a 400 before the handler runs.
+// `shape` says which keys may be named and where the posts sit in `data`
+pub async fn fields_middleware(State(shape): State<PostFields>, request: Request, next: Next) -> Response {
+    let lang = Lang::from_headers(request.headers());
+    let query = match axum::extract::Query::<FieldsQuery>::try_from_uri(request.uri()) {
+        Ok(query) => query.0,
+        Err(rejection) => {
+            return error_response_with_status::<Value>(
+                StatusCode::BAD_REQUEST,
+                "Bad Request".to_string(),
+                lang.format("request.invalid_query", &[&rejection.body_text()]),
+            ).into_response();
+        }
+    };
+    let keys = match query.keys(shape.allowed()) {
+        Ok(Some(keys)) => keys,
+        Ok(None) => return next.run(request).await,
+        Err(field) => {
+            return error_response_with_status::<Value>(
+                StatusCode::BAD_REQUEST,
+                "Bad Request".to_string(),
+                lang.format("request.unknown_field", &[&field, &shape.allowed().join(", ")]),
+            ).into_response();
+        }
+    };
+
+    let response = next.run(request).await;
+    if !response.status().is_success() || !has_json_body(response.headers()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, JSON_BODY_LIMIT).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to buffer response body for field projection: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let posts = match shape {
+        PostFields::Thread => value.get_mut("data").and_then(|data| data.get_mut("post")),
+        PostFields::Post | PostFields::V2 => value.get_mut("data"),
+    };
+    if let Some(posts) = posts {
+        project_fields(posts, &keys);
+    }
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(value.to_string()))
+}
+
+// Keeps only `keys` of a post, or of each post in a list
+fn project_fields(data: &mut Value, keys: &[String]) {
+    match data {
+        Value::Array(posts) => posts.iter_mut().for_each(|post| project_fields(post, keys)),
+        Value::Object(post) => post.retain(|key, _| keys.contains(key)),
+        _ => {}
+    }
+}
+
+// Rejects oversized query strings before any Query extractor parses them
+pub async fn query_guard_middleware(
+    State(settings): State<Arc<Settings>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(query) = request.uri().query() {
+        let lang = Lang::from_headers(request.headers());
+        let message = if query.len() > settings.max_query_length {
+            Some(lang.format("request.query_too_long", &[&settings.max_query_length.to_string()]))
+        } else if query.split('&').filter(|param| !param.is_empty()).count() > settings.max_query_params {
+            Some(lang.format("request.query_too_many_params", &[&settings.max_query_params.to_string()]))
         } else {
-            request.extensions_mut().insert(None::<uuid::Uuid>);
+            None
+        };
+
+        if let Some(message) = message {
+            return error_response_with_status::<Value>(StatusCode::BAD_REQUEST, "Bad Request".to_string(), message)
+                .into_response();
         }
+    }
+
+    next.run(request).await
+}
+
+// Rejects requests with too many or too large headers with 431, before anything reads them
+pub async fn header_guard_middleware(
+    State(settings): State<Arc<Settings>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let bytes: usize = headers.iter().map(|(name, value)| name.as_str().len() + value.len()).sum();
+
+    let exceeded = if headers.len() > settings.max_header_count {
+        Some(("request.too_many_headers", settings.max_header_count))
+    } else if bytes > settings.max_header_bytes {
+        Some(("request.headers_too_large", settings.max_header_bytes))
     } else {
-        request.extensions_mut().insert(None::<uuid::Uuid>);
+        None
+    };
+
+    if let Some((key, limit)) = exceeded {
+        warn!("Rejected request with {} headers totalling {} bytes", headers.len(), bytes);
+        let message = Lang::from_headers(headers).format(key, &[&limit.to_string()]);
+        return error_response_with_status::<Value>(
+            ErrorCode::RequestHeaderFieldsTooLarge.status(),
+            ErrorCode::RequestHeaderFieldsTooLarge.error().to_string(),
+            message,
+        )
+        .into_response();
     }
-    
-    Ok(next.run(request).await)
-} 
\ No newline at end of file
+
+    next.run(request).await
+}
+
+// Bounds each request by REQUEST_TIMEOUT_MS; handlers pass the same deadline on to their queries so they stop too
+pub async fn request_timeout_middleware(
+    State(settings): State<Arc<Settings>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(timeout) = settings.request_timeout else {
+        return next.run(request).await;
+    };
+
+    let lang = Lang::from_headers(request.headers());
+    request.extensions_mut().insert(Deadline(Some(Instant::now() + timeout)));
+
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!("Request exceeded its {}ms budget", timeout.as_millis());
+            error_response_with_status::<Value>(
+                StatusCode::GATEWAY_TIMEOUT,
+                "Gateway Timeout".to_string(),
+                lang.text("request.timeout").to_string(),
+            ).into_response()
+        }
+    }
+}
+
+// Access log: one line per request with its outcome and latency
+pub async fn request_logging_middleware(
+    State(level): State<Level>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let client_ip = request
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ClientIp(ip)| ip.to_string())
+        .unwrap_or_else(|| "-".to_string());
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16();
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    macro_rules! log_request {
+        ($log:ident) => {
+            $log!(%client_ip, %method, %path, status, elapsed_ms, "{} {} {} -> {} ({}ms)", client_ip, method, path, status, elapsed_ms)
+        };
+    }
+    match level {
+        Level::ERROR => log_request!(error),
+        Level::WARN => log_request!(warn),
+        Level::INFO => log_request!(info),
+        Level::DEBUG => log_request!(debug),
+        _ => log_request!(trace),
+    }
+
+    response
+}
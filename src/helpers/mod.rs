@@ -3,3 +3,12 @@ pub mod user_helpers;
 pub mod validation;
 pub mod auth;
 pub mod middleware;
+pub mod messages;
+pub mod extractors;
+pub mod timestamp;
+pub mod errors;
+pub mod rate_limit;
+pub mod cache;
+pub mod clock;
+pub mod pagination;
+pub mod log_level;
@@ -0,0 +1,114 @@
+use axum::{
+    http::{header, HeaderValue, Uri},
+    response::{IntoResponseParts, ResponseParts},
+};
+use std::convert::Infallible;
+use std::future::Future;
+use crate::model::model::PageQuery;
+
+/// Query parameters a listing is paged with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageStyle {
+    /// `?page=` (1-based) and `?per_page=`
+    Page,
+    /// `?offset=` and `?limit=`
+    Offset,
+}
+
+impl PageStyle {
+    // (position, size) parameter names
+    fn params(self) -> (&'static str, &'static str) {
+        match self {
+            PageStyle::Page => ("page", "per_page"),
+            PageStyle::Offset => ("offset", "limit"),
+        }
+    }
+}
+
+/// RFC 8288 `Link` header with the first, prev, next and last pages of a listing
+#[derive(Clone, Debug)]
+pub struct PageLinks(HeaderValue);
+
+impl PageLinks {
+    /// Links around the `limit` items starting at `offset`, out of `total`.
+    /// They point at the request's own URL, other query parameters kept
+    pub fn new(uri: &Uri, style: PageStyle, offset: u64, limit: u64, total: u64) -> Option<Self> {
+        let limit = limit.max(1);
+        let last = total.saturating_sub(1) / limit * limit;
+
+        let mut links = vec![("first", 0)];
+        if offset > 0 {
+            links.push(("prev", offset.saturating_sub(limit).min(last)));
+        }
+        if offset.saturating_add(limit) < total {
+            links.push(("next", offset + limit));
+        }
+        links.push(("last", last));
+
+        let value = links
+            .into_iter()
+            .map(|(rel, offset)| format!("<{}>; rel=\"{}\"", page_url(uri, style, offset, limit), rel))
+            .collect::<Vec<_>>()
+            .join(", ");
+        HeaderValue::from_str(&value).ok().map(PageLinks)
+    }
+
+    /// Links for a `?page=&per_page=` listing of `total` items
+    pub fn for_page(uri: &Uri, page: &PageQuery, total: i64) -> Option<Self> {
+        Self::new(uri, PageStyle::Page, page.offset().into(), page.limit().into(), total.max(0) as u64)
+    }
+
+    /// Only a next link, for a cursor-paged listing: the request's URL with `cursor` in place of its own values
+    pub fn next(uri: &Uri, cursor: &[(&str, String)]) -> Option<Self> {
+        HeaderValue::from_str(&format!("<{}>; rel=\"next\"", url_with(uri, cursor))).ok().map(PageLinks)
+    }
+}
+
+impl IntoResponseParts for PageLinks {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.headers_mut().insert(header::LINK, self.0);
+        Ok(res)
+    }
+}
+
+/// The listing's total when PAGINATION_LINKS is on; `count` is only run then
+pub async fn total_if<F>(enabled: bool, count: F) -> anyhow::Result<Option<i64>>
+where
+    F: Future<Output = anyhow::Result<i64>>,
+{
+    if enabled {
+        count.await.map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+// `uri` with its paging parameters replaced by the page starting at `offset`
+fn page_url(uri: &Uri, style: PageStyle, offset: u64, limit: u64) -> String {
+    let (position, size) = style.params();
+    let position_value = match style {
+        PageStyle::Page => offset / limit + 1,
+        PageStyle::Offset => offset,
+    };
+    url_with(uri, &[(position, position_value.to_string()), (size, limit.to_string())])
+}
+
+// `uri` with `params` appended in place of any earlier values; the values must already be URL-safe
+fn url_with(uri: &Uri, params: &[(&str, String)]) -> String {
+    let mut pairs: Vec<String> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            params.iter().all(|(name, _)| key != *name)
+        })
+        .map(str::to_string)
+        .collect();
+    pairs.extend(params.iter().map(|(name, value)| format!("{}={}", name, value)));
+
+    format!("{}?{}", uri.path(), pairs.join("&"))
+}
@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Fixed-window request counter per key, by default the client IP
+pub struct RateLimiter<K = IpAddr> {
+    limit: u32,
+    window: Duration,
+    hits: Mutex<HashMap<K, (Instant, u32)>>,
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self { limit, window, hits: Mutex::new(HashMap::new()) }
+    }
+
+    /// Records a hit for `key`; false once it has used up the current window
+    pub fn check(&self, key: K) -> bool {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // Expired windows are dropped so the map only holds recently active clients
+        hits.retain(|_, (started, _)| now.duration_since(*started) < self.window);
+
+        let (_, count) = hits.entry(key).or_insert((now, 0));
+        if *count >= self.limit {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
@@ -4,14 +4,42 @@ use axum::{
     Json,
 };
 use serde_json::Value;
+use std::fmt::Display;
+use std::future::Future;
+use crate::db::sql_db::DeadlineExceeded;
 use crate::model::model::{ApiResponse, ErrorResponse};
 
+tokio::task_local! {
+    static EXPOSE_DETAILS: bool;
+}
+
+/// Runs `future` with underlying errors included in (or kept out of) database error messages
+pub async fn error_details_scope<F: Future>(expose: bool, future: F) -> F::Output {
+    EXPOSE_DETAILS.scope(expose, future).await
+}
+
+// Client-facing message for a failed `context`; the error itself only goes out with EXPOSE_ERROR_DETAILS,
+// otherwise it stays in the handler's log line
+fn error_message(context: &str, error: &dyn Display) -> String {
+    if EXPOSE_DETAILS.try_with(|expose| *expose).unwrap_or(false) {
+        format!("{}: {}", context, error)
+    } else {
+        context.to_string()
+    }
+}
+
 // Unified response type that can handle both success and error cases
 #[derive(serde::Serialize)]
 #[serde(untagged)]
 pub enum UnifiedResponse<T> {
     Success(ApiResponse<T>),
     Error(ErrorResponse),
+    // Error that needs a status other than the default 400
+    #[serde(skip)]
+    ErrorWithStatus(StatusCode, ErrorResponse),
+    // Data sent with a status other than 200, e.g. the per-item outcome of a rolled-back batch
+    #[serde(skip)]
+    SuccessWithStatus(StatusCode, ApiResponse<T>),
 }
 
 impl<T> IntoResponse for UnifiedResponse<T>
@@ -28,6 +56,12 @@ where
                 let (status, json) = (StatusCode::BAD_REQUEST, Json(response));
                 (status, json).into_response()
             }
+            UnifiedResponse::ErrorWithStatus(status, response) => {
+                (status, Json(response)).into_response()
+            }
+            UnifiedResponse::SuccessWithStatus(status, response) => {
+                (status, Json(response)).into_response()
+            }
         }
     }
 }
@@ -37,6 +71,19 @@ pub fn error_response_generic<T>(error: String, message: String) -> UnifiedRespo
     UnifiedResponse::Error(ErrorResponse { error, message })
 }
 
+// Generic error response with an explicit status code
+pub fn error_response_with_status<T>(status: StatusCode, error: String, message: String) -> UnifiedResponse<T> {
+    UnifiedResponse::ErrorWithStatus(status, ErrorResponse { error, message })
+}
+
+// Generic response carrying data under an explicit status code
+pub fn data_response_with_status<T>(status: StatusCode, message: String, data: T) -> UnifiedResponse<T> {
+    UnifiedResponse::SuccessWithStatus(status, ApiResponse {
+        message,
+        data: Some(data),
+    })
+}
+
 // Generic not found response that can be converted to any type
 pub fn not_found_response_generic<T>(message: String) -> UnifiedResponse<T> {
     UnifiedResponse::Success(ApiResponse {
@@ -47,9 +94,16 @@ pub fn not_found_response_generic<T>(message: String) -> UnifiedResponse<T> {
 
 // Generic SQL error response that can be converted to any type
 pub fn sql_error_response_generic<T>(error: anyhow::Error, context: &str) -> UnifiedResponse<T> {
+    if error.is::<DeadlineExceeded>() {
+        return error_response_with_status(
+            StatusCode::GATEWAY_TIMEOUT,
+            "Gateway Timeout".to_string(),
+            error_message(context, &error),
+        );
+    }
     UnifiedResponse::Error(ErrorResponse {
         error: "Database Error".to_string(),
-        message: format!("{}: {}", context, error),
+        message: error_message(context, &error),
     })
 }
 
@@ -77,10 +131,19 @@ pub fn create_error_response(
     (status_code, Json(response))
 }
 
+// Shared 401 body so middleware, extractors and handlers word rejections the same way
+pub fn unauthorized_response(message: String) -> (StatusCode, Json<ErrorResponse>) {
+    create_error_response("Unauthorized".to_string(), message, StatusCode::UNAUTHORIZED)
+}
+
+pub fn unauthorized_response_generic<T>(message: String) -> UnifiedResponse<T> {
+    error_response_with_status(StatusCode::UNAUTHORIZED, "Unauthorized".to_string(), message)
+}
+
 pub fn handle_mongo_error(error: mongodb::error::Error, error_context: &str) -> (StatusCode, Json<ErrorResponse>) {
     create_error_response(
         "Database Error".to_string(),
-        format!("{}: {}", error_context, error),
+        error_message(error_context, &error),
         StatusCode::INTERNAL_SERVER_ERROR,
     )
 }
@@ -88,7 +151,7 @@ pub fn handle_mongo_error(error: mongodb::error::Error, error_context: &str) ->
 pub fn handle_sql_error(error: anyhow::Error, error_context: &str) -> (StatusCode, Json<ErrorResponse>) {
     create_error_response(
         "Database Error".to_string(),
-        format!("{}: {}", error_context, error),
+        error_message(error_context, &error),
         StatusCode::INTERNAL_SERVER_ERROR,
     )
 }
@@ -115,7 +178,7 @@ pub fn not_found_response(message: String) -> UnifiedResponse<Value> {
 pub fn sql_error_response(error: anyhow::Error, context: &str) -> UnifiedResponse<Value> {
     UnifiedResponse::Error(ErrorResponse {
         error: "Database Error".to_string(),
-        message: format!("{}: {}", context, error),
+        message: error_message(context, &error),
     })
 }
 
@@ -0,0 +1,69 @@
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+use crate::config::TimestampFormat;
+
+tokio::task_local! {
+    static FORMAT: TimestampFormat;
+}
+
+/// Format in effect for the current request (RFC 3339 outside of one)
+pub fn current() -> TimestampFormat {
+    FORMAT.try_with(|format| *format).unwrap_or_default()
+}
+
+/// Runs `future` with response timestamps serialized as `format`
+pub async fn scope<F: Future>(format: TimestampFormat, future: F) -> F::Output {
+    FORMAT.scope(format, future).await
+}
+
+/// Synchronous counterpart of `scope`, for serializing outside the request task (SSE frames)
+pub fn sync_scope<R>(format: TimestampFormat, f: impl FnOnce() -> R) -> R {
+    FORMAT.sync_scope(format, f)
+}
+
+// `#[serde(with = "crate::helpers::timestamp")]` for response timestamps
+pub fn serialize<S: Serializer>(value: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    match current() {
+        TimestampFormat::Rfc3339 => serializer.serialize_str(&value.to_rfc3339()),
+        TimestampFormat::EpochMillis => serializer.serialize_i64(value.timestamp_millis()),
+    }
+}
+
+// Accepts either format so responses round-trip whatever the server setting
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Millis(i64),
+        Rfc3339(DateTime<Utc>),
+    }
+
+    match Repr::deserialize(deserializer)? {
+        Repr::Millis(millis) => DateTime::from_timestamp_millis(millis)
+            .ok_or_else(|| D::Error::custom(format!("Timestamp out of range: {}", millis))),
+        Repr::Rfc3339(value) => Ok(value),
+    }
+}
+
+/// `#[serde(with = "crate::helpers::timestamp::option")]` for optional response timestamps
+pub mod option {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => super::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(deserialize_with = "super::deserialize")] DateTime<Utc>);
+
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+    }
+}
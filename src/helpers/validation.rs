@@ -1,56 +1,84 @@
 use crate::model::model::{User, CreateUserRequest};
+use crate::helpers::messages::Lang;
 use regex::Regex;
 use lazy_static::lazy_static;
 
 lazy_static! {
-    static ref EMAIL_REGEX: Regex = Regex::new(r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap();
+    // Dot-separated atoms on both sides, so no leading, trailing or doubled dots; domain labels can't start or end with '-'
+    static ref EMAIL_REGEX: Regex = Regex::new(
+        r"^[a-zA-Z0-9_%+-]+(?:\.[a-zA-Z0-9_%+-]+)*@(?:[a-zA-Z0-9](?:[a-zA-Z0-9-]*[a-zA-Z0-9])?\.)+[a-zA-Z]{2,}$"
+    ).unwrap();
+    static ref SLUG_REGEX: Regex = Regex::new(r"^[a-z0-9]+(?:-[a-z0-9]+)*$").unwrap();
+    static ref USERNAME_REGEX: Regex = Regex::new(r"^[a-z0-9_]+$").unwrap();
 }
 
-/// Helper to validate user data before processing
-pub fn validate_user(user: &User) -> Result<(), String> {
+pub const MAX_SLUG_LENGTH: usize = 100;
+pub const MIN_USERNAME_LENGTH: usize = 3;
+pub const MAX_USERNAME_LENGTH: usize = 32;
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Usernames that would read as the service itself or clash with route names
+const RESERVED_USERNAMES: [&str; 16] = [
+    "admin", "administrator", "api", "auth", "errors", "help", "me", "moderator",
+    "null", "posts", "root", "schema", "support", "system", "tags", "users",
+];
+
+/// Bundled list of throwaway email providers, see `parse_domain_list` for the format
+pub const DISPOSABLE_EMAIL_DOMAINS: &str = include_str!("disposable_email_domains.txt");
+
+/// Helper to validate user data before processing; the error is the message in `lang`
+pub fn validate_user(user: &User, lang: Lang) -> Result<(), String> {
     // Email validation
-    if !EMAIL_REGEX.is_match(&user.email) {
-        return Err("Invalid email format".to_string());
+    if !validate_email(&user.email) {
+        return Err(lang.text("auth.email_invalid").to_string());
     }
     
     // Password validation
-    if user.password.len() < 8 {
-        return Err("Password must be at least 8 characters".to_string());
+    if user.password.len() < MIN_PASSWORD_LENGTH {
+        return Err(lang.format("auth.password_too_short", &[&MIN_PASSWORD_LENGTH.to_string()]));
     }
     
     // Name validation
     if user.name.is_empty() {
-        return Err("Name cannot be empty".to_string());
+        return Err(lang.text("auth.name_empty").to_string());
     }
     
     Ok(())
 }
 
-/// Helper fn  to validate user registration data
-pub fn validate_user_registration(user: &CreateUserRequest) -> Result<(), String> {
+/// Helper fn  to validate user registration data; emails at `blocked_domains` are refused. The error is the message in `lang`
+pub fn validate_user_registration(user: &CreateUserRequest, blocked_domains: &[String], lang: Lang) -> Result<(), String> {
     // Email validation
-    if !EMAIL_REGEX.is_match(&user.email) {
-        return Err("Invalid email format".to_string());
+    if !validate_email(&user.email) {
+        return Err(lang.text("auth.email_invalid").to_string());
+    }
+
+    if is_email_domain_blocked(&user.email, blocked_domains) {
+        return Err(lang.text("auth.email_disposable").to_string());
+    }
+
+    if let Some(username) = &user.username {
+        validate_username(username, lang)?;
     }
     
     // Password validation
-    if user.password.len() < 8 {
-        return Err("Password must be at least 8 characters".to_string());
+    if user.password.len() < MIN_PASSWORD_LENGTH {
+        return Err(lang.format("auth.password_too_short", &[&MIN_PASSWORD_LENGTH.to_string()]));
     }
     
     // Name 
     if user.name.trim().is_empty() {
-        return Err("Name cannot be empty".to_string());
+        return Err(lang.text("auth.name_empty").to_string());
     }
     
     // Email length 
     if user.email.len() > 255 {
-        return Err("Email is too long".to_string());
+        return Err(lang.text("auth.email_too_long").to_string());
     }
     
     // Name length
     if user.name.len() > 100 {
-        return Err("Name is too long".to_string());
+        return Err(lang.text("auth.name_too_long").to_string());
     }
     
     Ok(())
@@ -58,22 +86,295 @@ pub fn validate_user_registration(user: &CreateUserRequest) -> Result<(), String
 
 ///   email format checker
 pub fn validate_email(email: &str) -> bool {
-    EMAIL_REGEX.is_match(email)
+    // RFC 5321 caps the local part at 64 octets
+    let local_fits = email.split_once('@').is_some_and(|(local, _)| local.len() <= 64);
+    local_fits && EMAIL_REGEX.is_match(email)
 }
 
-/// check password strength
-pub fn validate_password(password: &str) -> Result<(), String> {
-    if password.len() < 8 {
-        return Err("Password must be at least 8 characters".to_string());
+/// Lowercase letters, digits and underscores, 3 to 32 long, and not reserved; the error is the message in `lang`
+pub fn validate_username(username: &str, lang: Lang) -> Result<(), String> {
+    if username.len() < MIN_USERNAME_LENGTH || username.len() > MAX_USERNAME_LENGTH || !USERNAME_REGEX.is_match(username) {
+        return Err(lang.format(
+            "auth.username_invalid",
+            &[&MIN_USERNAME_LENGTH.to_string(), &MAX_USERNAME_LENGTH.to_string()],
+        ));
+    }
+    if is_reserved_username(username) {
+        return Err(lang.text("auth.username_reserved").to_string());
+    }
+    Ok(())
+}
+
+pub fn is_reserved_username(username: &str) -> bool {
+    RESERVED_USERNAMES.contains(&username)
+}
+
+/// Username suggested by the email's local part, e.g. "Jane.Doe@x.com" -> "jane_doe"; may be reserved or taken
+pub fn username_from_email(email: &str) -> String {
+    let local = email.split('@').next().unwrap_or_default();
+    let username: String = local
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    // Leave room for a collision suffix
+    let username = username[..username.len().min(MAX_USERNAME_LENGTH - 6)].trim_matches('_').to_string();
+
+    if username.len() < MIN_USERNAME_LENGTH {
+        format!("{}_user", username).trim_start_matches('_').to_string()
+    } else {
+        username
+    }
+}
+
+/// One lowercase domain per line; blank lines and `#` comments are skipped
+pub fn parse_domain_list(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim().to_ascii_lowercase())
+        .filter(|domain| !domain.is_empty())
+        .collect()
+}
+
+/// Whether the email's domain is one of `domains` or a subdomain of one
+pub fn is_email_domain_blocked(email: &str, domains: &[String]) -> bool {
+    let Some((_, domain)) = email.rsplit_once('@') else {
+        return false;
+    };
+    let domain = domain.to_ascii_lowercase();
+    domains.iter().any(|blocked| {
+        domain == *blocked || domain.strip_suffix(blocked.as_str()).is_some_and(|rest| rest.ends_with('.'))
+    })
+}
+
+/// Lowercase ASCII words joined by single dashes, e.g. "Hello, World!" -> "hello-world"
+pub fn slugify(title: &str) -> String {
+    let slug = title
+        .to_ascii_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    // Leave room for a collision suffix
+    let slug = slug[..slug.len().min(MAX_SLUG_LENGTH - 10)].trim_end_matches('-').to_string();
+
+    if slug.is_empty() {
+        "post".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Format a client-supplied slug must have: what `slugify` produces
+pub fn validate_slug(slug: &str) -> bool {
+    slug.len() <= MAX_SLUG_LENGTH && SLUG_REGEX.is_match(slug)
+}
+
+/// check password strength; the error is the message in `lang`
+pub fn validate_password(password: &str, lang: Lang) -> Result<(), String> {
+    if password.len() < MIN_PASSWORD_LENGTH {
+        return Err(lang.format("auth.password_too_short", &[&MIN_PASSWORD_LENGTH.to_string()]));
     }
     
     let has_uppercase = password.chars().any(|c| c.is_uppercase());
     let has_lowercase = password.chars().any(|c| c.is_lowercase());
-    let has_digit = password.chars().any(|c| c.is_digit(10));
+    let has_digit = password.chars().any(|c| c.is_ascii_digit());
     
     if !has_uppercase || !has_lowercase || !has_digit {
-        return Err("Password must contain at least one uppercase letter, one lowercase letter, and one digit".to_string());
+        return Err(lang.text("auth.password_too_weak").to_string());
     }
     
     Ok(())
-} 
+}
+
+pub const MAX_TAGS_PER_POST: usize = 10;
+pub const MAX_TAG_LENGTH: usize = 32;
+
+/// Canonical form of a tag: lowercase letters and digits in hyphen-joined words, e.g. " Web  Dev! " -> "web-dev".
+/// None when no letter or digit is left
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-')
+        .collect::<String>();
+    let tag = tag
+        .split('-')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if tag.is_empty() {
+        None
+    } else {
+        Some(tag)
+    }
+}
+
+/// Every tag through `normalize_tag`, duplicates dropped and sorted to match how they're read back;
+/// the error is the first tag with nothing left
+pub fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, String> {
+    let mut normalized = tags
+        .into_iter()
+        .map(|tag| normalize_tag(&tag).ok_or(tag))
+        .collect::<Result<Vec<_>, _>>()?;
+    normalized.sort();
+    normalized.dedup();
+    Ok(normalized)
+}
+
+/// Detects a supported image type from its leading bytes
+pub fn detect_image_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// Structural limits a JSON body can break, see `check_json_shape`
+#[derive(Debug, PartialEq, Eq)]
+pub enum JsonShapeError {
+    TooDeep,
+    TooManyFields,
+}
+
+/// Scans raw JSON for nesting depth and total object field count without deserializing it
+pub fn check_json_shape(data: &[u8], max_depth: usize, max_fields: usize) -> Result<(), JsonShapeError> {
+    let mut depth = 0usize;
+    let mut fields = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for &byte in data {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                depth += 1;
+                if depth > max_depth {
+                    return Err(JsonShapeError::TooDeep);
+                }
+            },
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            // Every object member has exactly one colon outside a string
+            b':' => {
+                fields += 1;
+                if fields > max_fields {
+                    return Err(JsonShapeError::TooManyFields);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  Rust --  & Axum 2024 "), "rust-axum-2024");
+        assert_eq!(slugify("¡¿!?"), "post");
+        assert!(validate_slug(&slugify(&"long title ".repeat(50))));
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag("  Rust  ").as_deref(), Some("rust"));
+        assert_eq!(normalize_tag("Web   Dev").as_deref(), Some("web-dev"));
+        assert_eq!(normalize_tag("C++ & <script>").as_deref(), Some("c-script"));
+        assert_eq!(normalize_tag("--async -- await--").as_deref(), Some("async-await"));
+        assert_eq!(normalize_tag("Café").as_deref(), Some("café"));
+        assert_eq!(normalize_tag("'; DROP TABLE posts; --").as_deref(), Some("drop-table-posts"));
+        for bad in ["", "   ", "!!!", "- -"] {
+            assert_eq!(normalize_tag(bad), None, "{:?} should be rejected", bad);
+        }
+
+        let tags = vec!["Rust".to_string(), " rust ".to_string(), "Web Dev".to_string(), "web-dev".to_string()];
+        assert_eq!(normalize_tags(tags), Ok(vec!["rust".to_string(), "web-dev".to_string()]));
+        assert_eq!(normalize_tags(vec!["ok".to_string(), "?!".to_string()]), Err("?!".to_string()));
+    }
+
+    #[test]
+    fn test_usernames() {
+        assert_eq!(username_from_email("Jane.Doe+blog@example.com"), "jane_doe_blog");
+        assert_eq!(username_from_email("a@example.com"), "a_user");
+        assert!(validate_username(&username_from_email(&format!("{}@example.com", "x".repeat(64))), Lang::En).is_ok());
+
+        assert!(validate_username("jane_doe", Lang::En).is_ok());
+        for bad in ["ab", "Jane", "jane-doe", "jane doe", &"x".repeat(33)] {
+            assert!(validate_username(bad, Lang::En).is_err(), "{:?} should be invalid", bad);
+        }
+        assert_eq!(validate_username("admin", Lang::En), Err("Username is reserved".to_string()));
+        assert_eq!(
+            validate_username("ab", Lang::Es),
+            Err("El nombre de usuario debe tener de 3 a 32 letras minúsculas, dígitos o guiones bajos".to_string())
+        );
+    }
+
+    #[test]
+    fn test_disposable_email_domains() {
+        let domains = parse_domain_list(DISPOSABLE_EMAIL_DOMAINS);
+        assert!(domains.contains(&"mailinator.com".to_string()));
+        assert!(!domains.iter().any(|domain| domain.starts_with('#')));
+
+        assert!(is_email_domain_blocked("user@mailinator.com", &domains));
+        assert!(is_email_domain_blocked("user@MAILINATOR.com", &domains));
+        assert!(is_email_domain_blocked("user@eu.mailinator.com", &domains));
+        assert!(!is_email_domain_blocked("user@notmailinator.com", &domains));
+        assert!(!is_email_domain_blocked("user@example.com", &domains));
+        assert!(!is_email_domain_blocked("user@mailinator.com", &[]));
+    }
+
+    #[test]
+    fn test_validate_email_accepts_valid_addresses() {
+        for email in ["a@c.com", "a+b@c.co.uk", "first.last@example.org", "user_1%tag@sub-domain.example.io"] {
+            assert!(validate_email(email), "{} should be valid", email);
+        }
+    }
+
+    #[test]
+    fn test_validate_email_rejects_invalid_addresses() {
+        let too_long_local = format!("{}@example.com", "a".repeat(65));
+        for email in [
+            "a..b@c.com",
+            ".a@c.com",
+            "a.@c.com",
+            "a@.com",
+            "a@c..com",
+            "a@-c.com",
+            "a@c-.com",
+            "a@c",
+            "a@c.c",
+            "@c.com",
+            "a@",
+            "a b@c.com",
+            "a@@c.com",
+            too_long_local.as_str(),
+        ] {
+            assert!(!validate_email(email), "{} should be invalid", email);
+        }
+    }
+}
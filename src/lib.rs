@@ -2,6 +2,9 @@ pub mod model;
 pub mod handlers;
 pub mod helpers;
 pub mod db;
+pub mod config;
+pub mod state;
+pub mod app;
 
 // Re-exporting  commonly used types for easier access in tests
 pub use model::model::{CreateUserRequest, LoginRequest, CreatePostRequest}; 
@@ -1,35 +1,36 @@
-use axum::{
-    routing::{get, post, put, delete},
-    Router,
-    middleware,
-};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tower_http::cors::{CorsLayer, Any};
 use dotenv::dotenv;
+use tracing_subscriber::prelude::*;
 
-mod handlers;
-use handlers::{
-    handlers::{root},
-    auth_handlers::{register_user, login_user, get_profile, update_profile},
-    post_handlers::{create_post, get_post, get_user_posts, get_all_posts, update_post, delete_post},
+use api_rustone::{
+    app::build_app_with_read_pool,
+    db::{pruning::spawn_pruning, sql_db::get_sql_client},
+    config::Settings,
+    helpers::log_level::LogFilter,
 };
-pub mod model;
-pub use model::model::User;
-
-mod db;
-use db::sql_db::get_sql_client;
-
-pub mod helpers;
-use helpers::middleware::{auth_middleware, optional_auth_middleware};
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
-    tracing_subscriber::fmt::init();
+    // The filter sits behind a reload handle so admins can change the level at runtime
+    let (log_filter, filter_layer) = LogFilter::reloadable();
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    // Configuration is validated up front so every problem is reported before anything starts
+    let settings = match Settings::from_env() {
+        Ok(settings) => Settings { log_filter, ..settings },
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
     // Database setup
-    let sql_db = match get_sql_client().await {
+    let sql_db = match get_sql_client(&settings).await {
         Ok(db) => {
             println!("Connected to SQLite database successfully!");
             db
@@ -41,56 +42,20 @@ async fn main() {
     };
     
     let pool = Arc::new(sql_db.get_pool().clone());
+    let read_pool = Arc::new(sql_db.get_read_pool().clone());
+    // Expired sessions and old trash are cleaned up in the background for as long as the server runs
+    spawn_pruning((*pool).clone(), &settings);
+    let socket_address: SocketAddr = SocketAddr::from(([127, 0, 0, 1], settings.port));
     
-    // CORS setup
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
     // Route setup
-    let app = Router::new()
-        .route("/", get(root))
-        
-        // Public routes
-        .route("/auth/register", post(register_user))
-        .route("/auth/login", post(login_user))
-        .route("/posts", get(get_all_posts))
-        .route("/posts/{id}", get(get_post))
-        
-        // Protected routes
-        .route("/auth/profile", get(get_profile))
-        .route("/auth/profile", put(update_profile))
-        .route("/posts", post(create_post))
-        .route("/posts/my", get(get_user_posts))
-        .route("/posts/{id}", put(update_post))
-        .route("/posts/{id}", delete(delete_post))
-        
-        .layer(cors)
-        .layer(middleware::from_fn_with_state(
-            pool.clone(),
-            |req: axum::extract::Request, next: axum::middleware::Next| async move {
-                // Auth middleware
-                let path = req.uri().path();
-                if path.starts_with("/auth/profile") || 
-                   path.starts_with("/posts") && req.method() == "POST" ||
-                   path.starts_with("/posts/my") ||
-                   (path.starts_with("/posts/") && (req.method() == "PUT" || req.method() == "DELETE")) {
-                    auth_middleware(req, next).await
-                } else {
-                    optional_auth_middleware(req, next).await
-                }
-            }
-        ))
-        .with_state(pool);
-    
-    let socket_address: SocketAddr = SocketAddr::from(([127, 0, 0, 1], 8081));
+    let app = build_app_with_read_pool(pool, read_pool, settings);
 
     // Server startup
     
     tracing::debug!("Listening on {}", socket_address);
     let listener = tokio::net::TcpListener::bind(socket_address).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Peer address is needed to resolve the client IP
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
 
 
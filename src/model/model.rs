@@ -1,32 +1,160 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
+use crate::config::PostSort;
+use crate::helpers::messages::Lang;
+use crate::helpers::validation::{normalize_tag, validate_username, MAX_TAG_LENGTH};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "user" => Ok(Role::User),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("Unknown role: {}", other)),
+        }
+    }
+}
+
+/// Who can see a post: everyone, only its author, or anyone with the link (kept out of listings)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    #[default]
+    Public,
+    Private,
+    Unlisted,
+}
+
+impl Visibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Visibility::Public => "public",
+            Visibility::Private => "private",
+            Visibility::Unlisted => "unlisted",
+        }
+    }
+
+    /// Whether `viewer` may read a post by `author_id` directly (by id)
+    pub fn allows(&self, author_id: Uuid, viewer: Option<Uuid>) -> bool {
+        match self {
+            Visibility::Private => viewer == Some(author_id),
+            Visibility::Public | Visibility::Unlisted => true,
+        }
+    }
+}
+
+impl std::str::FromStr for Visibility {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "public" => Ok(Visibility::Public),
+            "private" => Ok(Visibility::Private),
+            "unlisted" => Ok(Visibility::Unlisted),
+            other => Err(format!("Unknown visibility: {}", other)),
+        }
+    }
+}
+
+/// Where a post is in the publishing workflow; drafts are seen only by their author, whatever their visibility
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PostStatus {
+    #[default]
+    Draft,
+    Published,
+}
+
+impl PostStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostStatus::Draft => "draft",
+            PostStatus::Published => "published",
+        }
+    }
+
+    /// Whether `viewer` may read a post by `author_id` in this status
+    pub fn allows(&self, author_id: Uuid, viewer: Option<Uuid>) -> bool {
+        match self {
+            PostStatus::Draft => viewer == Some(author_id),
+            PostStatus::Published => true,
+        }
+    }
+}
+
+impl std::str::FromStr for PostStatus {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "draft" => Ok(PostStatus::Draft),
+            "published" => Ok(PostStatus::Published),
+            other => Err(format!("Unknown post status: {}", other)),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct User {
     pub id: Uuid,
+    /// Unique lowercase handle; `name` is only for display
+    pub username: String,
     pub name: String,
     pub email: String,
     pub password: String,
+    pub role: Role,
+    pub email_verified: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct CreateUserRequest {
     pub name: String,
     pub email: String,
     pub password: String,
+    /// Derived from the email when absent
+    #[serde(default)]
+    pub username: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct UpdateUserRequest {
     pub name: Option<String>,
     pub email: Option<String>,
     pub password: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+impl UpdateUserRequest {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.email.is_none() && self.password.is_none()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct LoginRequest {
     pub email: String,
     pub password: String,
@@ -35,59 +163,837 @@ pub struct LoginRequest {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LoginResponse {
     pub token: String,
+    /// Always "Bearer": how the token goes in the Authorization header
+    pub token_type: String,
+    /// When the token stops being accepted, so clients can log in again beforehand
+    #[serde(with = "crate::helpers::timestamp")]
+    pub expires_at: DateTime<Utc>,
     pub user: UserResponse,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct UserResponse {
     pub id: Uuid,
+    #[serde(default)]
+    pub username: String,
     pub name: String,
     pub email: String,
+    #[serde(with = "crate::helpers::timestamp")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "crate::helpers::timestamp")]
     pub updated_at: DateTime<Utc>,
 }
 
+// Profile of the signed-in user: the public fields plus account state only they see
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProfileResponse {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    pub verified: bool,
+}
+
+// Body of PUT /admin/users/{id}/role
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SetRoleRequest {
+    pub role: Role,
+}
+
+// Body of PUT /admin/log-level: trace, debug, info, warn, error or off
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SetLogLevelRequest {
+    pub level: String,
+}
+
+// Filter in effect after PUT /admin/log-level
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LogLevelResponse {
+    pub level: String,
+}
+
+// A user as seen by admins: the public fields plus their role
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AdminUserResponse {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    pub role: Role,
+}
+
+/// Sensitive actions recorded in the audit log
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    PostForceDeleted,
+    UserRoleChanged,
+    UserDeleted,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::PostForceDeleted => "post_force_deleted",
+            AuditAction::UserRoleChanged => "user_role_changed",
+            AuditAction::UserDeleted => "user_deleted",
+        }
+    }
+}
+
+impl std::str::FromStr for AuditAction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "post_force_deleted" => Ok(AuditAction::PostForceDeleted),
+            "user_role_changed" => Ok(AuditAction::UserRoleChanged),
+            "user_deleted" => Ok(AuditAction::UserDeleted),
+            other => Err(format!("Unknown audit action: {}", other)),
+        }
+    }
+}
+
+// One row of GET /admin/audit
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub actor_id: Uuid,
+    pub action: AuditAction,
+    pub target_id: Uuid,
+    #[serde(with = "crate::helpers::timestamp")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EmailQuery {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EmailAvailability {
+    pub available: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Post {
     pub id: Uuid,
     pub title: String,
+    #[serde(default)]
+    pub slug: String,
     pub content: String,
     pub author_id: Uuid,
+    #[serde(default)]
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub status: PostStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::helpers::timestamp::option")]
+    pub published_at: Option<DateTime<Utc>>,
+    /// When the title or content last changed; metadata-only updates leave it alone
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::helpers::timestamp::option")]
+    pub edited_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(with = "crate::helpers::timestamp")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "crate::helpers::timestamp")]
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+impl Post {
+    /// Whether `viewer` may read this post directly (by id)
+    pub fn is_visible_to(&self, viewer: Option<Uuid>) -> bool {
+        self.visibility.allows(self.author_id, viewer) && self.status.allows(self.author_id, viewer)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct CreatePostRequest {
     pub title: String,
     pub content: String,
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// New posts are drafts unless created as `published`
+    #[serde(default)]
+    pub status: PostStatus,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Globally unique URL slug (requires CLIENT_SLUGS); derived from the title when absent
+    #[serde(default)]
+    pub slug: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct UpdatePostRequest {
     pub title: Option<String>,
     pub content: Option<String>,
+    pub visibility: Option<Visibility>,
+    /// Replaces all of the post's tags when present
+    pub tags: Option<Vec<String>>,
+}
+
+impl UpdatePostRequest {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.content.is_none() && self.visibility.is_none() && self.tags.is_none()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PostResponse {
     pub id: Uuid,
     pub title: String,
+    #[serde(default)]
+    pub slug: String,
+    pub content: String,
+    pub author: PostAuthor,
+    #[serde(default)]
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub status: PostStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::helpers::timestamp::option")]
+    pub published_at: Option<DateTime<Utc>>,
+    /// When the title or content last changed; metadata-only updates leave it alone
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::helpers::timestamp::option")]
+    pub edited_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+    /// Estimated from the word count of `content`; never stored
+    #[serde(default)]
+    pub reading_time_minutes: u32,
+    /// Whether the signed-in viewer marked the post read; left out for anonymous viewers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read: Option<bool>,
+    /// Whether `edited_at` is set
+    #[serde(default)]
+    pub is_edited: bool,
+    #[serde(with = "crate::helpers::timestamp")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "crate::helpers::timestamp")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PostResponse {
+    /// Whether `viewer` may read this post directly (by id)
+    pub fn is_visible_to(&self, viewer: Option<Uuid>) -> bool {
+        let author_id = self.author.id();
+        self.visibility.allows(author_id, viewer) && self.status.allows(author_id, viewer)
+    }
+
+    /// Hides the author's email from anyone but the author, unless `expose_email` is set
+    pub fn for_viewer(mut self, viewer: Option<Uuid>, expose_email: bool) -> Self {
+        if !expose_email && viewer != Some(self.author.id()) {
+            self.author = self.author.into_public();
+        }
+        self
+    }
+}
+
+// Author embedded in an expanded post; public views only get the id and name
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum PostAuthor {
+    Full(UserResponse),
+    Public(PublicAuthor),
+}
+
+impl PostAuthor {
+    pub fn id(&self) -> Uuid {
+        match self {
+            PostAuthor::Full(user) => user.id,
+            PostAuthor::Public(author) => author.id,
+        }
+    }
+
+    pub fn into_public(self) -> Self {
+        match self {
+            PostAuthor::Full(user) => PostAuthor::Public(PublicAuthor { id: user.id, username: user.username, name: user.name }),
+            public => public,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PublicAuthor {
+    pub id: Uuid,
+    #[serde(default)]
+    pub username: String,
+    pub name: String,
+}
+
+// Body of GET /users/{username}: what anyone may see about an account
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PublicUser {
+    pub id: Uuid,
+    pub username: String,
+    pub name: String,
+    #[serde(with = "crate::helpers::timestamp")]
+    pub created_at: DateTime<Utc>,
+}
+
+// Post as returned by read endpoints: lean (author_id only) unless the author is expanded
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum PostView {
+    Expanded(PostResponse),
+    Lean(Post),
+}
+
+impl PostView {
+    pub fn is_visible_to(&self, viewer: Option<Uuid>) -> bool {
+        match self {
+            PostView::Expanded(post) => post.is_visible_to(viewer),
+            PostView::Lean(post) => post.is_visible_to(viewer),
+        }
+    }
+
+    pub fn for_viewer(self, viewer: Option<Uuid>, expose_email: bool) -> Self {
+        match self {
+            PostView::Expanded(post) => PostView::Expanded(post.for_viewer(viewer, expose_email)),
+            lean => lean,
+        }
+    }
+}
+
+// Characters of content carried as a v2 post's excerpt
+pub const POST_EXCERPT_CHARS: usize = 200;
+
+// Post shape served under /v2: always the public author, plus an excerpt and an edited flag
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PostV2Response {
+    pub id: Uuid,
+    pub slug: String,
+    pub title: String,
+    pub excerpt: String,
+    pub content: String,
+    pub author: PublicAuthor,
+    pub visibility: Visibility,
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+    pub reading_time_minutes: u32,
+    pub edited: bool,
+    #[serde(with = "crate::helpers::timestamp")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "crate::helpers::timestamp")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<PostResponse> for PostV2Response {
+    fn from(post: PostResponse) -> Self {
+        let author = match post.author.into_public() {
+            PostAuthor::Public(author) => author,
+            PostAuthor::Full(user) => PublicAuthor { id: user.id, username: user.username, name: user.name },
+        };
+
+        PostV2Response {
+            id: post.id,
+            slug: post.slug,
+            title: post.title,
+            excerpt: post.content.chars().take(POST_EXCERPT_CHARS).collect(),
+            content: post.content,
+            author,
+            visibility: post.visibility,
+            tags: post.tags,
+            cover_url: post.cover_url,
+            reading_time_minutes: post.reading_time_minutes,
+            edited: post.is_edited,
+            created_at: post.created_at,
+            updated_at: post.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PostId {
+    pub id: Uuid,
+}
+
+// Body of create/update responses: the full post, or just its id under `Prefer: return=minimal`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum PostWriteResponse {
+    Full(Box<PostResponse>),
+    Minimal(PostId),
+}
+
+// Keys a post can be cut down to with `?fields=`; `author_id` is the lean shape's, `author` the expanded one's
+pub const POST_FIELDS: [&str; 17] = [
+    "id", "title", "slug", "content", "author", "author_id", "visibility", "status", "published_at", "edited_at",
+    "tags", "cover_url", "reading_time_minutes", "read", "is_edited", "created_at", "updated_at",
+];
+
+// Keys of a PostV2Response
+pub const POST_V2_FIELDS: [&str; 13] = [
+    "id", "slug", "title", "excerpt", "content", "author", "visibility", "tags", "cover_url", "reading_time_minutes",
+    "edited", "created_at", "updated_at",
+];
+
+/// Response shapes `?fields=` projects, each with the keys it may name
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostFields {
+    /// A post, or a list of them, in `data`
+    Post,
+    /// A PostThread: its `post` is projected, the counts and comments are left alone
+    Thread,
+    /// A PostV2Response, or a list of them
+    V2,
+}
+
+impl PostFields {
+    pub fn allowed(self) -> &'static [&'static str] {
+        match self {
+            PostFields::Post | PostFields::Thread => &POST_FIELDS,
+            PostFields::V2 => &POST_V2_FIELDS,
+        }
+    }
+}
+
+// `?fields=id,title` on post reads keeps only those keys of each post
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct FieldsQuery {
+    pub fields: Option<String>,
+}
+
+impl FieldsQuery {
+    /// The requested keys, or the first one not in `allowed`
+    pub fn keys(&self, allowed: &[&str]) -> Result<Option<Vec<String>>, String> {
+        let Some(fields) = &self.fields else {
+            return Ok(None);
+        };
+        fields
+            .split(',')
+            .map(str::trim)
+            .map(|field| if allowed.contains(&field) { Ok(field.to_string()) } else { Err(field.to_string()) })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some)
+    }
+}
+
+// `?expand=author` query parameter for post reads
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct ExpandQuery {
+    pub expand: Option<String>,
+}
+
+impl ExpandQuery {
+    pub fn author(&self) -> bool {
+        self.expand
+            .as_deref()
+            .is_some_and(|expand| expand.split(',').any(|field| field.trim() == "author"))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Comment {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub author_id: Uuid,
     pub content: String,
-    pub author: UserResponse,
+    #[serde(with = "crate::helpers::timestamp")]
     pub created_at: DateTime<Utc>,
+}
+
+// Body of GET /posts/{id}/comments/count
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CommentCount {
+    pub post_id: Uuid,
+    pub count: i64,
+}
+
+// Body of GET /posts/{id}/full: everything a post page shows, in one response
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PostThread {
+    pub post: PostResponse,
+    pub like_count: i64,
+    /// Across all pages; `comments` is just the requested one
+    pub comment_count: i64,
+    pub comments: Vec<Comment>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CreateCommentRequest {
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReportPostRequest {
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PostReport {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub reporter_id: Uuid,
+    pub reason: String,
+    #[serde(with = "crate::helpers::timestamp")]
+    pub created_at: DateTime<Utc>,
+}
+
+// One row of GET /admin/reports: a live post with how often, and why, it was reported
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReportedPost {
+    pub post_id: Uuid,
+    pub title: String,
+    pub author_id: Uuid,
+    pub report_count: i64,
+    pub reasons: Vec<String>,
+    #[serde(with = "crate::helpers::timestamp")]
+    pub last_reported_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BatchDeleteRequest {
+    #[schemars(with = "Vec<String>")]
+    pub ids: Vec<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MarkReadRequest {
+    #[schemars(with = "Vec<String>")]
+    pub ids: Vec<Uuid>,
+}
+
+// `?mode=partial|atomic` on batch endpoints
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchMode {
+    // Apply every item that can be applied and report the rest
+    #[default]
+    Partial,
+    // Apply all of the items or none of them
+    Atomic,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct BatchQuery {
+    #[serde(default)]
+    pub mode: BatchMode,
+}
+
+// One item a batch endpoint couldn't apply; `index` is its position in the request's list
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BatchFailure {
+    pub index: usize,
+    pub error: String,
+}
+
+// Per-item outcome of a batch endpoint (POST /posts/batch-delete, POST /posts/read)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BatchResult<T> {
+    pub succeeded: Vec<T>,
+    pub failed: Vec<BatchFailure>,
+}
+
+impl<T> BatchResult<T> {
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl BatchResult<Uuid> {
+    /// Outcome for `ids` (each with its request index) once `applied` went through; every other id fails with `error`
+    pub fn for_ids(ids: &[(usize, Uuid)], applied: &[Uuid], error: &str) -> Self {
+        let (succeeded, failed): (Vec<_>, Vec<_>) = ids.iter().copied().partition(|(_, id)| applied.contains(id));
+        BatchResult {
+            succeeded: succeeded.into_iter().map(|(_, id)| id).collect(),
+            failed: failed
+                .into_iter()
+                .map(|(index, _)| BatchFailure { index, error: error.to_string() })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentOrder {
+    #[default]
+    Oldest,
+    Newest,
+}
+
+// `?limit=&offset=&since=<rfc3339>&order=oldest|newest` for a post's comments
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct CommentQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    pub since: Option<DateTime<Utc>>,
+    pub order: Option<CommentOrder>,
+}
+
+impl CommentQuery {
+    const DEFAULT_LIMIT: u32 = 20;
+    const MAX_LIMIT: u32 = 100;
+
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.offset.unwrap_or(0)
+    }
+}
+
+// Body of GET /version
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VersionInfo {
+    pub version: String,
+    /// Short git hash of the build, "unknown" when built outside a checkout
+    pub commit: String,
+    #[serde(with = "crate::helpers::timestamp")]
+    pub built_at: DateTime<Utc>,
+}
+
+// One entry of GET /users/{id}/activity, tagged by `type`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActivityItem {
+    Post {
+        id: Uuid,
+        #[serde(with = "crate::helpers::timestamp")]
+        created_at: DateTime<Utc>,
+        /// The post's title
+        summary: String,
+    },
+    Comment {
+        id: Uuid,
+        post_id: Uuid,
+        #[serde(with = "crate::helpers::timestamp")]
+        created_at: DateTime<Utc>,
+        /// The start of the comment
+        summary: String,
+    },
+}
+
+impl ActivityItem {
+    pub fn created_at(&self) -> DateTime<Utc> {
+        match self {
+            ActivityItem::Post { created_at, .. } | ActivityItem::Comment { created_at, .. } => *created_at,
+        }
+    }
+}
+
+// `?before=<rfc3339>&limit=` for an activity timeline, newest first; pass the last item's created_at as `before` for the next page
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct ActivityQuery {
+    pub before: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+}
+
+impl ActivityQuery {
+    const DEFAULT_LIMIT: u32 = 20;
+    const MAX_LIMIT: u32 = 100;
+
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+}
+
+// One row of GET /tags
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+// `?page=&per_page=` for paginated listings (1-based pages)
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct PageQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+impl PageQuery {
+    const DEFAULT_PER_PAGE: u32 = 20;
+    const MAX_PER_PAGE: u32 = 100;
+
+    pub fn limit(&self) -> u32 {
+        self.per_page.unwrap_or(Self::DEFAULT_PER_PAGE).clamp(1, Self::MAX_PER_PAGE)
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.page.unwrap_or(1).max(1).saturating_sub(1).saturating_mul(self.limit())
+    }
+}
+
+// Query string of GET /posts; `validate` turns it into the filter the repository runs
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct PostListQuery {
+    pub sort: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    pub tag: Option<String>,
+    /// Username of the author
+    pub author: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl PostListQuery {
+    pub const MAX_LIMIT: u32 = 100;
+
+    /// Checks every parameter against its bounds or allowlist; the error is the message for the 400
+    pub fn validate(self, default_sort: PostSort, lang: Lang) -> Result<PostListFilter, String> {
+        let sort = match self.sort.as_deref() {
+            None => default_sort,
+            Some(sort) => sort.parse().map_err(|_| lang.format("post.invalid_sort", &[sort]))?,
+        };
+
+        if self.limit.is_some_and(|limit| limit == 0 || limit > Self::MAX_LIMIT) {
+            return Err(lang.format("request.invalid_limit", &[&Self::MAX_LIMIT.to_string()]));
+        }
+
+        // Same canonical form tags are stored in, so `?tag=Web Dev` finds "web-dev"
+        let tag = match self.tag {
+            Some(tag) => match normalize_tag(&tag) {
+                Some(tag) if tag.chars().count() <= MAX_TAG_LENGTH => Some(tag),
+                _ => return Err(lang.format("post.invalid_tag_filter", &[&MAX_TAG_LENGTH.to_string()])),
+            },
+            None => None,
+        };
+
+        let author = self.author.map(|author| author.trim().to_lowercase());
+        if author.as_deref().is_some_and(|author| validate_username(author, lang).is_err()) {
+            return Err(lang.text("post.invalid_author_filter").to_string());
+        }
+
+        Ok(PostListFilter {
+            sort,
+            limit: self.limit,
+            offset: self.offset.unwrap_or(0),
+            tag,
+            author,
+            since: self.since,
+        })
+    }
+}
+
+/// Validated GET /posts parameters; the default is the whole feed, newest first
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PostListFilter {
+    pub sort: PostSort,
+    /// Everything from `offset` on when unset
+    pub limit: Option<u32>,
+    pub offset: u32,
+    pub tag: Option<String>,
+    pub author: Option<String>,
+    /// Only posts created after this instant
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl PostListFilter {
+    /// The full feed in `sort` order, with no paging or filters
+    pub fn everything(sort: PostSort) -> Self {
+        PostListFilter { sort, ..Self::default() }
+    }
+}
+
+// `?quiet=true` on list endpoints leaves out the human-readable message
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct QuietQuery {
+    #[serde(default)]
+    pub quiet: bool,
+}
+
+impl QuietQuery {
+    /// The response message, only built when the client wants one
+    pub fn message(&self, message: impl FnOnce() -> String) -> String {
+        if self.quiet {
+            String::new()
+        } else {
+            message()
+        }
+    }
+}
+
+// `?since=<rfc3339>` cursor for /posts/changes; `after` is the last id of the previous page,
+// so entries sharing its timestamp aren't skipped
+#[derive(Deserialize, Clone, Debug)]
+pub struct ChangesQuery {
+    pub since: DateTime<Utc>,
+    pub after: Option<Uuid>,
+    pub limit: Option<u32>,
+}
+
+impl ChangesQuery {
+    const DEFAULT_LIMIT: u32 = 100;
+    const MAX_LIMIT: u32 = 500;
+
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+}
+
+/// A post the viewer could see that has since been deleted or hidden from them
+#[derive(Serialize, Clone, Debug)]
+pub struct PostTombstone {
+    pub id: Uuid,
+    pub deleted: bool, // Always true, marks the entry as a tombstone
+    #[serde(with = "crate::helpers::timestamp")]
     pub updated_at: DateTime<Utc>,
 }
 
+/// One entry of /posts/changes: the current post, or a tombstone once it is gone
+#[derive(Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum PostChange {
+    Upserted(Post),
+    Removed(PostTombstone),
+}
+
+impl PostChange {
+    pub fn updated_at(&self) -> DateTime<Utc> {
+        match self {
+            PostChange::Upserted(post) => post.updated_at,
+            PostChange::Removed(tombstone) => tombstone.updated_at,
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        match self {
+            PostChange::Upserted(post) => post.id,
+            PostChange::Removed(tombstone) => tombstone.id,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // User ID
     pub exp: usize,  // Expiration time
     pub iat: usize,  // Issued at
+    #[serde(default)]
+    pub role: Role,  // Role at the time the token was issued
+    pub iss: String, // Issuing service
+    pub aud: String, // Intended audience
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>, // Session id, only while sessions per user are capped
+}
+
+// Claims of an email verification token; `purpose` keeps it from passing as an access token
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerificationClaims {
+    pub sub: String,     // User ID
+    pub email: String,   // Address being verified
+    pub exp: usize,      // Expiration time
+    pub purpose: String, // Always "verify_email"
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct ApiResponse<T> {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub message: String,
     pub data: Option<T>,
 }
@@ -116,3 +1022,10 @@ pub struct UpdateUser{
     pub created_at: String,
     pub updated_at: String,
 }
+
+// Events broadcast to live subscribers (WebSocket)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum PostEvent {
+    PostCreated(PostResponse),
+}
@@ -0,0 +1,96 @@
+use axum::extract::FromRef;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+use crate::config::Settings;
+use crate::helpers::cache::PostsCache;
+use crate::helpers::rate_limit::RateLimiter;
+use crate::model::model::PostEvent;
+
+// How many undelivered events a slow subscriber may fall behind before it starts skipping
+const POST_EVENTS_CAPACITY: usize = 100;
+
+pub type PostEvents = broadcast::Sender<PostEvent>;
+
+/// Pool for read-only queries: a replica when one is configured, otherwise the primary
+#[derive(Clone)]
+pub struct ReadPool(pub Arc<SqlitePool>);
+
+// Shared application state; handlers extract the pieces they need via FromRef
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: Arc<SqlitePool>,
+    pub read_pool: Arc<SqlitePool>,
+    pub post_events: PostEvents,
+    pub settings: Arc<Settings>,
+    pub email_check_limiter: Arc<RateLimiter>,
+    /// Verification resends per user
+    pub verification_resend_limiter: Arc<RateLimiter<Uuid>>,
+    pub posts_cache: Arc<PostsCache>,
+}
+
+impl AppState {
+    pub fn new(pool: Arc<SqlitePool>, settings: Settings) -> Self {
+        Self::with_read_pool(pool.clone(), pool, settings)
+    }
+
+    pub fn with_read_pool(pool: Arc<SqlitePool>, read_pool: Arc<SqlitePool>, settings: Settings) -> Self {
+        let (post_events, _) = broadcast::channel(POST_EVENTS_CAPACITY);
+        let email_check_limiter = Arc::new(RateLimiter::new(settings.email_check_rate_limit, Duration::from_secs(60)));
+        let verification_resend_limiter = Arc::new(RateLimiter::new(settings.verification_resend_rate_limit, Duration::from_secs(60 * 60)));
+        let posts_cache = Arc::new(PostsCache::new(settings.posts_cache_ttl));
+        Self {
+            pool,
+            read_pool,
+            post_events,
+            settings: Arc::new(settings),
+            email_check_limiter,
+            verification_resend_limiter,
+            posts_cache,
+        }
+    }
+}
+
+impl FromRef<AppState> for Arc<SqlitePool> {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for ReadPool {
+    fn from_ref(state: &AppState) -> Self {
+        ReadPool(state.read_pool.clone())
+    }
+}
+
+impl FromRef<AppState> for PostEvents {
+    fn from_ref(state: &AppState) -> Self {
+        state.post_events.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Settings> {
+    fn from_ref(state: &AppState) -> Self {
+        state.settings.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RateLimiter> {
+    fn from_ref(state: &AppState) -> Self {
+        state.email_check_limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RateLimiter<Uuid>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.verification_resend_limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<PostsCache> {
+    fn from_ref(state: &AppState) -> Self {
+        state.posts_cache.clone()
+    }
+}
@@ -2,30 +2,38 @@ use axum::{
     body::Body,
     http::{Request, StatusCode},
     Router,
-    routing::{get, post, put, delete},
+    extract::ConnectInfo,
+    Extension,
+    routing::get,
     middleware,
 };
 
 use tower::ServiceExt;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tower_http::cors::{CorsLayer, Any};
 use api_rustone::{
-    model::model::{CreateUserRequest, LoginRequest, CreatePostRequest},
-    handlers::{
-        handlers::root,
-        auth_handlers::{register_user, login_user, get_profile, update_profile},
-        post_handlers::{create_post, get_post, get_user_posts, get_all_posts, update_post, delete_post},
-    },
-    helpers::middleware::{auth_middleware, optional_auth_middleware},
-    db::sql_db::get_sql_client,
+    app::{build_app, build_app_with_read_pool},
+    model::model::{CreateUserRequest, LoginRequest, CreatePostRequest, PostResponse, PostStatus, Role},
+    helpers::middleware::{client_ip_middleware, ClientIp},
+    config::{Features, PasswordAlgorithm, PostDeletion, Settings, TimestampFormat, TrailingSlash},
+    db::{pruning::prune, repositories::sql_user_repo::{SqlUserRepository, MAX_USERS_PER_PAGE}, sql_db::SqlDatabase},
+    helpers::auth::AuthHelper,
+    helpers::clock::FixedClock,
+    helpers::log_level::LogFilter,
+    helpers::messages::Lang,
 };
+use serde_json::Value;
 
 // Test app setup
 async fn create_test_app() -> Router {
+    create_test_app_with(Settings::default()).await
+}
+
+async fn create_test_app_with(settings: Settings) -> Router {
     dotenv::dotenv().ok();
     
-    // Database setup
-    let sql_db = match get_sql_client().await {
+    // Database setup (fresh in-memory database per app)
+    let sql_db = match SqlDatabase::new("sqlite::memory:").await {
         Ok(db) => db,
         Err(e) => {
             eprintln!("Failed to connect to SQLite database: {}", e);
@@ -34,48 +42,69 @@ async fn create_test_app() -> Router {
     };
     
     let pool = Arc::new(sql_db.get_pool().clone());
-    
-    // CORS setup
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    build_app(pool, settings)
+}
 
-    // Route setup
-    Router::new()
-        .route("/", get(root))
-        
-        // Public routes
-        .route("/auth/register", post(register_user))
-        .route("/auth/login", post(login_user))
-        .route("/posts", get(get_all_posts))
-        .route("/posts/{id}", get(get_post))
-        
-        // Protected routes
-        .route("/auth/profile", get(get_profile))
-        .route("/auth/profile", put(update_profile))
-        .route("/posts", post(create_post))
-        .route("/posts/my", get(get_user_posts))
-        .route("/posts/{id}", put(update_post))
-        .route("/posts/{id}", delete(delete_post))
-        
-        .layer(cors)
-        .layer(middleware::from_fn_with_state(
-            pool.clone(),
-            |req: axum::extract::Request, next: axum::middleware::Next| async move {
-                // Auth middleware
-                let path = req.uri().path();
-                if path.starts_with("/auth/profile") || 
-                   path.starts_with("/posts") && req.method() == "POST" ||
-                   path.starts_with("/posts/my") ||
-                   (path.starts_with("/posts/") && (req.method() == "PUT" || req.method() == "DELETE")) {
-                    auth_middleware(req, next).await
-                } else {
-                    optional_auth_middleware(req, next).await
-                }
-            }
-        ))
-        .with_state(pool)
+// Sends a request and returns the status with the parsed JSON body
+async fn send_json(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some(token) = token {
+        builder = builder.header("authorization", format!("Bearer {}", token));
+    }
+    let body = match body {
+        Some(body) => Body::from(body.to_string()),
+        None => Body::empty(),
+    };
+
+    let response = app
+        .clone()
+        .oneshot(builder.body(body).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let json = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, json)
+}
+
+// Registers a user and returns a valid token for it
+async fn register_and_login(app: &Router, name: &str, email: &str) -> String {
+    let password = "TestPass123";
+    let (status, _) = send_json(
+        app,
+        "POST",
+        "/auth/register",
+        None,
+        Some(serde_json::json!({ "name": name, "email": email, "password": password })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    login(app, email).await
+}
+
+// Logs in an existing test user (created with the shared test password)
+async fn login(app: &Router, email: &str) -> String {
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/auth/login",
+        None,
+        Some(serde_json::json!({ "email": email, "password": "TestPass123" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    body["data"]["token"].as_str().unwrap().to_string()
 }
 
 #[tokio::test]
@@ -86,6 +115,7 @@ async fn test_register_user() {
         name: "Test User".to_string(),
         email: "test@example.com".to_string(),
         password: "TestPass123".to_string(),
+        username: None,
     };
 
     let response = app
@@ -112,6 +142,7 @@ async fn test_login_user() {
         name: "Test User".to_string(),
         email: "test@example.com".to_string(),
         password: "TestPass123".to_string(),
+        username: None,
     };
 
     let register_response = app
@@ -157,6 +188,10 @@ async fn test_create_post() {
     let post_data = CreatePostRequest {
         title: "Test Post".to_string(),
         content: "This is a test post content.".to_string(),
+        visibility: Default::default(),
+        status: PostStatus::Published,
+        tags: Vec::new(),
+        slug: None,
     };
 
     let response = app
@@ -174,4 +209,4140 @@ async fn test_create_post() {
 
     // Check unauthorized
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
-} 
\ No newline at end of file
+} 
+
+#[tokio::test]
+async fn test_get_user_posts_includes_author() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Author", "author@example.com").await;
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Mine", "content": "My own post." })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send_json(&app, "GET", "/posts/my?expand=author", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let posts = body["data"].as_array().unwrap();
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0]["title"], "Mine");
+    assert_eq!(posts[0]["author"]["name"], "Author");
+    assert_eq!(posts[0]["author"]["email"], "author@example.com");
+    assert!(posts[0].get("author_id").is_none());
+}
+
+#[tokio::test]
+async fn test_messages_follow_accept_language() {
+    let app = create_test_app().await;
+    register_and_login(&app, "Lang User", "lang@example.com").await;
+
+    let login_body = serde_json::json!({ "email": "lang@example.com", "password": "TestPass123" }).to_string();
+    let mut messages = Vec::new();
+    for language in ["en-US,en;q=0.9", "es-ES,es;q=0.9,en;q=0.5"] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/auth/login")
+                    .header("content-type", "application/json")
+                    .header("accept-language", language)
+                    .body(Body::from(login_body.clone()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        messages.push(body["message"].as_str().unwrap().to_string());
+    }
+
+    assert_eq!(messages[0], "Login successful");
+    assert_eq!(messages[1], "Inicio de sesión correcto");
+
+    // Without a supported Accept-Language the configured default applies
+    let app = create_test_app_with(Settings { default_language: Lang::Es, ..Settings::default() }).await;
+    register_and_login(&app, "Lang User", "lang@example.com").await;
+    let (_, body) = send_json(&app, "POST", "/auth/login", None, Some(serde_json::json!({ "email": "lang@example.com", "password": "TestPass123" }))).await;
+    assert_eq!(body["message"], "Inicio de sesión correcto");
+
+    // Validation errors are localized too
+    let user = serde_json::json!({ "name": "Short", "email": "short@example.com", "password": "abc" });
+    let (status, body) = send_json(&app, "POST", "/auth/register", None, Some(user)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "La contraseña debe tener al menos 8 caracteres");
+    let user = serde_json::json!({ "name": " ", "email": "blank@example.com", "password": "TestPass123" });
+    let (_, body) = send_json(&app, "POST", "/auth/register", None, Some(user)).await;
+    assert_eq!(body["message"], "El nombre no puede estar vacío");
+}
+
+#[tokio::test]
+async fn test_ws_delivers_created_post() {
+    use futures_util::StreamExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Live Author", "live@example.com").await;
+
+    // WebSocket upgrades need a real connection, so serve the same app on an ephemeral port
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server_app = app.clone();
+    tokio::spawn(async move { axum::serve(listener, server_app).await.unwrap() });
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/posts", address))
+        .await
+        .unwrap();
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Live Post", "content": "Pushed to subscribers.", "status": "published" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+        .await
+        .expect("no post event received")
+        .unwrap()
+        .unwrap();
+    let event: Value = match message {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        other => panic!("unexpected message: {:?}", other),
+    };
+
+    assert_eq!(event["type"], "post_created");
+    assert_eq!(event["data"]["title"], "Live Post");
+    assert_eq!(event["data"]["author"]["name"], "Live Author");
+}
+
+#[tokio::test]
+async fn test_sse_streams_created_post() {
+    use futures_util::StreamExt;
+
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Stream Author", "stream@example.com").await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/posts/stream")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    let mut events = response.into_body().into_data_stream();
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Streamed Post", "content": "Sent over SSE.", "status": "published" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), events.next())
+        .await
+        .expect("no post event received")
+        .unwrap()
+        .unwrap();
+    let chunk = String::from_utf8(chunk.to_vec()).unwrap();
+
+    assert!(chunk.contains("event: post_created"));
+    let data = chunk
+        .lines()
+        .find_map(|line| line.strip_prefix("data: "))
+        .unwrap();
+    let post: Value = serde_json::from_str(data).unwrap();
+    assert_eq!(post["title"], "Streamed Post");
+}
+
+// Collects formatted tracing output so tests can assert on log lines
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for CapturedLogs {
+    type Writer = CapturedLogs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+impl CapturedLogs {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+#[tokio::test]
+async fn test_request_logging_records_method_path_status_latency() {
+    let logs = CapturedLogs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(logs.clone())
+        .with_ansi(false)
+        .with_max_level(tracing::Level::INFO)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = create_test_app().await;
+    let (status, _) = send_json(&app, "GET", "/posts/not-a-real-route/extra", None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let output = logs.contents();
+    let line = output
+        .lines()
+        .find(|line| line.contains("GET /posts/not-a-real-route/extra -> 404"))
+        .expect("no access log line");
+    assert!(line.contains(" INFO "));
+    assert!(line.contains("method=GET"));
+    assert!(line.contains("path=/posts/not-a-real-route/extra"));
+    assert!(line.contains("status=404"));
+    assert!(line.contains("elapsed_ms="));
+}
+
+#[tokio::test]
+async fn test_post_author_is_lean_unless_expanded() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Expand Author", "expand@example.com").await;
+
+    let (status, created) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Shape", "content": "Lean or expanded.", "status": "published" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let post_id = created["data"]["id"].as_str().unwrap().to_string();
+    let author_id = created["data"]["author"]["id"].clone();
+
+    for uri in ["/posts".to_string(), format!("/posts/{}", post_id)] {
+        let (status, body) = send_json(&app, "GET", &uri, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let post = if body["data"].is_array() { body["data"][0].clone() } else { body["data"].clone() };
+        assert_eq!(post["author_id"], author_id);
+        assert!(post.get("author").is_none());
+
+        let (status, body) = send_json(&app, "GET", &format!("{}?expand=author", uri), None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let post = if body["data"].is_array() { body["data"][0].clone() } else { body["data"].clone() };
+        assert_eq!(post["author"]["id"], author_id);
+        assert_eq!(post["author"]["name"], "Expand Author");
+        assert!(post.get("author_id").is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_post_changes_are_paged_by_cursor() {
+    // The clock never moves, so every change shares one timestamp and only the id cursor tells pages apart
+    let start = chrono::DateTime::parse_from_rfc3339("2024-03-01T09:30:00Z").unwrap().to_utc();
+    let app = create_test_app_with(Settings { clock: Arc::new(FixedClock::new(start)), ..Settings::default() }).await;
+    let token = register_and_login(&app, "Pager", "pager@example.com").await;
+
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        let (_, body) = send_json(
+            &app,
+            "POST",
+            "/posts",
+            Some(&token),
+            Some(serde_json::json!({ "title": format!("Post {}", i), "content": "c", "status": "published" })),
+        )
+        .await;
+        ids.push(body["data"]["id"].as_str().unwrap().to_string());
+    }
+    for id in &ids[..2] {
+        let (status, _) = send_json(&app, "DELETE", &format!("/posts/{}", id), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let mut seen = Vec::new();
+    let mut pages = 0;
+    let mut next = Some("/posts/changes?since=2024-03-01T09:00:00Z&limit=2".to_string());
+    while let Some(uri) = next.take() {
+        let response = app.clone().oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        next = response.headers().get("link").map(|value| {
+            let value = value.to_str().unwrap();
+            assert!(value.ends_with("; rel=\"next\""), "{}", value);
+            value.trim_start_matches('<').split('>').next().unwrap().to_string()
+        });
+        let body: Value = serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        let page = body["data"].as_array().unwrap();
+        assert!(page.len() <= 2);
+        seen.extend(page.iter().map(|change| change["id"].as_str().unwrap().to_string()));
+        pages += 1;
+    }
+
+    // Three live posts and two tombstones, each exactly once, the last page without a next link
+    assert_eq!(pages, 3);
+    let mut expected = ids.clone();
+    expected.sort();
+    assert_eq!(seen, expected);
+}
+
+#[tokio::test]
+async fn test_post_changes_since_cursor() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Sync Author", "sync@example.com").await;
+
+    let mut ids = Vec::new();
+    for title in ["Before A", "Before B", "Before D", "Before E"] {
+        let (status, body) = send_json(
+            &app,
+            "POST",
+            "/posts",
+            Some(&token),
+            Some(serde_json::json!({ "title": title, "content": "Existing content.", "status": "published" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        ids.push(body["data"]["id"].as_str().unwrap().to_string());
+    }
+
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let since = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true);
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+    // Update A, delete B, then create C after the cursor
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/posts/{}", ids[0]),
+        Some(&token),
+        Some(serde_json::json!({ "title": "After A" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_json(&app, "DELETE", &format!("/posts/{}", ids[1]), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "After C", "content": "New content.", "status": "published" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // D goes private and E back to draft: hidden from everyone but their author
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/posts/{}", ids[2]),
+        Some(&token),
+        Some(serde_json::json!({ "visibility": "private" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let (status, _) = send_json(&app, "POST", &format!("/posts/{}/unpublish", ids[3]), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send_json(&app, "GET", &format!("/posts/changes?since={}", since), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let changes = body["data"].as_array().unwrap();
+    let titles: Vec<&str> = changes
+        .iter()
+        .filter_map(|change| change["title"].as_str())
+        .collect();
+    assert_eq!(titles, vec!["After A", "After C"]);
+
+    // Deleted and hidden posts come back as tombstones
+    let tombstones: Vec<&serde_json::Value> = changes.iter().filter(|change| change.get("title").is_none()).collect();
+    let tombstone_ids: Vec<&str> = tombstones.iter().map(|change| change["id"].as_str().unwrap()).collect();
+    assert_eq!(tombstone_ids, vec![ids[1].as_str(), ids[2].as_str(), ids[3].as_str()]);
+    for tombstone in &tombstones {
+        assert_eq!(tombstone["deleted"], true);
+        assert!(tombstone["updated_at"].as_str().unwrap() > since.as_str());
+        assert_eq!(tombstone.as_object().unwrap().len(), 3);
+    }
+
+    // The author still sees D and E, only B is gone for them
+    let (status, body) = send_json(&app, "GET", &format!("/posts/changes?since={}", since), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let changes = body["data"].as_array().unwrap();
+    let titles: Vec<&str> = changes.iter().filter_map(|change| change["title"].as_str()).collect();
+    assert_eq!(titles, vec!["After A", "After C", "Before D", "Before E"]);
+    let tombstone_ids: Vec<&str> = changes
+        .iter()
+        .filter(|change| change["deleted"] == true)
+        .map(|change| change["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(tombstone_ids, vec![ids[1].as_str()]);
+
+    // Restoring B brings it back as a post
+    let (status, _) = send_json(&app, "POST", &format!("/posts/{}/restore", ids[1]), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send_json(&app, "GET", &format!("/posts/changes?since={}", since), None, None).await;
+    let changes = body["data"].as_array().unwrap();
+    assert!(changes.iter().any(|change| change["title"] == "Before B"));
+    assert!(!changes.iter().any(|change| change["id"] == ids[1].as_str() && change["deleted"] == true));
+}
+
+#[tokio::test]
+async fn test_duplicate_post_title_rejected_per_author() {
+    let app = create_test_app_with(Settings {
+        reject_duplicate_post_titles: true,
+        ..Settings::default()
+    })
+    .await;
+    let first = register_and_login(&app, "First Author", "first@example.com").await;
+    let second = register_and_login(&app, "Second Author", "second@example.com").await;
+    let post = serde_json::json!({ "title": "Same Title", "content": "Some content." });
+
+    let (status, _) = send_json(&app, "POST", "/posts", Some(&first), Some(post.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send_json(&app, "POST", "/posts", Some(&first), Some(post.clone())).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["error"], "Conflict");
+
+    // A different author may reuse the title
+    let (status, _) = send_json(&app, "POST", "/posts", Some(&second), Some(post)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_duplicate_post_title_allowed_by_default() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Repeat Author", "repeat@example.com").await;
+    let post = serde_json::json!({ "title": "Again", "content": "Some content." });
+
+    for _ in 0..2 {
+        let (status, _) = send_json(&app, "POST", "/posts", Some(&token), Some(post.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn test_profile_loads_authenticated_user() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Profile User", "profile@example.com").await;
+
+    let (status, body) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["email"], "profile@example.com");
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        "/auth/profile",
+        Some(&token),
+        Some(serde_json::json!({ "name": "Renamed User" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["name"], "Renamed User");
+}
+
+#[tokio::test]
+async fn test_profile_rejects_token_for_missing_user() {
+    let app = create_test_app().await;
+    // Validly signed token whose user doesn't exist
+    let token = api_rustone::helpers::auth::AuthHelper::generate_token(uuid::Uuid::new_v4(), Role::User, &Settings::default()).unwrap();
+
+    for method in ["GET", "PUT"] {
+        let (status, body) = send_json(
+            &app,
+            method,
+            "/auth/profile",
+            Some(&token),
+            Some(serde_json::json!({ "name": "Ghost" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"], "Unauthorized");
+        assert_eq!(body["message"], "The account for this token no longer exists");
+    }
+}
+
+#[tokio::test]
+async fn test_idle_session_is_rejected_until_next_login() {
+    let app = create_test_app_with(Settings {
+        idle_timeout: Some(chrono::Duration::milliseconds(1000)),
+        ..Settings::default()
+    })
+    .await;
+    let token = register_and_login(&app, "Idle User", "idle@example.com").await;
+
+    let (status, _) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Stay idle past the window
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    let (status, body) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["message"], "Session expired due to inactivity, please log in again");
+
+    // Logging in again resets the window
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/auth/login",
+        None,
+        Some(serde_json::json!({ "email": "idle@example.com", "password": "TestPass123" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let token = body["data"]["token"].as_str().unwrap().to_string();
+    let (status, _) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_idle_session_reads_as_anonymous_on_optional_routes() {
+    let clock = Arc::new(FixedClock::new(chrono::Utc::now()));
+    let app = create_test_app_with(Settings {
+        idle_timeout: Some(chrono::Duration::minutes(10)),
+        clock: clock.clone(),
+        ..Settings::default()
+    })
+    .await;
+    let token = register_and_login(&app, "Idle Reader", "idle-reader@example.com").await;
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Draft", "content": "Mine" }))).await;
+    let raw = format!("/posts/{}/raw", body["data"]["id"].as_str().unwrap());
+
+    // Reading where signing in is optional doesn't count as activity...
+    clock.advance(chrono::Duration::minutes(6));
+    let (status, _) = send_json(&app, "GET", &raw, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // ...so six more minutes make the session idle, and its token anonymous there
+    clock.advance(chrono::Duration::minutes(6));
+    let (status, _) = send_json(&app, "GET", &raw, Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_logins_past_the_session_cap_revoke_the_oldest() {
+    let app = create_test_app_with(Settings { max_sessions_per_user: Some(2), ..Settings::default() }).await;
+    let first = register_and_login(&app, "Busy", "busy@example.com").await;
+    let other = register_and_login(&app, "Other", "other@example.com").await;
+    let second = login(&app, "busy@example.com").await;
+
+    let (status, _) = send_json(&app, "GET", "/auth/profile", Some(&first), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // A third live session pushes out the first
+    let third = login(&app, "busy@example.com").await;
+    let (status, body) = send_json(&app, "GET", "/auth/profile", Some(&first), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["message"], "Session ended by a newer login, please log in again");
+    for token in [&second, &third, &other] {
+        let (status, _) = send_json(&app, "GET", "/auth/profile", Some(token), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    // Where signing in is optional the revoked token reads as anonymous, so it no longer reaches the owner's draft
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&third), Some(serde_json::json!({ "title": "Draft", "content": "Mine" }))).await;
+    let draft = format!("/posts/{}", body["data"]["id"].as_str().unwrap());
+    let (status, _) = send_json(&app, "GET", &format!("{}/raw", draft), Some(&third), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_json(&app, "GET", &format!("{}/raw", draft), Some(&first), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, body) = send_json(&app, "GET", &draft, Some(&first), None).await;
+    assert_eq!(body["data"], Value::Null);
+
+    // Unlimited by default
+    let app = create_test_app().await;
+    let first = register_and_login(&app, "Busy", "busy@example.com").await;
+    for _ in 0..3 {
+        login(&app, "busy@example.com").await;
+    }
+    let (status, _) = send_json(&app, "GET", "/auth/profile", Some(&first), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_admin_can_delete_any_post() {
+    let app = create_test_app_with(Settings {
+        admin_emails: vec!["admin@example.com".to_string()],
+        ..Settings::default()
+    })
+    .await;
+    let admin = register_and_login(&app, "Admin", "admin@example.com").await;
+    let author = register_and_login(&app, "Author", "author@example.com").await;
+    let other = register_and_login(&app, "Other", "other@example.com").await;
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&author),
+        Some(serde_json::json!({ "title": "Abusive", "content": "Should be moderated." })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let uri = format!("/admin/posts/{}", body["data"]["id"].as_str().unwrap());
+
+    // Regular users can't use the moderation route, even on their own posts
+    for token in [&other, &author] {
+        let (status, body) = send_json(&app, "DELETE", &uri, Some(token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"], "Forbidden");
+    }
+    let (status, _) = send_json(&app, "DELETE", &uri, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send_json(&app, "DELETE", &uri, Some(&admin), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn test_auth_rejection_bodies() {
+    let app = create_test_app().await;
+    let expired = api_rustone::helpers::auth::AuthHelper::generate_token_with_ttl(
+        uuid::Uuid::new_v4(),
+        Role::User,
+        chrono::Duration::hours(-2),
+        &Settings::default(),
+    )
+    .unwrap();
+
+    let cases = [
+        (None, "No authorization header found"),
+        (Some("not.a.jwt"), "Invalid token"),
+        (Some(expired.as_str()), "Token has expired"),
+    ];
+    for (token, message) in cases {
+        let (status, body) = send_json(&app, "GET", "/auth/profile", token, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body, serde_json::json!({ "error": "Unauthorized", "message": message }));
+    }
+
+    // Handler-level rejections use the same envelope
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/auth/login",
+        None,
+        Some(serde_json::json!({ "email": "nobody@example.com", "password": "TestPass123" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body, serde_json::json!({ "error": "Unauthorized", "message": "Invalid email or password" }));
+}
+
+#[tokio::test]
+async fn test_authorization_scheme_is_parsed_leniently() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Lenient", "lenient@example.com").await;
+    let profile = |authorization: String| {
+        let request = Request::builder()
+            .uri("/auth/profile")
+            .header("authorization", authorization)
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request)
+    };
+
+    for authorization in [
+        format!("bearer {}", token),
+        format!("BEARER {}", token),
+        format!("Bearer  {}", token),
+        format!("  Bearer\t{}  ", token),
+    ] {
+        let response = profile(authorization.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", authorization);
+    }
+
+    for authorization in [format!("Basic {}", token), format!("Bearer{}", token), token.clone(), "Bearer   ".to_string()] {
+        let response = profile(authorization.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{:?}", authorization);
+    }
+}
+
+// Uploads bytes as the `cover` field of a multipart form
+async fn upload_cover(app: &Router, post_id: &str, token: &str, content_type: &str, data: &[u8]) -> (StatusCode, Value) {
+    upload_cover_to(app, &format!("/posts/{}/cover", post_id), token, content_type, data).await
+}
+
+async fn upload_cover_to(app: &Router, uri: &str, token: &str, content_type: &str, data: &[u8]) -> (StatusCode, Value) {
+    let boundary = "test-boundary-7MA4YWxkTrZu0gW";
+    let mut body = Vec::new();
+    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+    body.extend_from_slice(b"Content-Disposition: form-data; name=\"cover\"; filename=\"cover\"\r\n");
+    body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", content_type).as_bytes());
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_post_cover_upload() {
+    let app = create_test_app_with(Settings {
+        max_cover_bytes: 1024,
+        ..Settings::default()
+    })
+    .await;
+    let token = register_and_login(&app, "Cover Author", "cover@example.com").await;
+    let other = register_and_login(&app, "Other", "other-cover@example.com").await;
+
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "With Cover", "content": "Has an image.", "status": "published" })),
+    )
+    .await;
+    let post_id = created["data"]["id"].as_str().unwrap().to_string();
+    assert!(created["data"].get("cover_url").is_none());
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.extend_from_slice(&[0u8; 64]);
+
+    // Valid image from the owner
+    let (status, body) = upload_cover(&app, &post_id, &token, "image/png", &png).await;
+    assert_eq!(status, StatusCode::OK);
+    let cover_url = format!("/posts/{}/cover", post_id);
+    assert_eq!(body["data"]["cover_url"], cover_url.as_str());
+
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(&cover_url).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(bytes.to_vec(), png);
+
+    // Over the size limit
+    let mut large = png.clone();
+    large.extend_from_slice(&[0u8; 2048]);
+    let (status, body) = upload_cover(&app, &post_id, &token, "image/png", &large).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(body["error"], "Payload Too Large");
+
+    // Not an image, whether declared honestly or not
+    let (status, _) = upload_cover(&app, &post_id, &token, "text/plain", b"hello").await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let (status, body) = upload_cover(&app, &post_id, &token, "image/png", b"hello").await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(body["error"], "Unsupported Media Type");
+
+    // Someone else's post
+    let (status, body) = upload_cover(&app, &post_id, &other, "image/png", &png).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["data"].is_null());
+}
+
+// Echoes the resolved client IP behind the same middleware the app uses
+fn client_ip_app(trust_proxy: bool) -> Router {
+    let settings = Arc::new(Settings { trust_proxy, ..Settings::default() });
+    Router::new()
+        .route(
+            "/ip",
+            get(|Extension(ClientIp(ip)): Extension<ClientIp>| async move { ip.to_string() }),
+        )
+        .layer(middleware::from_fn_with_state(settings, client_ip_middleware))
+}
+
+async fn resolved_ip(app: &Router, headers: &[(&str, &str)]) -> String {
+    let peer: SocketAddr = "10.0.0.1:40000".parse().unwrap();
+    let mut builder = Request::builder().uri("/ip").extension(ConnectInfo(peer));
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    let response = app.clone().oneshot(builder.body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_client_ip_resolution() {
+    // Untrusted: spoofed forwarding headers are ignored
+    let app = client_ip_app(false);
+    assert_eq!(resolved_ip(&app, &[]).await, "10.0.0.1");
+    assert_eq!(resolved_ip(&app, &[("x-forwarded-for", "203.0.113.7")]).await, "10.0.0.1");
+    assert_eq!(resolved_ip(&app, &[("x-real-ip", "203.0.113.7")]).await, "10.0.0.1");
+
+    // Trusted: forwarding headers win, with the peer address as the fallback
+    let app = client_ip_app(true);
+    assert_eq!(resolved_ip(&app, &[]).await, "10.0.0.1");
+    assert_eq!(
+        resolved_ip(&app, &[("x-forwarded-for", "203.0.113.7, 10.0.0.2")]).await,
+        "203.0.113.7"
+    );
+    assert_eq!(resolved_ip(&app, &[("x-real-ip", "198.51.100.4")]).await, "198.51.100.4");
+    assert_eq!(
+        resolved_ip(&app, &[("x-forwarded-for", "not-an-ip"), ("x-real-ip", "198.51.100.4")]).await,
+        "198.51.100.4"
+    );
+}
+
+#[tokio::test]
+async fn test_admin_route_uses_role_from_token() {
+    let app = create_test_app_with(Settings {
+        admin_emails: vec!["admin@example.com".to_string()],
+        ..Settings::default()
+    })
+    .await;
+    let admin = register_and_login(&app, "Admin", "admin@example.com").await;
+    let (_, profile) = send_json(&app, "GET", "/auth/profile", Some(&admin), None).await;
+    let admin_id = uuid::Uuid::parse_str(profile["data"]["id"].as_str().unwrap()).unwrap();
+
+    let (_, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&admin),
+        Some(serde_json::json!({ "title": "Moderated", "content": "Owned by the admin." })),
+    )
+    .await;
+    let uri = format!("/admin/posts/{}", body["data"]["id"].as_str().unwrap());
+
+    // The account is an admin, but a token minted with the user role is still refused
+    let user_token = api_rustone::helpers::auth::AuthHelper::generate_token(admin_id, Role::User, &Settings::default()).unwrap();
+    let (status, body) = send_json(&app, "DELETE", &uri, Some(&user_token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body, serde_json::json!({ "error": "Forbidden", "message": "Admin role required" }));
+
+    let (status, _) = send_json(&app, "DELETE", &uri, Some(&admin), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_malformed_post_id_envelope() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Path User", "path@example.com").await;
+    let expected = serde_json::json!({ "error": "Bad Request", "message": "Invalid id format" });
+
+    let (status, body) = send_json(&app, "GET", "/posts/not-a-uuid", None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, expected);
+
+    for method in ["PUT", "DELETE"] {
+        let (status, body) = send_json(
+            &app,
+            method,
+            "/posts/not-a-uuid",
+            Some(&token),
+            Some(serde_json::json!({ "title": "Nope" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_registration_open_by_default() {
+    let app = create_test_app().await;
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/auth/register",
+        None,
+        Some(serde_json::json!({ "name": "Open", "email": "open@example.com", "password": "TestPass123" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["email"], "open@example.com");
+}
+
+#[tokio::test]
+async fn test_closed_registration_admin_creates_users() {
+    let app = create_test_app_with(Settings {
+        registration_open: false,
+        admin_emails: vec!["admin@example.com".to_string()],
+        ..Settings::default()
+    })
+    .await;
+    let new_user = serde_json::json!({ "name": "Invitee", "email": "invitee@example.com", "password": "TestPass123" });
+
+    let (status, body) = send_json(&app, "POST", "/auth/register", None, Some(new_user.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "Forbidden");
+    assert_eq!(body["message"], "Registration is closed; ask an administrator to create your account");
+
+    // Configured admins can still sign up, and then create accounts for others
+    let admin = register_and_login(&app, "Admin", "admin@example.com").await;
+    let (status, body) = send_json(&app, "POST", "/admin/users", Some(&admin), Some(new_user)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["email"], "invitee@example.com");
+
+    let invitee = login(&app, "invitee@example.com").await;
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/admin/users",
+        Some(&invitee),
+        Some(serde_json::json!({ "name": "Another", "email": "another@example.com", "password": "TestPass123" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_admin_sets_log_level_at_runtime() {
+    let log_filter = LogFilter::detached();
+    let app = create_test_app_with(Settings {
+        admin_emails: vec!["admin@example.com".to_string()],
+        log_filter: log_filter.clone(),
+        ..Settings::default()
+    })
+    .await;
+    let admin = register_and_login(&app, "Admin", "admin@example.com").await;
+    let user = register_and_login(&app, "User", "user@example.com").await;
+
+    let (status, body) = send_json(&app, "PUT", "/admin/log-level", Some(&admin), Some(serde_json::json!({ "level": "DEBUG" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["level"], "debug");
+    assert_eq!(log_filter.level(), "debug");
+
+    let (status, body) = send_json(&app, "PUT", "/admin/log-level", Some(&admin), Some(serde_json::json!({ "level": "loud" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "Bad Request");
+    assert_eq!(log_filter.level(), "debug");
+
+    let (status, _) = send_json(&app, "PUT", "/admin/log-level", Some(&user), Some(serde_json::json!({ "level": "trace" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(log_filter.level(), "debug");
+}
+
+#[tokio::test]
+async fn test_login_rejects_empty_credentials() {
+    let app = create_test_app().await;
+    register_and_login(&app, "Known", "known@example.com").await;
+
+    // 400 rather than the 401 a real lookup/verify would give
+    let cases = [
+        serde_json::json!({ "email": "", "password": "TestPass123" }),
+        serde_json::json!({ "email": "   ", "password": "TestPass123" }),
+        serde_json::json!({ "email": "known@example.com", "password": "" }),
+        serde_json::json!({ "email": "unknown@example.com", "password": "" }),
+    ];
+    for credentials in cases {
+        let (status, body) = send_json(&app, "POST", "/auth/login", None, Some(credentials)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, serde_json::json!({ "error": "Validation Error", "message": "Email and password are required" }));
+    }
+}
+
+#[tokio::test]
+async fn test_request_schema() {
+    let app = create_test_app().await;
+
+    let (status, body) = send_json(&app, "GET", "/schema/create_post", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let schema = &body["data"];
+    let mut required: Vec<&str> = schema["required"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field.as_str().unwrap())
+        .collect();
+    required.sort();
+    assert_eq!(required, ["content", "title"]);
+    assert_eq!(schema["properties"]["title"]["type"], "string");
+
+    let (status, body) = send_json(&app, "GET", "/schema/unknown", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["data"].is_null());
+}
+
+#[tokio::test]
+async fn test_post_visibility_rules() {
+    let app = create_test_app().await;
+    let author = register_and_login(&app, "Author", "vis-author@example.com").await;
+    let other = register_and_login(&app, "Other", "vis-other@example.com").await;
+
+    let mut ids = std::collections::HashMap::new();
+    for visibility in ["public", "private", "unlisted"] {
+        let (status, body) = send_json(
+            &app,
+            "POST",
+            "/posts",
+            Some(&author),
+            Some(serde_json::json!({ "title": visibility, "content": "Visibility test.", "visibility": visibility, "status": "published" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["visibility"], visibility);
+        ids.insert(visibility, body["data"]["id"].as_str().unwrap().to_string());
+    }
+
+    let listed_titles = |body: &Value| {
+        let mut titles: Vec<String> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|post| post["title"].as_str().unwrap().to_string())
+            .collect();
+        titles.sort();
+        titles
+    };
+
+    // Listing: unlisted never shows up, private only for its author
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(listed_titles(&body), ["public"]);
+    let (_, body) = send_json(&app, "GET", "/posts", Some(&other), None).await;
+    assert_eq!(listed_titles(&body), ["public"]);
+    let (_, body) = send_json(&app, "GET", "/posts?expand=author", Some(&author), None).await;
+    assert_eq!(listed_titles(&body), ["private", "public"]);
+
+    // Direct reads: public and unlisted for anyone, private only for its author
+    let get = |visibility: &str, token: Option<&str>| {
+        let uri = format!("/posts/{}", ids[visibility]);
+        let app = app.clone();
+        let token = token.map(str::to_string);
+        async move {
+            let (status, body) = send_json(&app, "GET", &uri, token.as_deref(), None).await;
+            assert_eq!(status, StatusCode::OK);
+            !body["data"].is_null()
+        }
+    };
+    assert!(get("public", None).await);
+    assert!(get("unlisted", None).await);
+    assert!(get("unlisted", Some(&other)).await);
+    assert!(!get("private", None).await);
+    assert!(!get("private", Some(&other)).await);
+    assert!(get("private", Some(&author)).await);
+
+    // Making the private post public puts it in everyone's listing
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/posts/{}", ids["private"]),
+        Some(&author),
+        Some(serde_json::json!({ "visibility": "public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["visibility"], "public");
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(listed_titles(&body), ["private", "public"]);
+}
+
+#[tokio::test]
+async fn test_head_post() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Head User", "head@example.com").await;
+    let (_, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Headed", "content": "Check me without a body.", "status": "published" })),
+    )
+    .await;
+    let uri = format!("/posts/{}", body["data"]["id"].as_str().unwrap());
+
+    let get = app.clone().oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap()).await.unwrap();
+    let get_length = get.headers()["content-length"].clone();
+
+    let head = app
+        .clone()
+        .oneshot(Request::builder().method("HEAD").uri(&uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(head.status(), StatusCode::OK);
+    assert_eq!(head.headers()["content-type"], "application/json");
+    assert_eq!(head.headers()["content-length"], get_length);
+    let bytes = axum::body::to_bytes(head.into_body(), usize::MAX).await.unwrap();
+    assert!(bytes.is_empty());
+
+    let missing = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("HEAD")
+                .uri(format!("/posts/{}", uuid::Uuid::new_v4()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_structurally_abusive_json_is_rejected() {
+    let app = create_test_app_with(Settings {
+        max_json_depth: 8,
+        max_json_fields: 10,
+        ..Settings::default()
+    })
+    .await;
+
+    let nested = format!("{}{}", "[".repeat(64), "]".repeat(64));
+    let body = serde_json::json!({ "name": "Deep", "email": "deep@example.com", "password": serde_json::from_str::<Value>(&nested).unwrap() });
+    let (status, body) = send_json(&app, "POST", "/auth/register", None, Some(body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, serde_json::json!({ "error": "Bad Request", "message": "JSON body is nested deeper than 8 levels" }));
+
+    let mut wide = serde_json::Map::new();
+    for i in 0..20 {
+        wide.insert(format!("field{}", i), Value::from(i));
+    }
+    let (status, body) = send_json(&app, "POST", "/auth/login", None, Some(Value::Object(wide))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "JSON body has more than 10 fields");
+
+    // Brackets and colons inside strings don't count
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/auth/register",
+        None,
+        Some(serde_json::json!({ "name": "[[[[[[[[[[:::::::::::]", "email": "shallow@example.com", "password": "TestPass123" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_tag_counts() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Tagger", "tagger@example.com").await;
+
+    let posts = [
+        ("One", vec!["rust", "web"]),
+        ("Two", vec!["Rust", " axum ", "rust"]),
+        ("Three", vec!["rust", "web", "sqlite"]),
+    ];
+    for (title, tags) in posts {
+        let (status, body) = send_json(
+            &app,
+            "POST",
+            "/posts",
+            Some(&token),
+            Some(serde_json::json!({ "title": title, "content": "Tagged.", "tags": tags, "status": "published" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        if title == "Two" {
+            assert_eq!(body["data"]["tags"], serde_json::json!(["axum", "rust"]));
+        }
+    }
+    // Private posts don't contribute to the public counts
+    send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Hidden", "content": "Tagged.", "tags": ["web"], "visibility": "private", "status": "published" })),
+    )
+    .await;
+
+    let (status, body) = send_json(&app, "GET", "/tags", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["data"],
+        serde_json::json!([
+            { "tag": "rust", "count": 3 },
+            { "tag": "web", "count": 2 },
+            { "tag": "axum", "count": 1 },
+            { "tag": "sqlite", "count": 1 },
+        ])
+    );
+
+    let (_, body) = send_json(&app, "GET", "/tags?page=2&per_page=2", None, None).await;
+    assert_eq!(
+        body["data"],
+        serde_json::json!([{ "tag": "axum", "count": 1 }, { "tag": "sqlite", "count": 1 }])
+    );
+
+    let too_many: Vec<String> = (0..11).map(|i| format!("tag{}", i)).collect();
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Overtagged", "content": "Tagged.", "tags": too_many, "status": "published" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "A post can have at most 10 tags");
+
+    // Messy tags are stored in canonical form, and filtering normalizes the same way
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Messy", "content": "Tagged.", "tags": ["  Web   Dev ", "web-dev", "#Async!"], "status": "published" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["tags"], serde_json::json!(["async", "web-dev"]));
+    let (_, body) = send_json(&app, "GET", "/posts?tag=Web%20Dev", None, None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["title"], "Messy");
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Symbols", "content": "Tagged.", "tags": ["rust", "#!?"], "status": "published" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Tag \"#!?\" has no letters or digits");
+}
+
+#[tokio::test]
+async fn test_max_posts_per_user() {
+    let create = |app: Router, token: String, title: String| async move {
+        send_json(
+            &app,
+            "POST",
+            "/posts",
+            Some(&token),
+            Some(serde_json::json!({ "title": title, "content": "Counting." })),
+        )
+        .await
+    };
+
+    // Unlimited by default
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Prolific", "prolific@example.com").await;
+    for i in 0..5 {
+        let (status, _) = create(app.clone(), token.clone(), format!("Post {}", i)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let app = create_test_app_with(Settings {
+        max_posts_per_user: Some(2),
+        ..Settings::default()
+    })
+    .await;
+    let token = register_and_login(&app, "Capped", "capped@example.com").await;
+    let other = register_and_login(&app, "Other", "capped-other@example.com").await;
+    for i in 0..2 {
+        let (status, _) = create(app.clone(), token.clone(), format!("Post {}", i)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, body) = create(app.clone(), token.clone(), "One too many".to_string()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body, serde_json::json!({ "error": "Forbidden", "message": "You have reached the limit of 2 posts" }));
+
+    // The cap is per user
+    let (status, _) = create(app.clone(), other, "Still fine".to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_deleting_user_removes_their_posts() {
+    for mode in [PostDeletion::Soft, PostDeletion::Hard] {
+        let app = create_test_app_with(Settings {
+            user_post_deletion: mode,
+            ..Settings::default()
+        })
+        .await;
+        let leaving = register_and_login(&app, "Leaving", "leaving@example.com").await;
+        let staying = register_and_login(&app, "Staying", "staying@example.com").await;
+
+        let mut leaving_post = String::new();
+        let mut staying_post = String::new();
+        for (token, title) in [(&leaving, "Goodbye"), (&staying, "Still here")] {
+            let (_, body) = send_json(
+                &app,
+                "POST",
+                "/posts",
+                Some(token),
+                Some(serde_json::json!({ "title": title, "content": "Before deletion.", "tags": ["farewell"], "status": "published" })),
+            )
+            .await;
+            let id = body["data"]["id"].as_str().unwrap().to_string();
+            if title == "Goodbye" {
+                leaving_post = id;
+            } else {
+                staying_post = id;
+            }
+        }
+        send_json(&app, "POST", &format!("/posts/{}/like", staying_post), Some(&leaving), None).await;
+        send_json(&app, "POST", &format!("/posts/{}/comments", staying_post), Some(&leaving), Some(serde_json::json!({ "content": "Bye" }))).await;
+
+        let (status, _) = send_json(&app, "DELETE", "/auth/profile", Some(&leaving), None).await;
+        assert_eq!(status, StatusCode::OK, "{:?}", mode);
+
+        // Nothing the deleted account left on other posts still shows
+        let (_, body) = send_json(&app, "GET", &format!("/posts/{}/full", staying_post), None, None).await;
+        assert_eq!(body["data"]["like_count"], 0, "{:?}", mode);
+        assert_eq!(body["data"]["comment_count"], 0, "{:?}", mode);
+        assert_eq!(body["data"]["comments"], serde_json::json!([]), "{:?}", mode);
+
+        for uri in ["/posts", "/posts?expand=author"] {
+            let (_, body) = send_json(&app, "GET", uri, None, None).await;
+            let titles: Vec<&str> = body["data"].as_array().unwrap().iter().map(|post| post["title"].as_str().unwrap()).collect();
+            assert_eq!(titles, ["Still here"], "{:?}", mode);
+        }
+        let (_, body) = send_json(&app, "GET", &format!("/posts/{}", leaving_post), None, None).await;
+        assert!(body["data"].is_null(), "{:?}", mode);
+        let (_, body) = send_json(&app, "GET", "/tags", None, None).await;
+        assert_eq!(body["data"], serde_json::json!([{ "tag": "farewell", "count": 1 }]), "{:?}", mode);
+
+        // The token now points at a missing account, so it can't write anything either, and the email and username are free again
+        let (status, body) = send_json(&app, "GET", "/auth/profile", Some(&leaving), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["message"], "The account for this token no longer exists");
+        let (status, _) = send_json(&app, "POST", "/posts", Some(&leaving), Some(serde_json::json!({ "title": "Ghost", "content": "Still here?" }))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", mode);
+        let (status, _) = send_json(&app, "POST", &format!("/posts/{}/like", staying_post), Some(&leaving), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", mode);
+        let returning = register_and_login(&app, "Returning", "leaving@example.com").await;
+        let (_, body) = send_json(&app, "GET", "/auth/profile", Some(&returning), None).await;
+        assert_eq!(body["data"]["username"], "leaving", "{:?}", mode);
+
+        // Deleting a tagged post directly clears its tags too
+        let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+        let uri = format!("/posts/{}", body["data"][0]["id"].as_str().unwrap());
+        let (status, _) = send_json(&app, "DELETE", &uri, Some(&staying), None).await;
+        assert_eq!(status, StatusCode::OK, "{:?}", mode);
+        let (_, body) = send_json(&app, "GET", "/tags", None, None).await;
+        assert_eq!(body["data"], serde_json::json!([]), "{:?}", mode);
+    }
+}
+
+#[tokio::test]
+async fn test_profile_shows_email_verification() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Verifier", "verifier@example.com").await;
+
+    let (_, body) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+    assert_eq!(body["data"]["verified"], false);
+    let user_id = uuid::Uuid::parse_str(body["data"]["id"].as_str().unwrap()).unwrap();
+
+    // Access tokens and tokens for another address don't verify anything
+    let stale = api_rustone::helpers::auth::AuthHelper::generate_email_verification_token(user_id, "old@example.com", &Settings::default()).unwrap();
+    for bad in [token.as_str(), stale.as_str()] {
+        let (status, body) = send_json(&app, "POST", "/auth/verify-email", None, Some(serde_json::json!({ "token": bad }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["message"], "Invalid or expired verification token");
+    }
+
+    let verification = api_rustone::helpers::auth::AuthHelper::generate_email_verification_token(user_id, "verifier@example.com", &Settings::default()).unwrap();
+    // ...and a verification token is no access token
+    let (status, _) = send_json(&app, "GET", "/auth/profile", Some(&verification), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send_json(&app, "POST", "/auth/verify-email", None, Some(serde_json::json!({ "token": verification }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+    assert_eq!(body["data"]["verified"], true);
+
+    // Changing the address needs a fresh verification
+    let (_, body) = send_json(
+        &app,
+        "PUT",
+        "/auth/profile",
+        Some(&token),
+        Some(serde_json::json!({ "email": "verifier-new@example.com" })),
+    )
+    .await;
+    assert_eq!(body["data"]["verified"], false);
+}
+
+#[tokio::test]
+async fn test_cors_max_age_and_exposed_headers() {
+    let app = create_test_app_with(Settings {
+        cors_max_age: Some(std::time::Duration::from_secs(1200)),
+        cors_expose_headers: vec!["X-Request-Id".to_string(), "ETag".to_string()],
+        ..Settings::default()
+    })
+    .await;
+
+    let preflight = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("OPTIONS")
+                .uri("/posts")
+                .header("origin", "https://client.example.com")
+                .header("access-control-request-method", "POST")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(preflight.status(), StatusCode::OK);
+    assert_eq!(preflight.headers()["access-control-max-age"], "1200");
+
+    // Exposed headers are sent on the actual cross-origin response
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/posts")
+                .header("origin", "https://client.example.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["access-control-expose-headers"], "x-request-id,etag");
+}
+
+#[tokio::test]
+async fn test_admin_ndjson_export() {
+    let app = create_test_app_with(Settings {
+        admin_emails: vec!["admin@example.com".to_string()],
+        ..Settings::default()
+    })
+    .await;
+    let admin = register_and_login(&app, "Admin", "admin@example.com").await;
+    let author = register_and_login(&app, "Author", "export-author@example.com").await;
+
+    for (title, visibility) in [("First", "public"), ("Second", "private"), ("Third", "unlisted")] {
+        send_json(
+            &app,
+            "POST",
+            "/posts",
+            Some(&author),
+            Some(serde_json::json!({ "title": title, "content": "Exported.", "visibility": visibility })),
+        )
+        .await;
+    }
+
+    let (status, _) = send_json(&app, "GET", "/admin/export/posts.ndjson", Some(&author), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/admin/export/posts.ndjson")
+                .header("authorization", format!("Bearer {}", admin))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let titles: Vec<String> = text
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap()["title"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(titles, ["First", "Second", "Third"]);
+}
+
+#[tokio::test]
+async fn test_empty_updates_are_rejected() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Noop", "noop@example.com").await;
+    let (_, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Untouched", "content": "Stays as is.", "status": "published" })),
+    )
+    .await;
+    let uri = format!("/posts/{}", body["data"]["id"].as_str().unwrap());
+    let updated_at = body["data"]["updated_at"].clone();
+    let expected = serde_json::json!({ "error": "Bad Request", "message": "No fields to update" });
+
+    for (uri, null_field) in [(uri.as_str(), "title"), ("/auth/profile", "name")] {
+        for empty in [serde_json::json!({}), serde_json::json!({ null_field: null })] {
+            let (status, body) = send_json(&app, "PUT", uri, Some(&token), Some(empty)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body, expected);
+        }
+    }
+
+    let (_, body) = send_json(&app, "GET", &uri, None, None).await;
+    assert_eq!(body["data"]["updated_at"], updated_at);
+}
+
+#[tokio::test]
+async fn test_admin_sets_user_roles() {
+    let app = create_test_app_with(Settings {
+        admin_emails: vec!["admin@example.com".to_string()],
+        ..Settings::default()
+    })
+    .await;
+    let admin = register_and_login(&app, "Admin", "admin@example.com").await;
+    let member = register_and_login(&app, "Member", "member@example.com").await;
+    let (_, profile) = send_json(&app, "GET", "/auth/profile", Some(&admin), None).await;
+    let admin_uri = format!("/admin/users/{}/role", profile["data"]["id"].as_str().unwrap());
+    let (_, profile) = send_json(&app, "GET", "/auth/profile", Some(&member), None).await;
+    let member_uri = format!("/admin/users/{}/role", profile["data"]["id"].as_str().unwrap());
+    let to_admin = serde_json::json!({ "role": "admin" });
+    let to_user = serde_json::json!({ "role": "user" });
+
+    // Regular users can't change roles, not even their own
+    let (status, _) = send_json(&app, "PUT", &member_uri, Some(&member), Some(to_admin.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // The only admin can't demote themselves
+    let (status, body) = send_json(&app, "PUT", &admin_uri, Some(&admin), Some(to_user.clone())).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["message"], "Cannot demote the last remaining admin");
+
+    // Promotion takes effect from the next login
+    let (status, body) = send_json(&app, "PUT", &member_uri, Some(&admin), Some(to_admin)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["role"], "admin");
+    assert_eq!(body["data"]["email"], "member@example.com");
+    let member = login(&app, "member@example.com").await;
+
+    // With a second admin around, demotion is allowed
+    let (status, body) = send_json(&app, "PUT", &admin_uri, Some(&member), Some(to_user.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["role"], "user");
+    let admin = login(&app, "admin@example.com").await;
+    let (status, _) = send_json(&app, "PUT", &member_uri, Some(&admin), Some(to_user.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // ...until it would leave nobody in charge
+    let (status, _) = send_json(&app, "PUT", &member_uri, Some(&member), Some(to_user)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let missing = format!("/admin/users/{}/role", uuid::Uuid::new_v4());
+    let (_, body) = send_json(&app, "PUT", &missing, Some(&member), Some(serde_json::json!({ "role": "user" }))).await;
+    assert_eq!(body["message"], "User not found");
+}
+
+#[tokio::test]
+async fn test_timestamp_format_setting() {
+    for (format, expect_number) in [(TimestampFormat::Rfc3339, false), (TimestampFormat::EpochMillis, true)] {
+        let app = create_test_app_with(Settings { timestamp_format: format, ..Settings::default() }).await;
+        let token = register_and_login(&app, "Clock", "clock@example.com").await;
+        let (_, created) = send_json(
+            &app,
+            "POST",
+            "/posts",
+            Some(&token),
+            Some(serde_json::json!({ "title": "Timed", "content": "When was this?", "status": "published" })),
+        )
+        .await;
+        let (_, profile) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+        let (_, listed) = send_json(&app, "GET", "/posts", None, None).await;
+
+        for value in [&created["data"], &created["data"]["author"], &profile["data"], &listed["data"][0]] {
+            for field in ["created_at", "updated_at"] {
+                assert_eq!(value[field].is_i64(), expect_number, "{:?} {} in {}", format, field, value);
+                assert_eq!(value[field].is_string(), !expect_number, "{:?} {} in {}", format, field, value);
+            }
+        }
+
+        // Both forms decode to the same instant
+        let post: PostResponse = serde_json::from_value(created["data"].clone()).unwrap();
+        if expect_number {
+            assert_eq!(created["data"]["created_at"], post.created_at.timestamp_millis());
+        } else {
+            assert_eq!(created["data"]["created_at"], post.created_at.to_rfc3339());
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_duplicate_post() {
+    let app = create_test_app().await;
+    let author = register_and_login(&app, "Author", "author@example.com").await;
+    let forker = register_and_login(&app, "Forker", "forker@example.com").await;
+    let (_, profile) = send_json(&app, "GET", "/auth/profile", Some(&forker), None).await;
+    let forker_id = profile["data"]["id"].clone();
+
+    let (_, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&author),
+        Some(serde_json::json!({ "title": "Template", "content": "Fill me in.", "tags": ["draft"], "status": "published" })),
+    )
+    .await;
+    let original_id = body["data"]["id"].as_str().unwrap().to_string();
+    let uri = format!("/posts/{}/duplicate", original_id);
+
+    // Own post
+    let (status, body) = send_json(&app, "POST", &uri, Some(&author), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["title"], "Template (copy)");
+    assert_eq!(body["data"]["content"], "Fill me in.");
+    assert_eq!(body["data"]["tags"], serde_json::json!(["draft"]));
+    assert_ne!(body["data"]["id"], original_id);
+
+    // Someone else's public post becomes the caller's
+    let (status, body) = send_json(&app, "POST", &uri, Some(&forker), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["title"], "Template (copy)");
+    assert_eq!(body["data"]["author"]["id"], forker_id);
+
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}", original_id), None, None).await;
+    assert_eq!(body["data"]["title"], "Template");
+
+    let (status, _) = send_json(&app, "POST", &uri, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Private posts of others can't be copied
+    let (_, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&author),
+        Some(serde_json::json!({ "title": "Secret", "content": "Mine only.", "visibility": "private", "status": "published" })),
+    )
+    .await;
+    let uri = format!("/posts/{}/duplicate", body["data"]["id"].as_str().unwrap());
+    let (_, body) = send_json(&app, "POST", &uri, Some(&forker), None).await;
+    assert_eq!(body["message"], "Post not found");
+    assert!(body["data"].is_null());
+}
+
+#[tokio::test]
+async fn test_quiet_list_responses() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Quiet", "quiet@example.com").await;
+    send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Hush", "content": "Machines don't read messages.", "tags": ["bots"], "status": "published" })),
+    )
+    .await;
+
+    for uri in ["/posts", "/posts/my", "/tags"] {
+        let (_, loud) = send_json(&app, "GET", uri, Some(&token), None).await;
+        assert!(loud["message"].as_str().unwrap().starts_with("Retrieved 1"), "{}", uri);
+
+        let (status, quiet) = send_json(&app, "GET", &format!("{}?quiet=true", uri), Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(quiet.get("message").is_none(), "{}", uri);
+        assert_eq!(quiet["data"], loud["data"], "{}", uri);
+    }
+
+    let (_, body) = send_json(&app, "GET", "/posts?quiet=false", None, None).await;
+    assert_eq!(body["message"], "Retrieved 1 posts");
+}
+
+#[tokio::test]
+async fn test_method_not_allowed_lists_allowed_methods() {
+    let app = create_test_app().await;
+
+    for (method, uri, allow) in [
+        ("GET", "/auth/login", "POST"),
+        ("PATCH", "/tags", "GET,HEAD"),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().method(method).uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()["allow"], allow);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"], "Method Not Allowed");
+        assert_eq!(body["message"], "Method not allowed for this resource");
+    }
+}
+
+#[tokio::test]
+async fn test_admin_actions_are_audited() {
+    let app = create_test_app_with(Settings {
+        admin_emails: vec!["admin@example.com".to_string()],
+        ..Settings::default()
+    })
+    .await;
+    let admin = register_and_login(&app, "Admin", "admin@example.com").await;
+    let author = register_and_login(&app, "Author", "author@example.com").await;
+    let (_, profile) = send_json(&app, "GET", "/auth/profile", Some(&admin), None).await;
+    let admin_id = profile["data"]["id"].clone();
+    let (_, profile) = send_json(&app, "GET", "/auth/profile", Some(&author), None).await;
+    let author_id = profile["data"]["id"].clone();
+
+    let (_, body) = send_json(&app, "GET", "/admin/audit", Some(&admin), None).await;
+    assert_eq!(body["data"], serde_json::json!([]));
+
+    let (_, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&author),
+        Some(serde_json::json!({ "title": "Spam", "content": "Buy now." })),
+    )
+    .await;
+    let post_id = body["data"]["id"].clone();
+
+    // Missing posts don't leave an entry
+    let missing = format!("/admin/posts/{}", uuid::Uuid::new_v4());
+    send_json(&app, "DELETE", &missing, Some(&admin), None).await;
+
+    let uri = format!("/admin/posts/{}", post_id.as_str().unwrap());
+    let (status, _) = send_json(&app, "DELETE", &uri, Some(&admin), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let uri = format!("/admin/users/{}/role", author_id.as_str().unwrap());
+    send_json(&app, "PUT", &uri, Some(&admin), Some(serde_json::json!({ "role": "admin" }))).await;
+
+    let (status, body) = send_json(&app, "GET", "/admin/audit", Some(&admin), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let entries = body["data"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["action"], "user_role_changed");
+    assert_eq!(entries[0]["target_id"], author_id);
+    assert_eq!(entries[1]["action"], "post_force_deleted");
+    assert_eq!(entries[1]["actor_id"], admin_id);
+    assert_eq!(entries[1]["target_id"], post_id);
+    assert!(entries[1]["created_at"].is_string());
+
+    let (_, body) = send_json(&app, "GET", "/admin/audit?per_page=1&page=2", Some(&admin), None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["action"], "post_force_deleted");
+
+    // Still the author's original token, minted with the user role
+    let (status, _) = send_json(&app, "GET", "/admin/audit", Some(&author), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_legacy_bcrypt_hash_upgraded_on_login() {
+    let sql_db = SqlDatabase::new("sqlite::memory:").await.unwrap();
+    let pool = Arc::new(sql_db.get_pool().clone());
+    let repo = SqlUserRepository::new((*pool).clone());
+    let legacy = AuthHelper::hash_password("TestPass123", PasswordAlgorithm::Bcrypt).unwrap();
+    repo.create_user(
+        CreateUserRequest {
+            name: "Legacy".to_string(),
+            email: "legacy@example.com".to_string(),
+            password: "TestPass123".to_string(),
+            username: None,
+        },
+        legacy.clone(),
+        Role::User,
+    )
+    .await
+    .unwrap();
+
+    let app = build_app(pool, Settings { password_hasher: PasswordAlgorithm::Argon2, ..Settings::default() });
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/auth/login",
+        None,
+        Some(serde_json::json!({ "email": "legacy@example.com", "password": "wrong" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(repo.find_by_email("legacy@example.com").await.unwrap().unwrap().password, legacy);
+
+    login(&app, "legacy@example.com").await;
+    let upgraded = repo.find_by_email("legacy@example.com").await.unwrap().unwrap().password;
+    assert!(upgraded.starts_with("$argon2"), "{}", upgraded);
+
+    // The upgraded hash keeps working
+    login(&app, "legacy@example.com").await;
+    assert_eq!(repo.find_by_email("legacy@example.com").await.unwrap().unwrap().password, upgraded);
+}
+
+#[tokio::test]
+async fn test_get_all_users_pages_and_clamps() {
+    let sql_db = SqlDatabase::new("sqlite::memory:").await.unwrap();
+    let repo = SqlUserRepository::new(sql_db.get_pool().clone());
+    let total = MAX_USERS_PER_PAGE as usize + 5;
+    for i in 0..total {
+        let user = CreateUserRequest {
+            name: format!("User {}", i),
+            email: format!("user{}@example.com", i),
+            password: String::new(),
+            username: None,
+        };
+        repo.create_user(user, "not-a-real-hash".to_string(), Role::User).await.unwrap();
+    }
+
+    let first = repo.get_all_users(3, 0).await.unwrap();
+    let second = repo.get_all_users(3, 3).await.unwrap();
+    assert_eq!(first.len(), 3);
+    assert_eq!(second.len(), 3);
+    assert!(first.iter().all(|user| second.iter().all(|other| other.id != user.id)));
+    assert_eq!(repo.get_all_users(10, total as u32 - 2).await.unwrap().len(), 2);
+
+    // Oversized and zero limits are clamped rather than honoured
+    assert_eq!(repo.get_all_users(u32::MAX, 0).await.unwrap().len(), MAX_USERS_PER_PAGE as usize);
+    assert_eq!(repo.get_all_users(0, 0).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_error_catalog() {
+    let app = create_test_app().await;
+
+    let (status, body) = send_json(&app, "GET", "/errors", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let catalog = body["data"].as_array().unwrap();
+    let entry = |code: &str| catalog.iter().find(|entry| entry["code"] == code).cloned().unwrap();
+
+    for (code, error, status) in [
+        ("Conflict", "Conflict", 409),
+        ("Unauthorized", "Unauthorized", 401),
+        ("NotFound", "Not Found", 404),
+        ("RequestHeaderFieldsTooLarge", "Request Header Fields Too Large", 431),
+    ] {
+        let entry = entry(code);
+        assert_eq!(entry["error"], error);
+        assert_eq!(entry["status"], status);
+        assert!(!entry["description"].as_str().unwrap().is_empty());
+    }
+
+    // Storage failures keep their legacy status, and the catalog says so
+    assert_eq!(entry("DatabaseError")["status"], 400);
+    assert!(entry("DatabaseError")["description"].as_str().unwrap().contains("legacy"));
+
+    // A missing random post is reported the way the catalog describes
+    let (status, body) = send_json(&app, "GET", "/posts/random", None, None).await;
+    assert_eq!(status.as_u16(), entry("NotFound")["status"]);
+    assert_eq!(body["error"], entry("NotFound")["error"]);
+
+    // Duplicate registration is reported the way the catalog describes
+    register_and_login(&app, "Taken", "taken@example.com").await;
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/auth/register",
+        None,
+        Some(serde_json::json!({ "name": "Again", "email": "taken@example.com", "password": "TestPass123" })),
+    )
+    .await;
+    assert_eq!(status.as_u16(), entry("Conflict")["status"]);
+    assert_eq!(body["error"], entry("Conflict")["error"]);
+}
+
+#[tokio::test]
+async fn test_trailing_slash_policy() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Slash", "slash@example.com").await;
+    let (status, created) = send_json(
+        &app,
+        "POST",
+        "/posts/",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Slashed", "content": "Created via /posts/.", "status": "published" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Both forms reach the same handler, query string included
+    let (_, plain) = send_json(&app, "GET", "/posts?quiet=true", None, None).await;
+    let (status, slashed) = send_json(&app, "GET", "/posts/?quiet=true", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(slashed, plain);
+    assert_eq!(slashed["data"][0]["id"], created["data"]["id"]);
+    let (_, body) = send_json(&app, "GET", "/auth/profile/", Some(&token), None).await;
+    assert_eq!(body["data"]["email"], "slash@example.com");
+
+    let app = create_test_app_with(Settings { trailing_slash: TrailingSlash::Redirect, ..Settings::default() }).await;
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri("/posts/?page=2").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.headers()["location"], "/posts?page=2");
+
+    let app = create_test_app_with(Settings { trailing_slash: TrailingSlash::Strict, ..Settings::default() }).await;
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri("/posts/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_comment_paging_and_since_filter() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Chatty", "chatty@example.com").await;
+    let (_, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Discuss", "content": "Thoughts?", "status": "published" })),
+    )
+    .await;
+    let uri = format!("/posts/{}/comments", body["data"]["id"].as_str().unwrap());
+
+    let mut created = Vec::new();
+    for i in 0..5 {
+        let (status, body) = send_json(&app, "POST", &uri, Some(&token), Some(serde_json::json!({ "content": format!("Comment {}", i) }))).await;
+        assert_eq!(status, StatusCode::OK);
+        created.push(body["data"].clone());
+    }
+    let (status, _) = send_json(&app, "POST", &uri, Some(&token), Some(serde_json::json!({ "content": "  " }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let contents = |body: &Value| -> Vec<String> {
+        body["data"].as_array().unwrap().iter().map(|c| c["content"].as_str().unwrap().to_string()).collect()
+    };
+
+    // Oldest first by default
+    let (_, body) = send_json(&app, "GET", &uri, None, None).await;
+    assert_eq!(contents(&body), ["Comment 0", "Comment 1", "Comment 2", "Comment 3", "Comment 4"]);
+
+    let (_, body) = send_json(&app, "GET", &format!("{}?limit=2&offset=2", uri), None, None).await;
+    assert_eq!(contents(&body), ["Comment 2", "Comment 3"]);
+
+    let (_, body) = send_json(&app, "GET", &format!("{}?order=newest&limit=2", uri), None, None).await;
+    assert_eq!(contents(&body), ["Comment 4", "Comment 3"]);
+
+    // Only comments strictly newer than the cursor
+    let since = created[2]["created_at"].as_str().unwrap().replace('+', "%2B");
+    let (_, body) = send_json(&app, "GET", &format!("{}?since={}", uri, since), None, None).await;
+    assert_eq!(contents(&body), ["Comment 3", "Comment 4"]);
+
+    let (_, body) = send_json(&app, "GET", &format!("{}?since={}&order=newest&limit=1", uri, since), None, None).await;
+    assert_eq!(contents(&body), ["Comment 4"]);
+
+    let missing = format!("/posts/{}/comments", uuid::Uuid::new_v4());
+    let (_, body) = send_json(&app, "GET", &missing, None, None).await;
+    assert_eq!(body["message"], "Post not found");
+}
+
+#[tokio::test]
+async fn test_comment_count() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Counter", "counter@example.com").await;
+    let (_, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Countable", "content": "How many?", "status": "published" })),
+    )
+    .await;
+    let post_id = body["data"]["id"].as_str().unwrap().to_string();
+    let count_uri = format!("/posts/{}/comments/count", post_id);
+
+    let (status, body) = send_json(&app, "GET", &count_uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["count"], 0);
+
+    for i in 0..3 {
+        let (status, _) = send_json(
+            &app,
+            "POST",
+            &format!("/posts/{}/comments", post_id),
+            Some(&token),
+            Some(serde_json::json!({ "content": format!("Comment {}", i) })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let (status, body) = send_json(&app, "GET", &count_uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["post_id"], post_id.as_str());
+    assert_eq!(body["data"]["count"], 3);
+    assert_eq!(body["message"], "Post has 3 comments");
+
+    let missing = format!("/posts/{}/comments/count", uuid::Uuid::new_v4());
+    let (status, body) = send_json(&app, "GET", &missing, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["message"], "Post not found");
+}
+
+#[tokio::test]
+async fn test_tokens_for_another_audience_are_rejected() {
+    let app = create_test_app_with(Settings {
+        jwt_issuer: "auth.example.com".to_string(),
+        jwt_audience: "blog-api".to_string(),
+        ..Settings::default()
+    })
+    .await;
+    let token = register_and_login(&app, "Scoped", "scoped@example.com").await;
+    let (status, profile) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let user_id = uuid::Uuid::parse_str(profile["data"]["id"].as_str().unwrap()).unwrap();
+
+    // Same signing key and user, but minted for a different audience or by a different issuer
+    for settings in [
+        Settings { jwt_issuer: "auth.example.com".to_string(), jwt_audience: "billing-api".to_string(), ..Settings::default() },
+        Settings { jwt_issuer: "evil.example.com".to_string(), jwt_audience: "blog-api".to_string(), ..Settings::default() },
+    ] {
+        let foreign = AuthHelper::generate_token(user_id, Role::User, &settings).unwrap();
+        let (status, body) = send_json(&app, "GET", "/auth/profile", Some(&foreign), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["message"], "Invalid token");
+    }
+}
+
+#[tokio::test]
+async fn test_request_bodies_reject_unknown_and_missing_fields() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Strict", "strict@example.com").await;
+
+    async fn rejection(app: &Router, method: &str, uri: &str, token: Option<&str>, body: Value) -> (StatusCode, String) {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        if let Some(token) = token {
+            builder = builder.header("authorization", format!("Bearer {}", token));
+        }
+        let response = app.clone().oneshot(builder.body(Body::from(body.to_string())).unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    // A typo is reported instead of being silently dropped
+    let (status, text) = rejection(
+        &app,
+        "POST",
+        "/auth/register",
+        None,
+        serde_json::json!({ "name": "Typo", "email": "typo@example.com", "password": "TestPass123", "passwrd": "x" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(text.contains("unknown field `passwrd`"), "{}", text);
+
+    let (status, text) = rejection(&app, "POST", "/auth/login", None, serde_json::json!({ "email": "strict@example.com" })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(text.contains("missing field `password`"), "{}", text);
+
+    let (status, text) = rejection(&app, "POST", "/posts", Some(&token), serde_json::json!({ "title": "T", "content": "C", "tag": ["x"] })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(text.contains("unknown field `tag`"), "{}", text);
+
+    let (status, text) = rejection(&app, "PUT", "/auth/profile", Some(&token), serde_json::json!({ "nmae": "Renamed" })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(text.contains("unknown field `nmae`"), "{}", text);
+
+    let (status, text) = rejection(&app, "POST", "/posts", Some(&token), serde_json::json!({ "title": 7, "content": "C" })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(text.contains("invalid type"), "{}", text);
+
+    // Optional fields may still be left out
+    let (status, _) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "T", "content": "C" }))).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_empty_request_bodies_get_the_error_envelope() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Empty", "empty@example.com").await;
+
+    // No body and no content type at all
+    let response = app
+        .clone()
+        .oneshot(Request::builder().method("POST").uri("/auth/register").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["error"], "Bad Request");
+    assert_eq!(body["message"], "Request body is required");
+
+    // Declared as JSON but empty
+    for (method, uri, token) in [("POST", "/auth/login", None), ("POST", "/posts", Some(token.as_str())), ("PUT", "/auth/profile", Some(token.as_str()))] {
+        let (status, body) = send_json(&app, method, uri, token, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{} {}", method, uri);
+        assert_eq!(body["message"], "Request body is required", "{} {}", method, uri);
+    }
+
+    // Malformed JSON is still a parse error rather than a missing body
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/auth/login")
+                .header("content-type", "application/json")
+                .body(Body::from("{"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(!String::from_utf8_lossy(&bytes).contains("Request body is required"));
+}
+
+#[tokio::test]
+async fn test_reads_and_writes_with_separate_read_pool() {
+    // Two pools only share data through a file; in-memory databases are per connection pool
+    let path = std::env::temp_dir().join(format!("api_rustone_replica_{}.db", uuid::Uuid::new_v4()));
+    let url = format!("sqlite:{}?mode=rwc", path.display());
+    let sql_db = SqlDatabase::new(&url).await.unwrap().with_read_replica(&url).await.unwrap();
+    let app = build_app_with_read_pool(
+        Arc::new(sql_db.get_pool().clone()),
+        Arc::new(sql_db.get_read_pool().clone()),
+        Settings::default(),
+    );
+
+    let token = register_and_login(&app, "Replica", "replica@example.com").await;
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Replicated", "content": "Read me back", "tags": ["db"], "status": "published" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}", id), None, None).await;
+    assert_eq!(body["data"]["title"], "Replicated");
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    let (_, body) = send_json(&app, "GET", "/tags", None, None).await;
+    assert_eq!(body["data"][0]["tag"], "db");
+
+    let (status, _) = send_json(&app, "PUT", &format!("/posts/{}", id), Some(&token), Some(serde_json::json!({ "title": "Edited" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}", id), None, None).await;
+    assert_eq!(body["data"]["title"], "Edited");
+
+    drop(app);
+    sql_db.get_pool().close().await;
+    sql_db.get_read_pool().close().await;
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_post_reading_time() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Reader", "reader@example.com").await;
+
+    // Anything under a minute still reads as one
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Short", "content": "Just a few words", "status": "published" }))).await;
+    assert_eq!(body["data"]["reading_time_minutes"], 1);
+
+    // 401 words at the default 200 wpm round up to 3 minutes
+    let long = vec!["word"; 401].join(" ");
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Long", "content": long, "status": "published" }))).await;
+    assert_eq!(body["data"]["reading_time_minutes"], 3);
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}?expand=author", id), None, None).await;
+    assert_eq!(body["data"]["reading_time_minutes"], 3);
+
+    let slow = create_test_app_with(Settings { reading_wpm: 100, ..Settings::default() }).await;
+    let token = register_and_login(&slow, "Reader", "reader@example.com").await;
+    let (_, body) = send_json(&slow, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Long", "content": long, "status": "published" }))).await;
+    assert_eq!(body["data"]["reading_time_minutes"], 5);
+}
+
+#[tokio::test]
+async fn test_email_availability() {
+    let app = create_test_app_with(Settings { email_check_rate_limit: 3, ..Settings::default() }).await;
+    register_and_login(&app, "Taken", "taken@example.com").await;
+
+    let (status, body) = send_json(&app, "GET", "/auth/email-available?email=free@example.com", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["available"], true);
+
+    let (_, body) = send_json(&app, "GET", "/auth/email-available?email=taken@example.com", None, None).await;
+    assert_eq!(body["data"]["available"], false);
+
+    let (status, body) = send_json(&app, "GET", "/auth/email-available?email=not-an-email", None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "Validation Error");
+
+    // The limit counts every check from the same client, valid or not
+    let (status, body) = send_json(&app, "GET", "/auth/email-available?email=free@example.com", None, None).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["error"], "Too Many Requests");
+}
+
+#[test]
+fn test_settings_report_every_config_problem() {
+    let vars = std::collections::HashMap::from([
+        ("PORT", "eighty"),
+        ("DATABASE_URL", "sqlite::memory:"),
+        ("DEFAULT_LANGUAGE", "fr"),
+        ("REQUEST_LOG_LEVEL", "loud"),
+    ]);
+    let err = Settings::from_lookup(|name| vars.get(name).map(|value| value.to_string())).unwrap_err();
+
+    assert_eq!(err.problems.len(), 4, "{}", err);
+    let message = err.to_string();
+    for name in ["PORT", "JWT_SECRET", "DEFAULT_LANGUAGE", "REQUEST_LOG_LEVEL"] {
+        assert!(message.contains(name), "{}", message);
+    }
+
+    let vars = std::collections::HashMap::from([
+        ("PORT", "9000"),
+        ("JWT_SECRET", "s3cret"),
+        ("DEFAULT_LANGUAGE", "es-MX"),
+        ("REQUEST_LOG_LEVEL", "debug"),
+    ]);
+    let settings = Settings::from_lookup(|name| vars.get(name).map(|value| value.to_string())).unwrap();
+    assert_eq!(settings.port, 9000);
+    assert_eq!(settings.jwt_secret, "s3cret");
+    assert_eq!(settings.default_language, Lang::Es);
+    assert_eq!(settings.request_log_level, tracing::Level::DEBUG);
+}
+
+#[tokio::test]
+async fn test_disabled_features_are_not_mounted() {
+    let app = create_test_app_with(Settings {
+        features: Features { comments: false, likes: false, ..Features::default() },
+        ..Settings::default()
+    })
+    .await;
+    let token = register_and_login(&app, "Lean", "lean@example.com").await;
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Quiet", "content": "c", "status": "published" }))).await;
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+
+    let (status, _) = send_json(&app, "POST", &format!("/posts/{}/comments", id), Some(&token), Some(serde_json::json!({ "content": "Hi" }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send_json(&app, "GET", &format!("/posts/{}/comments", id), None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send_json(&app, "POST", &format!("/posts/{}/like", id), Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The rest of the post routes are unaffected
+    let (status, body) = send_json(&app, "GET", &format!("/posts/{}", id), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["title"], "Quiet");
+
+    let app = create_test_app_with(Settings {
+        features: Features { registration: false, ..Features::default() },
+        ..Settings::default()
+    })
+    .await;
+    let (status, _) = send_json(&app, "POST", "/auth/register", None, Some(serde_json::json!({ "name": "New", "email": "new@example.com", "password": "TestPass123" }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send_json(&app, "POST", "/auth/login", None, Some(serde_json::json!({ "email": "new@example.com", "password": "TestPass123" }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let vars = std::collections::HashMap::from([("JWT_SECRET", "s3cret"), ("FEATURE_COMMENTS", "off")]);
+    let settings = Settings::from_lookup(|name| vars.get(name).map(|value| value.to_string())).unwrap();
+    assert_eq!(settings.features, Features { comments: false, ..Features::default() });
+}
+
+#[tokio::test]
+async fn test_post_raw_content() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Markdown", "markdown@example.com").await;
+    let content = "# Heading\n\nSome *markdown* with {\"json\": true} inside.";
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Raw", "content": content, "status": "published" }))).await;
+    let uri = format!("/posts/{}/raw", body["data"]["id"].as_str().unwrap());
+
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/markdown; charset=utf-8");
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(bytes, content.as_bytes());
+
+    let (status, body) = send_json(&app, "GET", &format!("/posts/{}/raw", uuid::Uuid::new_v4()), None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "Not Found");
+}
+
+#[tokio::test]
+async fn test_posts_feed_cache_is_invalidated_by_writes() {
+    let sql_db = SqlDatabase::new("sqlite::memory:").await.unwrap();
+    let pool = sql_db.get_pool().clone();
+    let app = build_app(
+        Arc::new(pool.clone()),
+        Settings { posts_cache_ttl: Some(std::time::Duration::from_secs(60)), ..Settings::default() },
+    );
+    let token = register_and_login(&app, "Cached", "cached@example.com").await;
+    let count = |body: &Value| body["data"].as_array().unwrap().len();
+
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "First", "content": "One", "status": "published" }))).await;
+    let author_id: uuid::Uuid = body["data"]["author"]["id"].as_str().unwrap().parse().unwrap();
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(count(&body), 1);
+
+    // A write that bypasses the API isn't seen while the feed is cached...
+    let request = CreatePostRequest { title: "Behind".to_string(), content: "the cache".to_string(), visibility: Default::default(), status: PostStatus::Published, tags: Vec::new(), slug: None };
+    api_rustone::db::repositories::sql_post_repo::SqlPostRepository::new(pool)
+        .create_post(request, author_id)
+        .await
+        .unwrap();
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(count(&body), 1);
+
+    // ...except by signed-in callers, whose feed is never cached
+    let (_, body) = send_json(&app, "GET", "/posts", Some(&token), None).await;
+    assert_eq!(count(&body), 2);
+
+    // Creating a post through the API drops the cached feed
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Third", "content": "Three", "status": "published" }))).await;
+    let third = body["data"]["id"].as_str().unwrap().to_string();
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(count(&body), 3);
+    let (_, body) = send_json(&app, "GET", "/posts?expand=author", None, None).await;
+    assert_eq!(count(&body), 3);
+
+    // So do updates and deletes
+    send_json(&app, "PUT", &format!("/posts/{}", third), Some(&token), Some(serde_json::json!({ "title": "Renamed" }))).await;
+    let (_, body) = send_json(&app, "GET", "/posts?expand=author", None, None).await;
+    assert!(body["data"].as_array().unwrap().iter().any(|post| post["title"] == "Renamed"));
+
+    send_json(&app, "DELETE", &format!("/posts/{}", third), Some(&token), None).await;
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(count(&body), 2);
+}
+
+#[tokio::test]
+async fn test_post_ownership_checks() {
+    let sql_db = SqlDatabase::new("sqlite::memory:").await.unwrap();
+    let pool = sql_db.get_pool().clone();
+    let app = build_app(Arc::new(pool.clone()), Settings::default());
+    let owner = register_and_login(&app, "Owner", "owner@example.com").await;
+    let other = register_and_login(&app, "Other", "other@example.com").await;
+
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&owner), Some(serde_json::json!({ "title": "Mine", "content": "Body" }))).await;
+    let id: uuid::Uuid = body["data"]["id"].as_str().unwrap().parse().unwrap();
+    let owner_id: uuid::Uuid = body["data"]["author"]["id"].as_str().unwrap().parse().unwrap();
+    let (_, body) = send_json(&app, "GET", "/auth/profile", Some(&other), None).await;
+    let other_id: uuid::Uuid = body["data"]["id"].as_str().unwrap().parse().unwrap();
+
+    let repo = api_rustone::db::repositories::sql_post_repo::SqlPostRepository::new(pool.clone());
+    assert!(repo.owned_by(id, owner_id).await.unwrap());
+    assert!(!repo.owned_by(uuid::Uuid::new_v4(), owner_id).await.unwrap());
+    assert!(!repo.owned_by(id, other_id).await.unwrap());
+
+    // Only the owner can delete; soft-deleted posts no longer count
+    assert!(!repo.delete_post(id, other_id).await.unwrap());
+    sqlx::query("UPDATE posts SET deleted_at = CURRENT_TIMESTAMP WHERE id = ?").bind(id.to_string()).execute(&pool).await.unwrap();
+    assert!(!repo.owned_by(id, owner_id).await.unwrap());
+    assert!(!repo.delete_post(id, owner_id).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_prefer_return_minimal() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Minimal", "minimal@example.com").await;
+
+    let send_preferring = |method: &'static str, uri: String, body: Value| {
+        let app = app.clone();
+        let token = token.clone();
+        async move {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", token))
+                .header("prefer", "return=minimal")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<Value>(&bytes).unwrap()
+        }
+    };
+
+    // Only the id comes back
+    let body = send_preferring("POST", "/posts".to_string(), serde_json::json!({ "title": "Lean", "content": "Body" })).await;
+    let data = body["data"].as_object().unwrap();
+    assert_eq!(data.len(), 1);
+    let id = data["id"].as_str().unwrap().to_string();
+
+    let body = send_preferring("PUT", format!("/posts/{}", id), serde_json::json!({ "title": "Leaner" })).await;
+    assert_eq!(body["data"], serde_json::json!({ "id": id }));
+
+    // Without the header the full post is returned
+    let (_, body) = send_json(&app, "PUT", &format!("/posts/{}", id), Some(&token), Some(serde_json::json!({ "content": "Full" }))).await;
+    assert_eq!(body["data"]["title"], "Leaner");
+    assert_eq!(body["data"]["author"]["email"], "minimal@example.com");
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Full", "content": "Body" }))).await;
+    assert_eq!(body["data"]["content"], "Body");
+}
+
+#[tokio::test]
+async fn test_feed_of_followed_users() {
+    let clock = Arc::new(FixedClock::new(chrono::Utc::now()));
+    let app = create_test_app_with(Settings { clock: clock.clone(), ..Settings::default() }).await;
+    let alice = register_and_login(&app, "Alice", "alice@example.com").await;
+    let bob = register_and_login(&app, "Bob", "bob@example.com").await;
+    let carol = register_and_login(&app, "Carol", "carol@example.com").await;
+    let user_id = |token: String| {
+        let app = app.clone();
+        async move {
+            let (_, body) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+            body["data"]["id"].as_str().unwrap().to_string()
+        }
+    };
+    let (alice_id, bob_id) = (user_id(alice.clone()).await, user_id(bob.clone()).await);
+    let titles = |body: &Value| -> Vec<String> {
+        body["data"].as_array().unwrap().iter().map(|post| post["title"].as_str().unwrap().to_string()).collect()
+    };
+
+    let (status, body) = send_json(&app, "POST", &format!("/users/{}/follow", bob_id), Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message"], "User followed");
+    // Following again is a no-op
+    let (status, _) = send_json(&app, "POST", &format!("/users/{}/follow", bob_id), Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    for (token, title, extra) in [
+        (&bob, "Bob's first", serde_json::json!({ "status": "published" })),
+        (&bob, "Bob's second", serde_json::json!({ "status": "published" })),
+        (&bob, "Bob's draft", serde_json::json!({})),
+        (&bob, "Bob's private", serde_json::json!({ "status": "published", "visibility": "private" })),
+        (&carol, "Carol's post", serde_json::json!({ "status": "published" })),
+        (&alice, "Alice's own", serde_json::json!({ "status": "published" })),
+    ] {
+        let mut post = serde_json::json!({ "title": title, "content": "c" });
+        post.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        let (status, _) = send_json(&app, "POST", "/posts", Some(token), Some(post)).await;
+        assert_eq!(status, StatusCode::OK);
+        clock.advance(chrono::Duration::seconds(1));
+    }
+
+    let (status, body) = send_json(&app, "GET", "/me/feed", Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(titles(&body), vec!["Bob's second", "Bob's first"]);
+    let (_, body) = send_json(&app, "GET", "/me/feed?per_page=1&page=2", Some(&alice), None).await;
+    assert_eq!(titles(&body), vec!["Bob's first"]);
+
+    // Following isn't mutual
+    let (_, body) = send_json(&app, "GET", "/me/feed", Some(&bob), None).await;
+    assert!(titles(&body).is_empty());
+
+    let (status, body) = send_json(&app, "DELETE", &format!("/users/{}/follow", bob_id), Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message"], "User unfollowed");
+    let (_, body) = send_json(&app, "GET", "/me/feed", Some(&alice), None).await;
+    assert!(titles(&body).is_empty());
+    let (_, body) = send_json(&app, "DELETE", &format!("/users/{}/follow", bob_id), Some(&alice), None).await;
+    assert!(body["data"].is_null());
+
+    let (status, body) = send_json(&app, "POST", &format!("/users/{}/follow", alice_id), Some(&alice), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "You can't follow yourself");
+    let (_, body) = send_json(&app, "POST", &format!("/users/{}/follow", uuid::Uuid::new_v4()), Some(&alice), None).await;
+    assert_eq!(body["message"], "User not found");
+
+    let (status, _) = send_json(&app, "GET", "/me/feed", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send_json(&app, "POST", &format!("/users/{}/follow", bob_id), None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_liked_posts() {
+    let app = create_test_app().await;
+    let author = register_and_login(&app, "Writer", "writer@example.com").await;
+    let fan = register_and_login(&app, "Fan", "fan@example.com").await;
+
+    let mut ids = Vec::new();
+    for title in ["Loved", "Skipped", "Fickle"] {
+        let (_, body) = send_json(&app, "POST", "/posts", Some(&author), Some(serde_json::json!({ "title": title, "content": "...", "status": "published" }))).await;
+        ids.push(body["data"]["id"].as_str().unwrap().to_string());
+    }
+    for id in [&ids[0], &ids[2]] {
+        let (status, body) = send_json(&app, "POST", &format!("/posts/{}/like", id), Some(&fan), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["message"], "Post liked");
+    }
+    let (_, body) = send_json(&app, "DELETE", &format!("/posts/{}/like", ids[2]), Some(&fan), None).await;
+    assert_eq!(body["message"], "Post unliked");
+
+    let titles = |body: &Value| -> Vec<String> {
+        body["data"].as_array().unwrap().iter().map(|post| post["title"].as_str().unwrap().to_string()).collect()
+    };
+    let (status, body) = send_json(&app, "GET", "/posts/liked", Some(&fan), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(titles(&body), ["Loved"]);
+    assert_eq!(body["data"][0]["author"]["name"], "Writer");
+
+    // Likes are per user and deleted posts drop out
+    let (_, body) = send_json(&app, "GET", "/posts/liked", Some(&author), None).await;
+    assert!(titles(&body).is_empty());
+    send_json(&app, "DELETE", &format!("/posts/{}", ids[0]), Some(&author), None).await;
+    let (_, body) = send_json(&app, "GET", "/posts/liked", Some(&fan), None).await;
+    assert!(titles(&body).is_empty());
+
+    let (status, _) = send_json(&app, "GET", "/posts/liked", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_oversized_query_strings_are_rejected() {
+    let app = create_test_app_with(Settings { max_query_length: 100, max_query_params: 3, ..Settings::default() }).await;
+
+    let (status, _) = send_json(&app, "GET", "/posts?expand=author&quiet=true", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let long = format!("/posts?expand={}", "a".repeat(200));
+    let (status, body) = send_json(&app, "GET", &long, None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Query string is longer than 100 bytes");
+
+    let (status, body) = send_json(&app, "GET", "/posts?a=1&b=2&c=3&d=4", None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Query string has more than 3 parameters");
+}
+
+#[tokio::test]
+async fn test_oversized_headers_are_rejected() {
+    let app = create_test_app_with(Settings { max_header_count: 10, max_header_bytes: 512, ..Settings::default() }).await;
+    let get = |headers: Vec<(String, String)>| {
+        let mut builder = Request::builder().uri("/posts");
+        for (name, value) in headers {
+            builder = builder.header(name, value);
+        }
+        app.clone().oneshot(builder.body(Body::empty()).unwrap())
+    };
+
+    let response = get((0..5).map(|i| (format!("x-extra-{}", i), "1".to_string())).collect()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = get((0..20).map(|i| (format!("x-extra-{}", i), "1".to_string())).collect()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    let body: Value = serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+    assert_eq!(body["message"], "Request has more than 10 headers");
+
+    let response = get(vec![("x-large".to_string(), "a".repeat(600))]).await.unwrap();
+    assert_eq!(response.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    let body: Value = serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+    assert_eq!(body["message"], "Request headers are larger than 512 bytes");
+    assert_eq!(body["error"], "Request Header Fields Too Large");
+}
+
+#[tokio::test]
+async fn test_post_slugs() {
+    let app = create_test_app_with(Settings { client_slugs: true, ..Settings::default() }).await;
+    let token = register_and_login(&app, "Slugger", "slugger@example.com").await;
+    let create = |body: Value| send_json(&app, "POST", "/posts", Some(&token), Some(body));
+
+    // Derived from the title, with a suffix when taken
+    let (_, body) = create(serde_json::json!({ "title": "Hello, World!", "content": "1" })).await;
+    assert_eq!(body["data"]["slug"], "hello-world");
+    let (_, body) = create(serde_json::json!({ "title": "Hello World", "content": "2" })).await;
+    assert_eq!(body["data"]["slug"], "hello-world-2");
+
+    // Client-supplied slugs are used verbatim
+    let (status, body) = create(serde_json::json!({ "title": "Anything", "content": "3", "slug": "my-custom-slug" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["slug"], "my-custom-slug");
+
+    // ...but must be unique across all posts
+    let (status, body) = create(serde_json::json!({ "title": "Other", "content": "4", "slug": "hello-world" })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["message"], "A post with this slug already exists");
+
+    for slug in ["Has Spaces", "UPPER", "-leading", "double--dash", ""] {
+        let (status, _) = create(serde_json::json!({ "title": "Bad", "content": "5", "slug": slug })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{:?}", slug);
+    }
+
+    // Off by default
+    let strict = create_test_app().await;
+    let token = register_and_login(&strict, "Slugger", "slugger@example.com").await;
+    let (status, body) = send_json(&strict, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "T", "content": "c", "slug": "mine" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Custom slugs are not enabled");
+}
+
+#[tokio::test]
+async fn test_user_activity_timeline() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Active", "active@example.com").await;
+    let (_, profile) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+    let uri = format!("/users/{}/activity", profile["data"]["id"].as_str().unwrap());
+
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "First post", "content": "Hi", "status": "published" }))).await;
+    let post_id = body["data"]["id"].as_str().unwrap().to_string();
+    let (_, body) = send_json(&app, "POST", &format!("/posts/{}/comments", post_id), Some(&token), Some(serde_json::json!({ "content": "Replying to myself" }))).await;
+    let comment_id = body["data"]["id"].as_str().unwrap().to_string();
+
+    // Newest first, tagged by type
+    let (status, body) = send_json(&app, "GET", &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let items = body["data"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["type"], "comment");
+    assert_eq!(items[0]["id"], comment_id.as_str());
+    assert_eq!(items[0]["post_id"], post_id.as_str());
+    assert_eq!(items[0]["summary"], "Replying to myself");
+    assert_eq!(items[1]["type"], "post");
+    assert_eq!(items[1]["id"], post_id.as_str());
+    assert_eq!(items[1]["summary"], "First post");
+
+    // Paging by timestamp
+    let (_, body) = send_json(&app, "GET", &format!("{}?limit=1", uri), None, None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    let before = items[0]["created_at"].as_str().unwrap().replace('+', "%2B");
+    let (_, body) = send_json(&app, "GET", &format!("{}?before={}", uri, before), None, None).await;
+    let items = body["data"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["type"], "post");
+
+    let missing = format!("/users/{}/activity", uuid::Uuid::new_v4());
+    let (_, body) = send_json(&app, "GET", &missing, None, None).await;
+    assert!(body["data"].is_null());
+    assert_eq!(body["message"], "User not found");
+}
+
+#[tokio::test]
+async fn test_disposable_email_domains_blocked_when_enabled() {
+    let register = |app: Router| async move {
+        send_json(
+            &app,
+            "POST",
+            "/auth/register",
+            None,
+            Some(serde_json::json!({ "name": "Throwaway", "email": "user@mailinator.com", "password": "TestPass123" })),
+        )
+        .await
+    };
+
+    let strict = create_test_app_with(Settings { block_disposable_emails: true, ..Settings::default() }).await;
+    let (status, body) = register(strict).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Disposable email addresses are not allowed");
+
+    let (status, _) = register(create_test_app().await).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The list can be replaced
+    let custom = create_test_app_with(Settings {
+        block_disposable_emails: true,
+        disposable_email_domains: vec!["example.net".to_string()],
+        ..Settings::default()
+    })
+    .await;
+    let (status, _) = register(custom).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The refusal follows the default language like any other message
+    let spanish = create_test_app_with(Settings { block_disposable_emails: true, default_language: Lang::Es, ..Settings::default() }).await;
+    let (_, body) = register(spanish).await;
+    assert_eq!(body["message"], "No se permiten direcciones de correo electrónico desechables");
+}
+
+#[tokio::test]
+async fn test_concurrent_registrations_with_same_email() {
+    let app = create_test_app().await;
+    let register = || {
+        send_json(
+            &app,
+            "POST",
+            "/auth/register",
+            None,
+            Some(serde_json::json!({ "name": "Racer", "email": "racer@example.com", "password": "TestPass123" })),
+        )
+    };
+
+    // Both pass the existence check while the other is still hashing its password
+    let ((first, _), (second, _)) = tokio::join!(register(), register());
+    let mut statuses = [first, second];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::OK, StatusCode::CONFLICT]);
+}
+
+#[tokio::test]
+async fn test_requests_over_concurrency_limit_are_shed() {
+    let app = create_test_app_with(Settings { max_concurrent_requests: Some(1), ..Settings::default() }).await;
+    let register = send_json(
+        &app,
+        "POST",
+        "/auth/register",
+        None,
+        Some(serde_json::json!({ "name": "Busy", "email": "busy@example.com", "password": "TestPass123" })),
+    );
+
+    // Registration holds the only slot while its password hashes
+    let ((first, _), (second, body)) = tokio::join!(register, send_json(&app, "GET", "/tags", None, None));
+    assert_eq!(first, StatusCode::OK);
+    assert_eq!(second, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["error"], "Service Unavailable");
+    assert_eq!(body["message"], "Server is busy, please retry shortly");
+
+    // The slot is free again once the first request finished
+    let (status, _) = send_json(&app, "GET", "/tags", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_login_reports_token_expiry() {
+    let settings = Settings { access_token_ttl: chrono::Duration::minutes(30), ..Settings::default() };
+    let app = create_test_app_with(settings.clone()).await;
+    register_and_login(&app, "Expiring", "expiring@example.com").await;
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/auth/login",
+        None,
+        Some(serde_json::json!({ "email": "expiring@example.com", "password": "TestPass123" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["token_type"], "Bearer");
+
+    let expires_at = chrono::DateTime::parse_from_rfc3339(body["data"]["expires_at"].as_str().unwrap()).unwrap();
+    let expected = chrono::Utc::now() + chrono::Duration::minutes(30);
+    assert!((expected - expires_at.to_utc()).num_seconds().abs() <= 5);
+
+    // Same instant the token itself carries
+    let claims = AuthHelper::validate_token(body["data"]["token"].as_str().unwrap(), &settings).unwrap();
+    assert_eq!(claims.exp as i64, expires_at.timestamp());
+}
+
+#[tokio::test]
+async fn test_batch_delete_own_posts() {
+    let app = create_test_app().await;
+    let owner = register_and_login(&app, "Owner", "owner@example.com").await;
+    let other = register_and_login(&app, "Other", "other@example.com").await;
+
+    let mut mine = Vec::new();
+    for title in ["One", "Two"] {
+        let (_, body) = send_json(&app, "POST", "/posts", Some(&owner), Some(serde_json::json!({ "title": title, "content": "c", "tags": ["x"], "status": "published" }))).await;
+        mine.push(body["data"]["id"].as_str().unwrap().to_string());
+    }
+    send_json(&app, "POST", &format!("/posts/{}/comments", mine[0]), Some(&other), Some(serde_json::json!({ "content": "Nice" }))).await;
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&other), Some(serde_json::json!({ "title": "Theirs", "content": "c", "status": "published" }))).await;
+    let theirs = body["data"]["id"].as_str().unwrap().to_string();
+    let missing = uuid::Uuid::new_v4().to_string();
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/posts/batch-delete",
+        Some(&owner),
+        Some(serde_json::json!({ "ids": [mine[0], theirs, missing, mine[1]] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["succeeded"], serde_json::json!([mine[0], mine[1]]));
+    let failed: Vec<u64> = body["data"]["failed"].as_array().unwrap().iter().map(|failure| failure["index"].as_u64().unwrap()).collect();
+    assert_eq!(failed, vec![1, 2]);
+
+    for id in &mine {
+        let (_, body) = send_json(&app, "GET", &format!("/posts/{}", id), None, None).await;
+        assert!(body["data"].is_null());
+    }
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}", theirs), None, None).await;
+    assert_eq!(body["data"]["title"], "Theirs");
+
+    let (status, _) = send_json(&app, "POST", "/posts/batch-delete", Some(&owner), Some(serde_json::json!({ "ids": [] }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(&app, "POST", "/posts/batch-delete", None, Some(serde_json::json!({ "ids": [theirs] }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_post_fields_projection() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Fields", "fields@example.com").await;
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Projected", "content": "Long body", "status": "published" }))).await;
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+    let keys = |post: &Value| -> Vec<String> {
+        let mut keys: Vec<String> = post.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    };
+
+    let (status, body) = send_json(&app, "GET", "/posts?fields=id,title", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(keys(&body["data"][0]), ["id", "title"]);
+    assert_eq!(body["data"][0]["title"], "Projected");
+
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}?expand=author&fields=title,author", id), None, None).await;
+    assert_eq!(keys(&body["data"]), ["author", "title"]);
+    assert_eq!(body["data"]["author"]["name"], "Fields");
+    assert_eq!(body["message"], "Post retrieved successfully");
+
+    let (status, body) = send_json(&app, "GET", &format!("/posts/{}?fields=id,password", id), None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().starts_with("Unknown field \"password\""));
+
+    // A repeated parameter can't be read as one list
+    let (status, body) = send_json(&app, "GET", "/posts?fields=id&fields=title", None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().starts_with("Invalid query parameters"));
+
+    // The thread keeps its counts and comments, only the post is projected
+    let (status, body) = send_json(&app, "GET", &format!("/posts/{}/full?fields=title", id), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(keys(&body["data"]["post"]), ["title"]);
+    assert_eq!(body["data"]["like_count"], 0);
+    assert!(body["data"]["comments"].is_array());
+
+    // v2 names its own keys
+    let (status, body) = send_json(&app, "GET", &format!("/v2/posts/{}?fields=id,excerpt,edited", id), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(keys(&body["data"]), ["edited", "excerpt", "id"]);
+    let (_, body) = send_json(&app, "GET", "/v2/posts?fields=title", None, None).await;
+    assert_eq!(keys(&body["data"][0]), ["title"]);
+    let (status, _) = send_json(&app, "GET", "/v2/posts?fields=author_id", None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_random_post() {
+    let app = create_test_app().await;
+
+    let (status, body) = send_json(&app, "GET", "/posts/random", None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["message"], "There are no posts to pick from");
+
+    // Drafts and private posts are never picked
+    let token = register_and_login(&app, "Random", "random@example.com").await;
+    send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Draft", "content": "Hidden" }))).await;
+    send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Private", "content": "Hidden", "visibility": "private", "status": "published" }))).await;
+    let (status, _) = send_json(&app, "GET", "/posts/random", None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    for title in ["One", "Two"] {
+        send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": title, "content": "Shown", "status": "published" }))).await;
+    }
+    for _ in 0..5 {
+        let (status, body) = send_json(&app, "GET", "/posts/random", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(["One", "Two"].contains(&body["data"]["title"].as_str().unwrap()));
+        assert_eq!(body["data"]["author"]["name"], "Random");
+        assert!(body["data"]["author"].get("email").is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_pruning_removes_only_eligible_rows() {
+    let sql_db = SqlDatabase::new("sqlite::memory:").await.unwrap();
+    let pool = sql_db.get_pool().clone();
+    let clock = Arc::new(FixedClock::new(chrono::Utc::now()));
+    let settings = Settings { clock: clock.clone(), max_sessions_per_user: Some(1), ..Settings::default() };
+    let retention = settings.trash_retention;
+    let app = build_app(Arc::new(pool.clone()), settings);
+    let owner = register_and_login(&app, "Owner", "owner@example.com").await;
+    let count = |table: &'static str| {
+        let pool = pool.clone();
+        async move {
+            sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM {}", table)).fetch_one(&pool).await.unwrap()
+        }
+    };
+
+    let mut ids = Vec::new();
+    for title in ["Old trash", "Recent trash", "Live"] {
+        let (_, body) = send_json(&app, "POST", "/posts", Some(&owner), Some(serde_json::json!({ "title": title, "content": "c", "tags": ["x"], "status": "published" }))).await;
+        ids.push(body["data"]["id"].as_str().unwrap().to_string());
+    }
+    send_json(&app, "POST", &format!("/posts/{}/comments", ids[0]), Some(&owner), Some(serde_json::json!({ "content": "Nice" }))).await;
+    let (status, _) = send_json(&app, "DELETE", &format!("/posts/{}", ids[0]), Some(&owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    clock.advance(chrono::Duration::days(25));
+    let owner = login(&app, "owner@example.com").await;
+    let (status, _) = send_json(&app, "DELETE", &format!("/posts/{}", ids[1]), Some(&owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    clock.advance(chrono::Duration::days(6));
+
+    // Two live sessions now, the older one revoked but not yet expired; the earlier ones have expired
+    let revoked = login(&app, "owner@example.com").await;
+    let current = login(&app, "owner@example.com").await;
+    let sessions = count("sessions").await;
+
+    let report = prune(&pool, clock.clone(), retention).await.unwrap();
+    assert_eq!(report.sessions as i64, sessions - 2);
+    assert_eq!(report.posts, 1);
+    assert_eq!(report.tombstones, 1);
+    assert_eq!(count("sessions").await, 2);
+    assert_eq!(count("comments").await, 0);
+
+    let (status, _) = send_json(&app, "GET", "/auth/profile", Some(&current), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_json(&app, "GET", "/auth/profile", Some(&revoked), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // The post trashed within the retention window is still restorable
+    let (_, body) = send_json(&app, "GET", "/posts/trash", Some(&current), None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["title"], "Recent trash");
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}", ids[2]), None, None).await;
+    assert_eq!(body["data"]["title"], "Live");
+
+    // A second pass finds nothing left to do
+    let report = prune(&pool, clock.clone(), retention).await.unwrap();
+    assert_eq!((report.sessions, report.posts, report.tombstones), (0, 0, 0));
+}
+
+#[tokio::test]
+async fn test_purge_trashed_posts() {
+    let sql_db = SqlDatabase::new("sqlite::memory:").await.unwrap();
+    let pool = sql_db.get_pool().clone();
+    let app = build_app(Arc::new(pool.clone()), Settings::default());
+    let owner = register_and_login(&app, "Owner", "owner@example.com").await;
+    let other = register_and_login(&app, "Other", "other@example.com").await;
+
+    let mut ids = Vec::new();
+    for title in ["Trashed", "Live"] {
+        let (_, body) = send_json(&app, "POST", "/posts", Some(&owner), Some(serde_json::json!({ "title": title, "content": "c", "tags": ["x"], "status": "published" }))).await;
+        ids.push(body["data"]["id"].as_str().unwrap().to_string());
+    }
+    let (trashed, live) = (&ids[0], &ids[1]);
+    send_json(&app, "POST", &format!("/posts/{}/comments", trashed), Some(&other), Some(serde_json::json!({ "content": "Nice" }))).await;
+    let trash = |token: String| {
+        let app = app.clone();
+        async move {
+            let (_, body) = send_json(&app, "GET", "/posts/trash", Some(&token), None).await;
+            body["data"].as_array().unwrap().iter().map(|post| post["title"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+        }
+    };
+
+    // Deleting moves the post to its owner's trash, and restoring brings it back with its comments
+    let (status, _) = send_json(&app, "DELETE", &format!("/posts/{}", trashed), Some(&owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}", trashed), None, None).await;
+    assert!(body["data"].is_null());
+    assert_eq!(trash(owner.clone()).await, ["Trashed"]);
+    assert!(trash(other.clone()).await.is_empty());
+
+    let (status, _) = send_json(&app, "POST", &format!("/posts/{}/restore", trashed), Some(&other), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, body) = send_json(&app, "POST", &format!("/posts/{}/restore", trashed), Some(&owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["title"], "Trashed");
+    assert!(trash(owner.clone()).await.is_empty());
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}/full", trashed), None, None).await;
+    assert_eq!(body["data"]["comment_count"], 1);
+    let (status, _) = send_json(&app, "POST", &format!("/posts/{}/restore", trashed), Some(&owner), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    send_json(&app, "DELETE", &format!("/posts/{}", trashed), Some(&owner), None).await;
+    let stored = |id: String| {
+        let pool = pool.clone();
+        async move {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM posts WHERE id = ?").bind(id).fetch_one(&pool).await.unwrap()
+        }
+    };
+
+    // Only the owner can purge it
+    let (status, _) = send_json(&app, "DELETE", &format!("/posts/{}/purge", trashed), Some(&other), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(stored(trashed.clone()).await, 1);
+
+    let (status, body) = send_json(&app, "DELETE", &format!("/posts/{}/purge", trashed), Some(&owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message"], "Post permanently deleted");
+    assert_eq!(stored(trashed.clone()).await, 0);
+    let comments: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM comments WHERE post_id = ?").bind(trashed).fetch_one(&pool).await.unwrap();
+    assert_eq!(comments, 0);
+
+    let (status, _) = send_json(&app, "DELETE", &format!("/posts/{}/purge", trashed), Some(&owner), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(trash(owner.clone()).await.is_empty());
+
+    // A live post isn't in the trash
+    let (status, body) = send_json(&app, "DELETE", &format!("/posts/{}/purge", live), Some(&owner), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["message"], "No post with that id in your trash");
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}", live), None, None).await;
+    assert_eq!(body["data"]["title"], "Live");
+
+    let (status, _) = send_json(&app, "DELETE", &format!("/posts/{}/purge", live), None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_batch_modes_report_each_item() {
+    let app = create_test_app().await;
+    let owner = register_and_login(&app, "Owner", "owner@example.com").await;
+    let other = register_and_login(&app, "Other", "other@example.com").await;
+
+    let mut mine = Vec::new();
+    for title in ["One", "Two", "Three"] {
+        let (_, body) = send_json(&app, "POST", "/posts", Some(&owner), Some(serde_json::json!({ "title": title, "content": "c", "status": "published" }))).await;
+        mine.push(body["data"]["id"].as_str().unwrap().to_string());
+    }
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&other), Some(serde_json::json!({ "title": "Theirs", "content": "c", "status": "published" }))).await;
+    let theirs = body["data"]["id"].as_str().unwrap().to_string();
+
+    // Atomic: one id the caller can't delete rolls the whole batch back
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/posts/batch-delete?mode=atomic",
+        Some(&owner),
+        Some(serde_json::json!({ "ids": [mine[0], theirs, mine[1]] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["data"]["succeeded"], serde_json::json!([]));
+    assert_eq!(body["data"]["failed"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"]["failed"][0]["index"], 1);
+    assert!(body["data"]["failed"][0]["error"].as_str().unwrap().contains("not found"));
+    for id in &mine {
+        let (_, body) = send_json(&app, "GET", &format!("/posts/{}", id), None, None).await;
+        assert_eq!(body["data"]["id"], id.as_str());
+    }
+
+    // Partial (the default): the rest goes through, duplicates report under their first index
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/posts/batch-delete?mode=partial",
+        Some(&owner),
+        Some(serde_json::json!({ "ids": [mine[0], theirs, mine[0], mine[1]] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["succeeded"], serde_json::json!([mine[0], mine[1]]));
+    assert_eq!(body["data"]["failed"], serde_json::json!([{ "index": 1, "error": "Post not found or you don't have permission to delete it" }]));
+
+    // Atomic batches that fully succeed are applied
+    let (status, body) = send_json(&app, "POST", "/posts/batch-delete?mode=atomic", Some(&owner), Some(serde_json::json!({ "ids": [mine[2]] }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["succeeded"], serde_json::json!([mine[2]]));
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}", mine[2]), None, None).await;
+    assert!(body["data"].is_null());
+
+    // Same modes on bulk mark-as-read
+    let missing = uuid::Uuid::new_v4().to_string();
+    let (status, body) = send_json(&app, "POST", "/posts/read?mode=atomic", Some(&other), Some(serde_json::json!({ "ids": [theirs, missing] }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["data"]["failed"][0]["index"], 1);
+    let (_, body) = send_json(&app, "GET", "/posts/unread", Some(&other), None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+
+    let (status, _) = send_json(&app, "POST", "/posts/read?mode=sometimes", Some(&other), Some(serde_json::json!({ "ids": [theirs] }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_public_post_views_hide_author_email() {
+    let app = create_test_app().await;
+    let author = register_and_login(&app, "Private Person", "private@example.com").await;
+    let reader = register_and_login(&app, "Reader", "reader@example.com").await;
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&author), Some(serde_json::json!({ "title": "Hello", "content": "c", "status": "published" }))).await;
+    let post_id = body["data"]["id"].as_str().unwrap().to_string();
+    // The author still sees their own email
+    assert_eq!(body["data"]["author"]["email"], "private@example.com");
+
+    let (_, body) = send_json(&app, "GET", "/posts?expand=author", None, None).await;
+    let author_json = &body["data"][0]["author"];
+    assert_eq!(author_json["name"], "Private Person");
+    assert!(author_json["id"].is_string());
+    assert!(author_json.get("email").is_none());
+
+    let uri = format!("/posts/{}?expand=author", post_id);
+    let (_, body) = send_json(&app, "GET", &uri, Some(&reader), None).await;
+    assert!(body["data"]["author"].get("email").is_none());
+    let (_, body) = send_json(&app, "GET", &uri, Some(&author), None).await;
+    assert_eq!(body["data"]["author"]["email"], "private@example.com");
+
+    // Opting in restores the old exposure
+    let open = create_test_app_with(Settings { expose_author_email: true, ..Settings::default() }).await;
+    let token = register_and_login(&open, "Open Person", "open@example.com").await;
+    send_json(&open, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Hi", "content": "c", "status": "published" }))).await;
+    let (_, body) = send_json(&open, "GET", "/posts?expand=author", None, None).await;
+    assert_eq!(body["data"][0]["author"]["email"], "open@example.com");
+}
+
+#[tokio::test]
+async fn test_resend_verification() {
+    let logs = CapturedLogs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(logs.clone())
+        .with_ansi(false)
+        .with_max_level(tracing::Level::DEBUG)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = create_test_app_with(Settings { verification_resend_rate_limit: 2, ..Settings::default() }).await;
+    let token = register_and_login(&app, "Forgetful", "forgetful@example.com").await;
+
+    let (status, body) = send_json(&app, "POST", "/auth/resend-verification", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message"], "Verification email sent");
+
+    // The newest logged token verifies the address
+    let output = logs.contents();
+    let resent = output
+        .lines()
+        .rev()
+        .find_map(|line| line.split("Email verification token for forgetful@example.com: ").nth(1))
+        .expect("no verification token logged")
+        .trim()
+        .to_string();
+    let (status, _) = send_json(&app, "POST", "/auth/verify-email", None, Some(serde_json::json!({ "token": resent }))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Already verified: a no-op that doesn't use up the limit
+    for _ in 0..3 {
+        let (status, body) = send_json(&app, "POST", "/auth/resend-verification", Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["message"], "Email is already verified");
+    }
+
+    let other = register_and_login(&app, "Spammer", "spammer@example.com").await;
+    for _ in 0..2 {
+        let (status, _) = send_json(&app, "POST", "/auth/resend-verification", Some(&other), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _) = send_json(&app, "POST", "/auth/resend-verification", Some(&other), None).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    let (status, _) = send_json(&app, "POST", "/auth/resend-verification", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_writes_retry_while_database_is_locked() {
+    use sqlx::{sqlite::SqliteConnectOptions, Connection};
+    use std::str::FromStr;
+
+    let path = std::env::temp_dir().join(format!("api_rustone_locked_{}.db", uuid::Uuid::new_v4()));
+    let url = format!("sqlite:{}?mode=rwc", path.display());
+    SqlDatabase::new(&url).await.unwrap();
+
+    // No busy timeout, so lock contention surfaces as an error right away instead of waiting inside SQLite
+    let options = SqliteConnectOptions::from_str(&url).unwrap().busy_timeout(std::time::Duration::ZERO);
+    let pool = sqlx::SqlitePool::connect_with(options.clone()).await.unwrap();
+    let repo = SqlUserRepository::new(pool);
+
+    // Another connection holds the write lock for a moment
+    let mut holder = sqlx::SqliteConnection::connect_with(&options).await.unwrap();
+    sqlx::query("BEGIN IMMEDIATE").execute(&mut holder).await.unwrap();
+    let release = tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        sqlx::query("COMMIT").execute(&mut holder).await.unwrap();
+    });
+
+    let user = CreateUserRequest { name: "Patient".to_string(), email: "patient@example.com".to_string(), password: "x".to_string(), username: None };
+    let created = repo.create_user(user, "hash".to_string(), Role::User).await;
+    release.await.unwrap();
+    assert!(created.is_ok(), "{:?}", created.err());
+    assert!(repo.find_by_email("patient@example.com").await.unwrap().is_some());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_version_reports_build_info() {
+    let app = create_test_app().await;
+
+    let (status, body) = send_json(&app, "GET", "/version", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["version"], env!("CARGO_PKG_VERSION"));
+    // A short hash in a checkout, "unknown" otherwise
+    assert!(!body["data"]["commit"].as_str().unwrap().is_empty());
+    assert!(chrono::DateTime::parse_from_rfc3339(body["data"]["built_at"].as_str().unwrap()).is_ok());
+}
+
+#[tokio::test]
+async fn test_usernames() {
+    let app = create_test_app().await;
+    let register = |email: &str, username: Option<&str>| {
+        let mut body = serde_json::json!({ "name": "Same Name", "email": email, "password": "TestPass123" });
+        if let Some(username) = username {
+            body["username"] = username.into();
+        }
+        let app = app.clone();
+        async move { send_json(&app, "POST", "/auth/register", None, Some(body)).await }
+    };
+
+    let (status, body) = register("alice@example.com", Some("Alice_1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["username"], "alice_1");
+    let alice_id = body["data"]["id"].clone();
+
+    // Unique regardless of case, while display names may repeat
+    let (status, body) = register("other@example.com", Some("alice_1")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["message"], "This username is already taken");
+
+    let (status, body) = register("boss@example.com", Some("admin")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Username is reserved");
+    let (status, _) = register("dash@example.com", Some("has-dash")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Derived from the email when not given, skipping taken and reserved names
+    let (_, body) = register("alice.1@example.com", None).await;
+    assert_eq!(body["data"]["username"], "alice_1_2");
+    let (_, body) = register("admin@example.com", None).await;
+    assert_eq!(body["data"]["username"], "admin_2");
+
+    let (status, body) = send_json(&app, "GET", "/users/ALICE_1", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["id"], alice_id);
+    assert_eq!(body["data"]["username"], "alice_1");
+    assert_eq!(body["data"]["name"], "Same Name");
+    assert!(body["data"].get("email").is_none());
+
+    let (_, body) = send_json(&app, "GET", "/users/nobody_here", None, None).await;
+    assert!(body["data"].is_null());
+    assert_eq!(body["message"], "User not found");
+}
+
+#[tokio::test]
+async fn test_routes_served_under_api_prefix() {
+    let prefixed = |trailing_slash| Settings { api_prefix: Some("/api/v1".to_string()), trailing_slash, ..Settings::default() };
+    let app = create_test_app_with(prefixed(TrailingSlash::Trim)).await;
+
+    let (status, _) = send_json(&app, "GET", "/api/v1/posts", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Auth rules still apply to the unprefixed route names
+    let user = serde_json::json!({ "name": "Prefixed", "email": "prefixed@example.com", "password": "TestPass123" });
+    send_json(&app, "POST", "/api/v1/auth/register", None, Some(user)).await;
+    let login = serde_json::json!({ "email": "prefixed@example.com", "password": "TestPass123" });
+    let (status, body) = send_json(&app, "POST", "/api/v1/auth/login", None, Some(login)).await;
+    assert_eq!(status, StatusCode::OK);
+    let token = body["data"]["token"].as_str().unwrap().to_string();
+    let post = serde_json::json!({ "title": "Nested", "content": "c", "status": "published" });
+    let (status, _) = send_json(&app, "POST", "/api/v1/posts", None, Some(post.clone())).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = send_json(&app, "POST", "/api/v1/posts", Some(&token), Some(post)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Links in responses carry the prefix too
+    let post_id = body["data"]["id"].as_str().unwrap().to_string();
+    let cover_url = format!("/api/v1/posts/{}/cover", post_id);
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\0".to_vec();
+    let (status, body) = upload_cover_to(&app, &cover_url, &token, "image/png", &png).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["cover_url"], cover_url.as_str());
+    let (_, body) = send_json(&app, "GET", &format!("/api/v1/posts/{}?expand=author", post_id), Some(&token), None).await;
+    assert_eq!(body["data"]["cover_url"], cover_url.as_str());
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(&cover_url).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+
+    // Redirects keep the prefix
+    let app = create_test_app_with(prefixed(TrailingSlash::Redirect)).await;
+    let response = app
+        .oneshot(Request::builder().uri("/api/v1/posts/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.headers()["location"], "/api/v1/posts");
+}
+
+#[tokio::test]
+async fn test_v1_and_v2_post_shapes_coexist() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Versioned", "versioned@example.com").await;
+    let content = "word ".repeat(100);
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Both", "content": content, "status": "published" }))).await;
+    let post_id = body["data"]["id"].as_str().unwrap().to_string();
+
+    // v1 is the current shape, same as the unversioned routes
+    let (status, v1) = send_json(&app, "GET", "/v1/posts", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, unversioned) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(v1["data"], unversioned["data"]);
+    assert!(v1["data"][0]["author_id"].is_string());
+    assert!(v1["data"][0].get("excerpt").is_none());
+
+    let (_, v1) = send_json(&app, "GET", &format!("/v1/posts/{}?expand=author", post_id), Some(&token), None).await;
+    assert_eq!(v1["data"]["author"]["email"], "versioned@example.com");
+
+    // v2 always embeds the lean author and adds excerpt/edited
+    let (status, v2) = send_json(&app, "GET", "/v2/posts", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let post = &v2["data"][0];
+    assert_eq!(post["id"], post_id.as_str());
+    assert_eq!(post["author"]["name"], "Versioned");
+    assert!(post["author"]["username"].is_string());
+    assert!(post.get("author_id").is_none());
+    assert_eq!(post["excerpt"].as_str().unwrap().chars().count(), 200);
+    assert_eq!(post["edited"], false);
+
+    // Even the author gets the lean author under v2
+    let (_, v2) = send_json(&app, "GET", &format!("/v2/posts/{}", post_id), Some(&token), None).await;
+    assert!(v2["data"]["author"].get("email").is_none());
+    assert_eq!(v2["data"]["content"], content.as_str());
+
+    send_json(&app, "PUT", &format!("/posts/{}", post_id), Some(&token), Some(serde_json::json!({ "title": "Edited" }))).await;
+    let (_, v2) = send_json(&app, "GET", &format!("/v2/posts/{}", post_id), None, None).await;
+    assert_eq!(v2["data"]["title"], "Edited");
+    assert_eq!(v2["data"]["edited"], true);
+
+    let (_, missing) = send_json(&app, "GET", &format!("/v2/posts/{}", uuid::Uuid::new_v4()), None, None).await;
+    assert!(missing["data"].is_null());
+}
+
+#[tokio::test]
+async fn test_slow_query_is_cancelled_at_deadline() {
+    use api_rustone::db::sql_db::{within_deadline, DeadlineExceeded};
+    use futures_util::FutureExt;
+    use std::time::{Duration, Instant};
+
+    // A single connection, so it's only usable again if the slow query really stopped
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    // Never finishes on its own
+    let endless = "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n) SELECT count(*) FROM n";
+
+    let started = Instant::now();
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        within_deadline(&pool, Some(Instant::now() + Duration::from_millis(100)), move |conn| {
+            sqlx::query_scalar::<_, i64>(endless).fetch_one(conn).boxed()
+        }),
+    )
+    .await
+    .expect("slow query ran past its deadline");
+    assert!(result.unwrap_err().is::<DeadlineExceeded>());
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    let one: i64 = tokio::time::timeout(Duration::from_secs(1), sqlx::query_scalar("SELECT 1").fetch_one(&pool))
+        .await
+        .expect("connection still held by the cancelled query")
+        .unwrap();
+    assert_eq!(one, 1);
+
+    // A caller dropped mid-query (as the request timeout does) still gets the connection back
+    let dropped = tokio::time::timeout(
+        Duration::from_millis(20),
+        within_deadline(&pool, Some(Instant::now() + Duration::from_millis(100)), move |conn| {
+            sqlx::query_scalar::<_, i64>(endless).fetch_one(conn).boxed()
+        }),
+    )
+    .await;
+    assert!(dropped.is_err());
+    let one: i64 = tokio::time::timeout(Duration::from_secs(1), sqlx::query_scalar("SELECT 1").fetch_one(&pool))
+        .await
+        .expect("connection still held by the abandoned query")
+        .unwrap();
+    assert_eq!(one, 1);
+}
+
+#[tokio::test]
+async fn test_requests_past_their_budget_time_out() {
+    let app = create_test_app_with(Settings {
+        request_timeout: Some(std::time::Duration::from_nanos(1)),
+        ..Settings::default()
+    })
+    .await;
+
+    let (status, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(body["error"], "Gateway Timeout");
+
+    // Without a budget the same read goes through
+    let app = create_test_app_with(Settings { request_timeout: None, ..Settings::default() }).await;
+    let (status, _) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_database_error_details_only_with_expose_error_details() {
+    for expose in [false, true] {
+        let sql_db = SqlDatabase::new("sqlite::memory:").await.unwrap();
+        let pool = Arc::new(sql_db.get_pool().clone());
+        let app = build_app(pool.clone(), Settings { expose_error_details: expose, ..Settings::default() });
+
+        // Break the query behind GET /tags
+        sqlx::query("DROP TABLE post_tags").execute(&*pool).await.unwrap();
+
+        let (status, body) = send_json(&app, "GET", "/tags", None, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "Database Error");
+        let message = body["message"].as_str().unwrap();
+        assert!(message.starts_with("Failed to get tag counts"), "{}", message);
+        assert_eq!(message.contains("no such table: post_tags"), expose, "{}", message);
+    }
+}
+
+#[tokio::test]
+async fn test_post_reports() {
+    let app = create_test_app_with(Settings {
+        admin_emails: vec!["admin@example.com".to_string()],
+        ..Settings::default()
+    })
+    .await;
+    let admin = register_and_login(&app, "Admin", "admin@example.com").await;
+    let author = register_and_login(&app, "Author", "author@example.com").await;
+    let first = register_and_login(&app, "First", "first@example.com").await;
+    let second = register_and_login(&app, "Second", "second@example.com").await;
+
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&author), Some(serde_json::json!({ "title": "Spammy", "content": "c", "status": "published" }))).await;
+    let spammy = body["data"]["id"].as_str().unwrap().to_string();
+    let (_, body) = send_json(&app, "POST", "/posts", Some(&author), Some(serde_json::json!({ "title": "Rude", "content": "c", "status": "published" }))).await;
+    let rude = body["data"]["id"].as_str().unwrap().to_string();
+
+    // Reporting needs a login and a reason
+    let uri = format!("/posts/{}/report", spammy);
+    let (status, _) = send_json(&app, "POST", &uri, None, Some(serde_json::json!({ "reason": "spam" }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = send_json(&app, "POST", &uri, Some(&first), Some(serde_json::json!({ "reason": "  " }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["message"], "Report reason cannot be empty");
+
+    let (status, body) = send_json(&app, "POST", &uri, Some(&first), Some(serde_json::json!({ "reason": "spam" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message"], "Post reported");
+    assert_eq!(body["data"]["post_id"], spammy.as_str());
+    assert_eq!(body["data"]["reason"], "spam");
+
+    // Once per user per post
+    let (status, body) = send_json(&app, "POST", &uri, Some(&first), Some(serde_json::json!({ "reason": "more spam" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["message"], "You have already reported this post");
+
+    send_json(&app, "POST", &uri, Some(&second), Some(serde_json::json!({ "reason": "ads" }))).await;
+    let rude_uri = format!("/posts/{}/report", rude);
+    send_json(&app, "POST", &rude_uri, Some(&first), Some(serde_json::json!({ "reason": "insults" }))).await;
+
+    let missing = format!("/posts/{}/report", uuid::Uuid::new_v4());
+    let (_, body) = send_json(&app, "POST", &missing, Some(&first), Some(serde_json::json!({ "reason": "spam" }))).await;
+    assert!(body["data"].is_null());
+
+    // Admins see each reported post once, most reported first
+    let (status, _) = send_json(&app, "GET", "/admin/reports", Some(&first), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = send_json(&app, "GET", "/admin/reports", Some(&admin), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message"], "Retrieved 2 reported posts");
+    let reports = body["data"].as_array().unwrap();
+    assert_eq!(reports[0]["post_id"], spammy.as_str());
+    assert_eq!(reports[0]["title"], "Spammy");
+    assert_eq!(reports[0]["report_count"], 2);
+    let mut reasons: Vec<&str> = reports[0]["reasons"].as_array().unwrap().iter().map(|r| r.as_str().unwrap()).collect();
+    reasons.sort();
+    assert_eq!(reasons, ["ads", "spam"]);
+    assert_eq!(reports[1]["post_id"], rude.as_str());
+    assert_eq!(reports[1]["report_count"], 1);
+
+    // Reports go with the post
+    let (status, _) = send_json(&app, "DELETE", &format!("/posts/{}", rude), Some(&author), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send_json(&app, "GET", "/admin/reports", Some(&admin), None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_post_listing_sort() {
+    let app = create_test_app().await;
+    let first = register_and_login(&app, "First", "first@example.com").await;
+    let second = register_and_login(&app, "Second", "second@example.com").await;
+    for (token, title) in [(&first, "banana"), (&first, "Apple"), (&second, "Banana"), (&second, "apple"), (&first, "cherry")] {
+        send_json(&app, "POST", "/posts", Some(token), Some(serde_json::json!({ "title": title, "content": "c", "status": "published" }))).await;
+    }
+    let titles = |body: &Value| -> Vec<String> {
+        body["data"].as_array().unwrap().iter().map(|post| post["title"].as_str().unwrap().to_lowercase()).collect()
+    };
+
+    // Case doesn't split "Apple" from "apple"
+    let (status, body) = send_json(&app, "GET", "/posts?sort=title", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(titles(&body), ["apple", "apple", "banana", "banana", "cherry"]);
+    let (_, body) = send_json(&app, "GET", "/posts?sort=-title&expand=author", None, None).await;
+    assert_eq!(titles(&body), ["cherry", "banana", "banana", "apple", "apple"]);
+
+    // The default stays newest first
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(titles(&body)[0], "cherry");
+
+    let (status, body) = send_json(&app, "GET", "/posts?sort=author", None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().starts_with("Unknown sort author"));
+
+    // The default order comes from settings
+    let app = create_test_app_with(Settings { default_post_sort: "title".parse().unwrap(), ..Settings::default() }).await;
+    let token = register_and_login(&app, "Author", "author@example.com").await;
+    for title in ["b", "C", "a"] {
+        send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": title, "content": "c", "status": "published" }))).await;
+    }
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(titles(&body), ["a", "b", "c"]);
+}
+
+#[tokio::test]
+async fn test_post_listing_ties_break_by_id_across_pages() {
+    let app = create_test_app().await;
+    for n in 0..3 {
+        let token = register_and_login(&app, "Tied", &format!("tied{}@example.com", n)).await;
+        for title in ["Tie", "tie"] {
+            send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": title, "content": "c", "status": "published" }))).await;
+        }
+    }
+    let ids = |body: &Value| -> Vec<String> {
+        body["data"].as_array().unwrap().iter().map(|post| post["id"].as_str().unwrap().to_string()).collect()
+    };
+
+    // Every title compares equal, so the order is by id alone
+    let (_, body) = send_json(&app, "GET", "/posts?sort=title", None, None).await;
+    let all = ids(&body);
+    assert_eq!(all.len(), 6);
+    let mut sorted = all.clone();
+    sorted.sort();
+    assert_eq!(all, sorted);
+
+    // Pages line up with the full listing: no repeats, no gaps, same on every read
+    for _ in 0..2 {
+        let mut paged = Vec::new();
+        for offset in [0, 4, 8] {
+            let (_, body) = send_json(&app, "GET", &format!("/posts?sort=title&limit=4&offset={}", offset), None, None).await;
+            paged.extend(ids(&body));
+        }
+        assert_eq!(paged, all);
+    }
+
+    let (_, body) = send_json(&app, "GET", "/posts?sort=-title&limit=6", None, None).await;
+    let mut reversed = all.clone();
+    reversed.reverse();
+    assert_eq!(ids(&body), reversed);
+}
+
+#[tokio::test]
+async fn test_body_logging_redacts_secrets() {
+    let logs = CapturedLogs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(logs.clone())
+        .with_ansi(false)
+        .with_max_level(tracing::Level::TRACE)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    // Off by default
+    let app = create_test_app().await;
+    register_and_login(&app, "Quiet", "quiet@example.com").await;
+    assert!(!logs.contents().contains("Request body"));
+
+    let app = create_test_app_with(Settings { log_bodies: true, ..Settings::default() }).await;
+    let token = register_and_login(&app, "Logged", "logged@example.com").await;
+    let output = logs.contents();
+
+    let registration = output
+        .lines()
+        .find(|line| line.contains("Request body") && line.contains("logged@example.com"))
+        .expect("registration body not logged");
+    assert!(registration.contains(r#""password":"[REDACTED]""#));
+    assert!(registration.contains(r#""name":"Logged""#));
+    assert!(!output.contains("TestPass123"));
+
+    // The issued token is kept out of the logged login response too
+    assert!(output.lines().any(|line| line.contains("Response body") && line.contains(r#""token":"[REDACTED]""#)));
+    assert!(!output.contains(&token));
+
+    // Oversized bodies are cut short
+    let content = "x".repeat(10_000);
+    send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": "Long", "content": content }))).await;
+    let long = logs.contents();
+    let line = long.lines().find(|line| line.contains("Request body") && line.contains(&"x".repeat(100))).unwrap();
+    assert!(line.contains("bytes total)"));
+    assert!(line.len() < 6000);
+}
+
+#[tokio::test]
+async fn test_post_listing_query_parameters() {
+    let app = create_test_app().await;
+    let alice = register_and_login(&app, "Alice", "alice@example.com").await;
+    let bob = register_and_login(&app, "Bob", "bob@example.com").await;
+    send_json(&app, "POST", "/posts", Some(&alice), Some(serde_json::json!({ "title": "Old", "content": "c", "tags": ["rust"], "status": "published" }))).await;
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    let since = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true);
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    send_json(&app, "POST", "/posts", Some(&alice), Some(serde_json::json!({ "title": "New", "content": "c", "tags": ["Rust", "web"], "status": "published" }))).await;
+    send_json(&app, "POST", "/posts", Some(&bob), Some(serde_json::json!({ "title": "Bobs", "content": "c", "tags": ["web"], "status": "published" }))).await;
+    let titles = |body: &Value| -> Vec<String> {
+        body["data"].as_array().unwrap().iter().map(|post| post["title"].as_str().unwrap().to_string()).collect()
+    };
+
+    // Valid combinations
+    let (status, body) = send_json(&app, "GET", "/posts?tag=RUST&sort=title", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(titles(&body), ["New", "Old"]);
+    let (_, body) = send_json(&app, "GET", "/posts?author=alice&tag=web", None, None).await;
+    assert_eq!(titles(&body), ["New"]);
+    let (_, body) = send_json(&app, "GET", &format!("/posts?since={}&sort=title", since), None, None).await;
+    assert_eq!(titles(&body), ["Bobs", "New"]);
+    let (_, body) = send_json(&app, "GET", "/posts?sort=created_at&limit=1&offset=1&expand=author", None, None).await;
+    assert_eq!(titles(&body), ["New"]);
+    let (_, body) = send_json(&app, "GET", "/posts?author=nobody", None, None).await;
+    assert_eq!(body["data"], serde_json::json!([]));
+
+    // Each invalid field gets the 400 envelope
+    for (query, message) in [
+        ("sort=popularity", "Unknown sort popularity; use created_at, updated_at or title, prefixed with - for descending"),
+        ("limit=0", "limit must be between 1 and 100"),
+        ("limit=101", "limit must be between 1 and 100"),
+        ("tag=%20", "tag must be 1 to 32 characters"),
+        ("author=not%20a%20name", "author must be a valid username"),
+    ] {
+        let (status, body) = send_json(&app, "GET", &format!("/posts?{}", query), None, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+        assert_eq!(body["error"], "Bad Request");
+        assert_eq!(body["message"], message, "{}", query);
+    }
+    for query in ["limit=ten", "offset=-1", "since=yesterday"] {
+        let (status, body) = send_json(&app, "GET", &format!("/posts?{}", query), None, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+        assert_eq!(body["error"], "Bad Request");
+        assert!(body["message"].as_str().unwrap().starts_with("Invalid query parameters"), "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn test_posts_with_equal_timestamps_keep_a_stable_order() {
+    // The clock never moves, so every post gets the same created_at
+    let start = chrono::DateTime::parse_from_rfc3339("2024-03-01T09:30:00Z").unwrap().to_utc();
+    let app = create_test_app_with(Settings { clock: Arc::new(FixedClock::new(start)), ..Settings::default() }).await;
+    let token = register_and_login(&app, "Author", "author@example.com").await;
+
+    let mut ids = Vec::new();
+    for title in ["One", "Two", "Three", "Four"] {
+        let (_, body) = send_json(&app, "POST", "/posts", Some(&token), Some(serde_json::json!({ "title": title, "content": "c", "status": "published" }))).await;
+        assert_eq!(body["data"]["created_at"], "2024-03-01T09:30:00+00:00");
+        ids.push(body["data"]["id"].as_str().unwrap().to_string());
+    }
+    ids.sort();
+    ids.reverse();
+
+    for uri in ["/posts/my", "/posts/my?expand=author", "/posts"] {
+        for _ in 0..3 {
+            let (status, body) = send_json(&app, "GET", uri, Some(&token), None).await;
+            assert_eq!(status, StatusCode::OK);
+            let listed: Vec<&str> = body["data"].as_array().unwrap().iter().map(|post| post["id"].as_str().unwrap()).collect();
+            assert_eq!(listed, ids, "{}", uri);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_fixed_clock_sets_exact_timestamps() {
+    let start = chrono::DateTime::parse_from_rfc3339("2024-03-01T09:30:00Z").unwrap().to_utc();
+    let clock = Arc::new(FixedClock::new(start));
+    let app = create_test_app_with(Settings { clock: clock.clone(), ..Settings::default() }).await;
+
+    let email = format!("clock_{}@example.com", uuid::Uuid::new_v4());
+    let token = register_and_login(&app, "Clock User", &email).await;
+
+    // Tokens are issued and checked against the same clock, even one far in the past
+    let (status, profile) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(profile["data"]["created_at"], start.to_rfc3339());
+    assert_eq!(profile["data"]["updated_at"], start.to_rfc3339());
+
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Clocked", "content": "Stamped by a fixed clock" })),
+    )
+    .await;
+    let id = created["data"]["id"].as_str().unwrap().to_string();
+    assert_eq!(created["data"]["created_at"], start.to_rfc3339());
+    assert_eq!(created["data"]["updated_at"], start.to_rfc3339());
+
+    clock.advance(chrono::Duration::minutes(90));
+    let (_, updated) = send_json(
+        &app,
+        "PUT",
+        &format!("/posts/{}", id),
+        Some(&token),
+        Some(serde_json::json!({ "content": "Edited later" })),
+    )
+    .await;
+    assert_eq!(updated["data"]["created_at"], start.to_rfc3339());
+    assert_eq!(updated["data"]["updated_at"], (start + chrono::Duration::minutes(90)).to_rfc3339());
+
+    // Past the access token TTL on the injected clock, the token has expired
+    clock.advance(chrono::Duration::days(2));
+    let (status, body) = send_json(&app, "GET", "/auth/profile", Some(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["message"], "Token has expired");
+}
+
+#[tokio::test]
+async fn test_read_state_and_unread_posts() {
+    let app = create_test_app().await;
+    let author = register_and_login(&app, "Writer", "writer@example.com").await;
+    let reader = register_and_login(&app, "Reader", "reader@example.com").await;
+
+    let mut ids = Vec::new();
+    for (title, visibility) in [("First", "public"), ("Second", "public"), ("Hidden", "private")] {
+        let (_, body) = send_json(
+            &app,
+            "POST",
+            "/posts",
+            Some(&author),
+            Some(serde_json::json!({ "title": title, "content": "c", "visibility": visibility, "status": "published" })),
+        )
+        .await;
+        ids.push(body["data"]["id"].as_str().unwrap().to_string());
+    }
+    let (first, second, hidden) = (&ids[0], &ids[1], &ids[2]);
+
+    let (status, _) = send_json(&app, "GET", "/posts/unread", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let unread = |body: &Value| -> Vec<String> {
+        body["data"].as_array().unwrap().iter().map(|post| post["id"].as_str().unwrap().to_string()).collect()
+    };
+    let (status, body) = send_json(&app, "GET", "/posts/unread", Some(&reader), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(unread(&body), [second.clone(), first.clone()]);
+    assert_eq!(body["data"][0]["read"], false);
+
+    let (status, body) = send_json(&app, "POST", &format!("/posts/{}/read", first), Some(&reader), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message"], "Post marked as read");
+    // Marking again is harmless
+    let (status, _) = send_json(&app, "POST", &format!("/posts/{}/read", first), Some(&reader), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, body) = send_json(&app, "GET", "/posts/unread", Some(&reader), None).await;
+    assert_eq!(unread(&body), vec![second.to_string()]);
+
+    // Read state shows up for the signed-in viewer only
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}?expand=author", first), Some(&reader), None).await;
+    assert_eq!(body["data"]["read"], true);
+    let (_, body) = send_json(&app, "GET", "/posts?expand=author", Some(&reader), None).await;
+    for post in body["data"].as_array().unwrap() {
+        assert_eq!(post["read"], post["id"] == first.as_str(), "{}", post);
+    }
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}?expand=author", first), None, None).await;
+    assert!(body["data"].get("read").is_none());
+
+    // Someone else's private post can't be marked read
+    let (status, body) = send_json(&app, "POST", &format!("/posts/{}/read", hidden), Some(&reader), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["data"].is_null());
+
+    let missing = uuid::Uuid::new_v4().to_string();
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/posts/read",
+        Some(&reader),
+        Some(serde_json::json!({ "ids": [second, hidden, missing] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["succeeded"], serde_json::json!([second]));
+    assert_eq!(body["data"]["failed"].as_array().unwrap().len(), 2);
+
+    let (_, body) = send_json(&app, "GET", "/posts/unread", Some(&reader), None).await;
+    assert!(unread(&body).is_empty());
+
+    // The author still has everything unread, their private post included
+    let (_, body) = send_json(&app, "GET", "/posts/unread", Some(&author), None).await;
+    assert_eq!(unread(&body).len(), 3);
+
+    let (status, _) = send_json(&app, "POST", "/posts/read", Some(&reader), Some(serde_json::json!({ "ids": [] }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_paged_listings_send_link_headers() {
+    async fn link(app: &Router, uri: &str, token: Option<&str>) -> Option<String> {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(token) = token {
+            builder = builder.header("authorization", format!("Bearer {}", token));
+        }
+        let response = app.clone().oneshot(builder.body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        response.headers().get("link").map(|value| value.to_str().unwrap().to_string())
+    }
+
+    let app = create_test_app_with(Settings { api_prefix: Some("/api".to_string()), ..Settings::default() }).await;
+    let credentials = serde_json::json!({ "email": "pager@example.com", "password": "TestPass123" });
+    let registration = serde_json::json!({ "name": "Pager", "email": "pager@example.com", "password": "TestPass123" });
+    send_json(&app, "POST", "/api/auth/register", None, Some(registration)).await;
+    let (_, body) = send_json(&app, "POST", "/api/auth/login", None, Some(credentials)).await;
+    let token = body["data"]["token"].as_str().unwrap().to_string();
+    for (i, tag) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+        let (status, _) = send_json(
+            &app,
+            "POST",
+            "/api/posts",
+            Some(&token),
+            Some(serde_json::json!({ "title": format!("Post {}", i), "content": "c", "tags": [tag], "status": "published" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    // Offset paging on GET /posts keeps the other parameters and the prefix
+    let header = link(&app, "/api/posts?sort=title&limit=2&offset=2", None).await.unwrap();
+    assert_eq!(
+        header,
+        "</api/posts?sort=title&offset=0&limit=2>; rel=\"first\", \
+         </api/posts?sort=title&offset=0&limit=2>; rel=\"prev\", \
+         </api/posts?sort=title&offset=4&limit=2>; rel=\"next\", \
+         </api/posts?sort=title&offset=4&limit=2>; rel=\"last\""
+    );
+
+    // The last page has no next
+    let header = link(&app, "/api/posts?limit=2&offset=4", None).await.unwrap();
+    assert!(!header.contains("rel=\"next\""), "{}", header);
+    assert!(header.contains("</api/posts?offset=2&limit=2>; rel=\"prev\""), "{}", header);
+
+    // Unpaged, the whole feed is one response with no links
+    assert_eq!(link(&app, "/api/posts", None).await, None);
+
+    // Page-numbered listings
+    let header = link(&app, "/api/tags?per_page=2", None).await.unwrap();
+    assert!(header.contains("</api/tags?page=2&per_page=2>; rel=\"next\""), "{}", header);
+    assert!(header.contains("</api/tags?page=3&per_page=2>; rel=\"last\""), "{}", header);
+    assert!(!header.contains("rel=\"prev\""), "{}", header);
+    let header = link(&app, "/api/posts/unread?page=2&per_page=2", Some(&token)).await.unwrap();
+    assert!(header.contains("</api/posts/unread?page=1&per_page=2>; rel=\"prev\""), "{}", header);
+    assert!(header.contains("</api/posts/unread?page=3&per_page=2>; rel=\"next\""), "{}", header);
+
+    // PAGINATION_LINKS=false turns them off
+    let app = create_test_app_with(Settings { pagination_links: false, ..Settings::default() }).await;
+    assert_eq!(link(&app, "/posts?limit=1", None).await, None);
+    assert_eq!(link(&app, "/tags", None).await, None);
+}
+
+#[tokio::test]
+async fn test_register_and_login_in_one_call() {
+    let app = create_test_app().await;
+    let credentials = serde_json::json!({ "name": "Quick", "email": "quick@example.com", "password": "TestPass123" });
+
+    let (status, body) = send_json(&app, "POST", "/auth/register-and-login", None, Some(credentials.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["token_type"], "Bearer");
+    assert_eq!(body["data"]["user"]["email"], "quick@example.com");
+    let token = body["data"]["token"].as_str().unwrap();
+
+    let (status, body) = send_json(&app, "GET", "/auth/profile", Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["email"], "quick@example.com");
+
+    // The account is a normal one that can log in again
+    login(&app, "quick@example.com").await;
+
+    let (status, _) = send_json(&app, "POST", "/auth/register-and-login", None, Some(credentials)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_login_with_unknown_email_still_verifies_a_password() {
+    let app = create_test_app().await;
+    register_and_login(&app, "Known", "known@example.com").await;
+    let attempt = |email: &'static str| {
+        let app = app.clone();
+        async move {
+            let started = std::time::Instant::now();
+            let (status, body) = send_json(
+                &app,
+                "POST",
+                "/auth/login",
+                None,
+                Some(serde_json::json!({ "email": email, "password": "WrongPass123" })),
+            )
+            .await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            assert_eq!(body["message"], "Invalid email or password");
+            started.elapsed()
+        }
+    };
+
+    // The first miss also hashes the dummy password, so it isn't timed
+    attempt("nobody@example.com").await;
+    let unknown = attempt("nobody@example.com").await;
+    let wrong_password = attempt("known@example.com").await;
+    assert!(unknown * 3 > wrong_password, "unknown email took {:?}, wrong password {:?}", unknown, wrong_password);
+}
+
+#[tokio::test]
+async fn test_post_with_full_comment_thread() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Threaded", "threaded@example.com").await;
+    let fan = register_and_login(&app, "Fan", "fan@example.com").await;
+    let (_, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Discuss", "content": "Thoughts?", "status": "published" })),
+    )
+    .await;
+    let post_id = body["data"]["id"].as_str().unwrap().to_string();
+
+    for i in 0..3 {
+        let (status, _) = send_json(
+            &app,
+            "POST",
+            &format!("/posts/{}/comments", post_id),
+            Some(&fan),
+            Some(serde_json::json!({ "content": format!("Comment {}", i) })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _) = send_json(&app, "POST", &format!("/posts/{}/like", post_id), Some(&fan), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send_json(&app, "GET", &format!("/posts/{}/full?limit=2", post_id), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let thread = &body["data"];
+    assert_eq!(thread["post"]["id"], post_id.as_str());
+    assert_eq!(thread["post"]["author"]["name"], "Threaded");
+    assert_eq!(thread["like_count"], 1);
+    assert_eq!(thread["comment_count"], 3);
+    let comments: Vec<&str> = thread["comments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|comment| comment["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(comments, ["Comment 0", "Comment 1"]);
+
+    let (_, body) = send_json(&app, "GET", &format!("/posts/{}/full?limit=2&offset=2", post_id), None, None).await;
+    assert_eq!(body["data"]["comments"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"]["comment_count"], 3);
+
+    let missing = format!("/posts/{}/full", uuid::Uuid::new_v4());
+    let (status, body) = send_json(&app, "GET", &missing, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["message"], "Post not found");
+}
+
+#[tokio::test]
+async fn test_options_reports_allowed_methods() {
+    async fn options(app: &Router, uri: &str) -> (StatusCode, Option<String>) {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().method("OPTIONS").uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let allow = response.headers().get("allow").map(|value| value.to_str().unwrap().to_string());
+        (response.status(), allow)
+    }
+
+    let app = create_test_app().await;
+    let post = format!("/posts/{}", uuid::Uuid::new_v4());
+
+    assert_eq!(
+        options(&app, &post).await,
+        (StatusCode::NO_CONTENT, Some("GET, HEAD, PUT, DELETE, OPTIONS".to_string()))
+    );
+    // Routes that need a token for everything else still answer without one
+    assert_eq!(
+        options(&app, "/auth/profile").await,
+        (StatusCode::NO_CONTENT, Some("GET, HEAD, PUT, DELETE, OPTIONS".to_string()))
+    );
+    assert_eq!(options(&app, "/nowhere").await.1, None);
+
+    // Preflights are still answered by the CORS layer
+    let preflight = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("OPTIONS")
+                .uri(&post)
+                .header("origin", "https://client.example.com")
+                .header("access-control-request-method", "PUT")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(preflight.status(), StatusCode::OK);
+    assert!(preflight.headers().contains_key("access-control-allow-methods"));
+
+    let app = create_test_app_with(Settings { options_allow: false, ..Settings::default() }).await;
+    assert_ne!(options(&app, &post).await.0, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_drafts_are_hidden_until_published() {
+    let app = create_test_app().await;
+    let writer = register_and_login(&app, "Writer", "writer@example.com").await;
+    let reader = register_and_login(&app, "Reader", "reader@example.com").await;
+    let titles = |body: &Value| -> Vec<String> {
+        body["data"].as_array().unwrap().iter().map(|post| post["title"].as_str().unwrap().to_string()).collect()
+    };
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&writer),
+        Some(serde_json::json!({ "title": "Work in progress", "content": "Not ready yet" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["status"], "draft");
+    assert!(body["data"].get("published_at").is_none());
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+    let uri = format!("/posts/{}", id);
+
+    // Only the owner sees a draft, in listings or by id
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert!(titles(&body).is_empty());
+    let (_, body) = send_json(&app, "GET", "/posts", Some(&reader), None).await;
+    assert!(titles(&body).is_empty());
+    let (_, body) = send_json(&app, "GET", &uri, Some(&reader), None).await;
+    assert_eq!(body["data"], Value::Null);
+    let (_, body) = send_json(&app, "GET", "/posts", Some(&writer), None).await;
+    assert_eq!(titles(&body), ["Work in progress"]);
+    let (_, body) = send_json(&app, "GET", &uri, Some(&writer), None).await;
+    assert_eq!(body["data"]["title"], "Work in progress");
+
+    // Nobody else can publish it
+    let (_, body) = send_json(&app, "POST", &format!("{}/publish", uri), Some(&reader), None).await;
+    assert_eq!(body["data"], Value::Null);
+
+    let (status, body) = send_json(&app, "POST", &format!("{}/publish", uri), Some(&writer), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["status"], "published");
+    assert!(body["data"]["published_at"].is_string());
+    assert_eq!(body["message"], "Post 'Work in progress' published");
+
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert_eq!(titles(&body), ["Work in progress"]);
+    let (_, body) = send_json(&app, "GET", &uri, Some(&reader), None).await;
+    assert_eq!(body["data"]["status"], "published");
+
+    // Unpublishing hides it again
+    let (_, body) = send_json(&app, "POST", &format!("{}/unpublish", uri), Some(&writer), None).await;
+    assert_eq!(body["data"]["status"], "draft");
+    assert!(body["data"].get("published_at").is_none());
+    let (_, body) = send_json(&app, "GET", "/posts", None, None).await;
+    assert!(titles(&body).is_empty());
+}
+
+#[tokio::test]
+async fn test_only_title_or_content_changes_mark_a_post_edited() {
+    let app = create_test_app().await;
+    let token = register_and_login(&app, "Editor", "editor@example.com").await;
+
+    let (_, body) = send_json(
+        &app,
+        "POST",
+        "/posts",
+        Some(&token),
+        Some(serde_json::json!({ "title": "Original", "content": "First draft", "status": "published" })),
+    )
+    .await;
+    assert_eq!(body["data"]["is_edited"], false);
+    let uri = format!("/posts/{}", body["data"]["id"].as_str().unwrap());
+
+    // Visibility, tags and an unchanged title are metadata only
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &uri,
+        Some(&token),
+        Some(serde_json::json!({ "visibility": "private", "tags": ["notes"], "title": "Original" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["visibility"], "private");
+    assert_eq!(body["data"]["is_edited"], false);
+    assert!(body["data"].get("edited_at").is_none());
+
+    let (status, body) = send_json(&app, "PUT", &uri, Some(&token), Some(serde_json::json!({ "content": "Second draft" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["is_edited"], true);
+    let edited_at = body["data"]["edited_at"].as_str().unwrap().to_string();
+
+    // A later metadata change keeps the edit time
+    send_json(&app, "PUT", &uri, Some(&token), Some(serde_json::json!({ "visibility": "public" }))).await;
+    let (_, body) = send_json(&app, "GET", &uri, Some(&token), None).await;
+    assert_eq!(body["data"]["edited_at"], edited_at.as_str());
+    let (_, body) = send_json(&app, "GET", &format!("{}?expand=author", uri), Some(&token), None).await;
+    assert_eq!(body["data"]["is_edited"], true);
+    assert_eq!(body["data"]["edited_at"], edited_at.as_str());
+
+    // Someone else's tags-only update writes nothing
+    let other = register_and_login(&app, "Other", "other@example.com").await;
+    let (_, body) = send_json(&app, "PUT", &uri, Some(&other), Some(serde_json::json!({ "tags": ["hijacked"] }))).await;
+    assert!(body["data"].is_null());
+    let (_, body) = send_json(&app, "GET", &uri, Some(&token), None).await;
+    assert_eq!(body["data"]["tags"], serde_json::json!(["notes"]));
+    assert_eq!(body["data"]["edited_at"], edited_at.as_str());
+}
+
+#[tokio::test]
+async fn test_trusted_api_keys_skip_rate_limits() {
+    let app = create_test_app_with(Settings {
+        email_check_rate_limit: 2,
+        trusted_api_keys: vec!["service-key".to_string()],
+        ..Settings::default()
+    })
+    .await;
+    let check = |key: Option<&'static str>| {
+        let app = app.clone();
+        async move {
+            let mut request = Request::builder().uri("/auth/email-available?email=free@example.com");
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+        }
+    };
+
+    assert_eq!(check(None).await, StatusCode::OK);
+    assert_eq!(check(None).await, StatusCode::OK);
+    assert_eq!(check(None).await, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(check(Some("wrong-key")).await, StatusCode::TOO_MANY_REQUESTS);
+
+    // The service caller shares the client's (exhausted) bucket but is never throttled
+    for _ in 0..5 {
+        assert_eq!(check(Some("service-key")).await, StatusCode::OK);
+    }
+}